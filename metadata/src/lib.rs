@@ -54,17 +54,27 @@ impl From<io::Error> for ErrorKind {
     }
 }
 
-pub fn find_manifest(mut current: &Path) -> Option<PathBuf> {
+pub fn find_manifest(current: &Path) -> Option<PathBuf> {
+    find_manifest_with(current, |p| p.is_dir(), |p| p.exists())
+}
+
+/// Like `find_manifest`, but queries the file system with `is_dir` and `exists`,
+/// so that a virtual file tree can be searched
+pub fn find_manifest_with<D, E>(mut current: &Path, is_dir: D, exists: E) -> Option<PathBuf>
+where
+    D: Fn(&Path) -> bool,
+    E: Fn(&Path) -> bool,
+{
     let file = "Cargo.toml";
-    if current.is_dir() {
+    if is_dir(current) {
         let manifest = current.join(file);
-        if manifest.exists() {
+        if exists(&manifest) {
             return Some(manifest);
         }
     }
     while let Some(parent) = current.parent() {
         let manifest = parent.join(file);
-        if manifest.exists() {
+        if exists(&manifest) {
             return Some(manifest);
         }
         current = parent;
@@ -72,11 +82,49 @@ pub fn find_manifest(mut current: &Path) -> Option<PathBuf> {
     None
 }
 
+/// Features to resolve the package graph with
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Features {
+    /// `--all-features`
+    All,
+    /// The default features and the listed ones
+    Default(Vec<String>),
+    /// Only the listed features (`--no-default-features`)
+    NoDefault(Vec<String>),
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features::All
+    }
+}
+
 pub fn run(manifest_path: &Path, frozen: bool) -> Result<Metadata, ErrorKind> {
+    run_with_features(manifest_path, frozen, &Features::All)
+}
+
+pub fn run_with_features(
+    manifest_path: &Path,
+    frozen: bool,
+    features: &Features,
+) -> Result<Metadata, ErrorKind> {
     let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
     let mut cmd = Command::new(cargo);
     cmd.arg("metadata");
-    cmd.arg("--all-features");
+    match features {
+        Features::All => {
+            cmd.arg("--all-features");
+        }
+        Features::Default(list) | Features::NoDefault(list) => {
+            if let Features::NoDefault(_) = features {
+                cmd.arg("--no-default-features");
+            }
+            if !list.is_empty() {
+                cmd.arg("--features");
+                cmd.arg(list.join(" "));
+            }
+        }
+    }
     cmd.args(&["--format-version", "1"]);
     cmd.args(&["--color", "never"]);
     cmd.arg("--manifest-path");
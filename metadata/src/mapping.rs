@@ -9,6 +9,7 @@ pub struct PackageMap {
     manifest_to_idx: HashMap<PathBuf, PackageIdx>,
     id_to_idx: HashMap<PackageId, PackageIdx>,
     packages: Vec<PackageInner>,
+    target_directory: Option<PathBuf>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -35,6 +36,7 @@ struct PackageInner {
     deps: Vec<(InternedString, PathBuf)>,
     lib: Option<Target>,
     id: PackageId,
+    features: Option<Vec<InternedString>>,
 }
 
 impl PackageInner {
@@ -44,6 +46,7 @@ impl PackageInner {
             deps: Vec::new(),
             id,
             lib,
+            features: None,
         }
     }
 }
@@ -54,9 +57,14 @@ pub struct PackageIdx(usize);
 impl PackageMap {
     pub fn from_metadata(meta: Metadata) -> Self {
         let Metadata {
-            packages, resolve, ..
+            packages,
+            resolve,
+            target_directory,
+            ..
         } = meta;
-        PackageMap::new(packages, resolve)
+        let mut map = PackageMap::new(packages, resolve);
+        map.target_directory = Some(target_directory);
+        map
     }
     pub fn new(packages: Vec<Package>, resolve: Option<Resolve>) -> Self {
         let mut manifest_to_idx = HashMap::new();
@@ -82,8 +90,12 @@ impl PackageMap {
             manifest_to_idx,
             id_to_idx,
             packages: inner,
+            target_directory: None,
         }
     }
+    pub fn target_directory(&self) -> Option<&Path> {
+        self.target_directory.as_ref().map(AsRef::as_ref)
+    }
     pub fn ids<'a>(&'a self) -> impl 'a + Iterator<Item = PackageId> {
         self.packages.iter().map(|p| p.id)
     }
@@ -108,6 +120,10 @@ impl PackageMap {
     pub fn get_dependencies(&self, idx: PackageIdx) -> &[(InternedString, PathBuf)] {
         self.packages[idx.0].deps.as_ref()
     }
+    /// Features enabled for the package, if the dependency graph was resolved
+    pub fn get_features(&self, idx: PackageIdx) -> Option<&[InternedString]> {
+        self.packages[idx.0].features.as_ref().map(AsRef::as_ref)
+    }
     pub fn get_src_path_from_libname(&self, id: PackageIdx, s: &str) -> Option<&Path> {
         let deps = self.get_dependencies(id);
         let query_str = InternedString::new_if_exists(s)?;
@@ -134,6 +150,7 @@ fn construct_deps(
             })
             .collect();
         res[idx.0].deps.extend(deps);
+        res[idx.0].features = Some(node.features);
     }
     Some(())
 }
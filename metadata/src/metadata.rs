@@ -37,6 +37,9 @@ pub struct Resolve {
 pub struct ResolveNode {
     pub id: PackageId,
     pub dependencies: Vec<PackageId>,
+    /// Features enabled for this package
+    #[serde(default)]
+    pub features: Vec<InternedString>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
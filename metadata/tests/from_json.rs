@@ -17,6 +17,11 @@ fn full() {
     assert!(pkg_map
         .get_src_path_from_libname(pkg_map.id_to_idx(regex).unwrap(), "memchr")
         .is_some());
+    let rand_core = pkg_map.ids().find(|id| id.name() == "rand_core").unwrap();
+    let features = pkg_map
+        .get_features(pkg_map.id_to_idx(rand_core).unwrap())
+        .unwrap();
+    assert!(features.iter().any(|f| f.as_str() == "std"));
 }
 
 #[test]
@@ -1,7 +1,7 @@
 extern crate racer_cargo_metadata;
 
-use racer_cargo_metadata::find_manifest;
-use std::path::PathBuf;
+use racer_cargo_metadata::{find_manifest, find_manifest_with};
+use std::path::{Path, PathBuf};
 
 fn manifest_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -39,3 +39,18 @@ fn same_dir() {
         manifest_dir().join("Cargo.toml")
     );
 }
+
+#[test]
+fn virtual_tree() {
+    let root = Path::new("/virtual/project");
+    let is_dir = |p: &Path| p == root || p == root.join("src");
+    let exists = |p: &Path| is_dir(p) || p == root.join("Cargo.toml");
+    assert_eq!(
+        find_manifest_with(&root.join("src"), is_dir, exists).unwrap(),
+        root.join("Cargo.toml")
+    );
+    assert_eq!(
+        find_manifest_with(Path::new("/virtual/other"), is_dir, exists),
+        None
+    );
+}
@@ -1,15 +1,17 @@
 use crate::ast_types::Path as RacerPath;
 use crate::ast_types::{
-    self, GenericsArgs, ImplHeader, Pat, PathAlias, PathAliasKind, TraitBounds, Ty,
+    self, GenericsArgs, ImplHeader, Pat, PathAlias, PathAliasKind, PathSegment, TraitBounds, Ty,
 };
 use crate::core::{self, BytePos, ByteRange, Match, MatchType, Scope, Session, SessionExt};
 use crate::nameres;
 use crate::typeinf;
 
+use std::cell::RefCell;
 use std::path::Path;
 use std::rc::Rc;
 
 use rustc_ast::ast::{self, ExprKind, FnRetTy, ItemKind, PatKind, UseTree, UseTreeKind};
+use rustc_ast::ptr::P;
 use rustc_ast::{self, visit};
 use rustc_data_structures::sync::Lrc;
 use rustc_errors::emitter::Emitter;
@@ -260,21 +262,48 @@ fn destructure_pattern_to_ty(
     ty: &Ty,
     scope: &Scope,
     session: &Session<'_>,
+) -> Option<Ty> {
+    destructure_pattern_to_ty_with_mode(pat, point, ty, None, scope, session)
+}
+
+// `mode` is the default binding mode: `None` for 'move', `Some(mutability)` for 'ref'
+fn destructure_pattern_to_ty_with_mode(
+    pat: &ast::Pat,
+    point: BytePos,
+    ty: &Ty,
+    mode: Option<ast::Mutability>,
+    scope: &Scope,
+    session: &Session<'_>,
 ) -> Option<Ty> {
     debug!(
-        "destructure_pattern_to_ty point {:?} ty {:?} pat: {:?}",
-        point, ty, pat.kind
+        "destructure_pattern_to_ty point {:?} ty {:?} pat: {:?} mode: {:?}",
+        point, ty, pat.kind, mode
     );
+    // match ergonomics: non-reference patterns see through references
+    let (ty, mode) = match pat.kind {
+        PatKind::Tuple(_) | PatKind::TupleStruct(..) | PatKind::Struct(..) | PatKind::Slice(_) => {
+            typeinf::peel_refs_for_pattern(ty.clone(), mode)
+        }
+        _ => (ty.clone(), mode),
+    };
+    let ty = &ty;
+    let destructure = |pat: &ast::Pat, ty: &Ty, mode: Option<ast::Mutability>| {
+        destructure_pattern_to_ty_with_mode(pat, point, ty, mode, scope, session)
+    };
     match pat.kind {
-        PatKind::Ident(_, ref spannedident, _) => {
+        PatKind::Ident(bm, ref spannedident, ref sub) => {
             if point_is_in_span(point, &spannedident.span) {
                 debug!("destructure_pattern_to_ty matched an ident!");
-                Some(ty.clone())
+                Some(typeinf::type_of_binding(ty.clone(), bm, mode))
+            } else if let Some(ref sub) = sub {
+                // x @ Some(y)
+                destructure(sub, ty, mode)
             } else {
-                panic!(
+                debug!(
                     "Expecting the point to be in the patident span. pt: {:?}",
                     point
                 );
+                None
             }
         }
         PatKind::Tuple(ref tuple_elements) => match *ty {
@@ -283,14 +312,54 @@ fn destructure_pattern_to_ty(
                     if !point_is_in_span(point, &p.span) {
                         continue;
                     }
-                    if let Some(ref ty) = typeelems[i] {
-                        return destructure_pattern_to_ty(p, point, ty, scope, session);
+                    if let Some(Some(ref ty)) = typeelems.get(i) {
+                        return destructure(p, ty, mode);
                     }
                 }
                 None
             }
-            _ => panic!("Expecting TyTuple"),
+            _ => {
+                debug!("Expecting TyTuple, but got {:?}", ty);
+                None
+            }
+        },
+        // `&pat` resets the default binding mode to 'move'
+        PatKind::Ref(ref inner, _) => match *ty {
+            Ty::RefPtr(ref ty, _) => destructure(inner, ty, None),
+            _ => destructure(inner, ty, None),
         },
+        PatKind::Box(ref inner) => {
+            let boxed = match path_to_match(ty.clone(), session)? {
+                Ty::Match(ref m) if m.matchstr == "Box" => m.resolved_generics().next()?.to_owned(),
+                _ => return None,
+            };
+            destructure(inner, &boxed, mode)
+        }
+        PatKind::Slice(ref elements) => {
+            let elem = match *ty {
+                Ty::Array(ref elem, _) | Ty::Slice(ref elem) => elem,
+                _ => return None,
+            };
+            let p = elements.iter().find(|p| point_is_in_span(point, &p.span))?;
+            // rest @ ..
+            if let PatKind::Ident(bm, ref ident, Some(ref sub)) = p.kind {
+                if let PatKind::Rest = sub.kind {
+                    if !point_is_in_span(point, &ident.span) {
+                        return None;
+                    }
+                    let rest = Ty::Slice(elem.clone());
+                    return Some(typeinf::type_of_binding(rest, bm, mode));
+                }
+            }
+            destructure(p, elem, mode)
+        }
+        PatKind::Or(ref alternatives) => {
+            let p = alternatives
+                .iter()
+                .find(|p| point_is_in_span(point, &p.span))?;
+            destructure(p, ty, mode)
+        }
+        PatKind::Paren(ref inner) => destructure(inner, ty, mode),
         PatKind::TupleStruct(ref path, ref children) => {
             let m = resolve_ast_path(path, &scope.filepath, scope.point, session)?;
             let contextty = path_to_match(ty.clone(), session);
@@ -309,7 +378,7 @@ fn destructure_pattern_to_ty(
                                 path_to_match(ty, session)
                             }
                         })
-                        .and_then(|ty| destructure_pattern_to_ty(p, point, &ty, scope, session));
+                        .and_then(|ty| destructure(p, &ty, mode));
                 }
             }
             None
@@ -331,9 +400,7 @@ fn destructure_pattern_to_ty(
                                 path_to_match(ty, session)
                             }
                         })
-                        .and_then(|ty| {
-                            destructure_pattern_to_ty(&child.pat, point, &ty, scope, session)
-                        });
+                        .and_then(|ty| destructure(&child.pat, &ty, mode));
                 }
             }
             None
@@ -345,6 +412,16 @@ fn destructure_pattern_to_ty(
     }
 }
 
+// Like `path_to_match`, but keeps references
+fn path_to_match_keeping_ref(ty: Ty, session: &Session<'_>) -> Option<Ty> {
+    match ty {
+        Ty::RefPtr(ty, mutbl) => {
+            path_to_match_keeping_ref(*ty, session).map(|ty| Ty::RefPtr(Box::new(ty), mutbl))
+        }
+        _ => path_to_match(ty, session),
+    }
+}
+
 struct LetTypeVisitor<'c, 's> {
     scope: Scope,
     session: &'s Session<'c>,
@@ -368,7 +445,7 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for LetTypeVisitor<'c, 's> {
             .and_then(|ty| {
                 destructure_pattern_to_ty(&local.pat, self.pos, &ty, &self.scope, self.session)
             })
-            .and_then(|ty| path_to_match(ty, self.session));
+            .and_then(|ty| path_to_match_keeping_ref(ty, self.session));
     }
 }
 
@@ -400,7 +477,7 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for MatchTypeVisitor<'c, 's> {
                     .and_then(|ty| {
                         destructure_pattern_to_ty(&arm.pat, self.pos, ty, &self.scope, self.session)
                     })
-                    .and_then(|ty| path_to_match(ty, self.session));
+                    .and_then(|ty| path_to_match_keeping_ref(ty, self.session));
             }
         }
     }
@@ -411,9 +488,19 @@ fn resolve_ast_path(
     filepath: &Path,
     pos: BytePos,
     session: &Session<'_>,
+) -> Option<Match> {
+    resolve_qualified_ast_path(None, path, filepath, pos, session)
+}
+
+fn resolve_qualified_ast_path(
+    qself: Option<&ast::QSelf>,
+    path: &ast::Path,
+    filepath: &Path,
+    pos: BytePos,
+    session: &Session<'_>,
 ) -> Option<Match> {
     let scope = Scope::new(filepath.to_owned(), pos);
-    let path = RacerPath::from_ast(path, &scope);
+    let path = RacerPath::from_qualified_ast(qself, path, &scope);
     nameres::resolve_path_with_primitive(
         &path,
         filepath,
@@ -452,16 +539,13 @@ pub(crate) fn find_type_match(
         session,
     )
     .into_iter()
-    .nth(0)
-    .and_then(|m| match m.mtype {
-        MatchType::Type => typeinf::get_type_of_typedef(&m, session),
-        _ => Some(m),
-    })?;
-    // TODO: 'Type' support
-    // if res is Enum/Struct and has a generic type paramter, let's resolve it.
-    for (param, typ) in res.generics_mut().zip(path.generic_types()) {
-        param.resolve(typ.to_owned());
+    .nth(0)?;
+    let types: Vec<_> = path.generic_types().cloned().collect();
+    if res.mtype == MatchType::Type {
+        return typeinf::get_type_of_typedef_with_generics(&res, &types, session);
     }
+    // if res is Enum/Struct and has a generic type paramter, let's resolve it.
+    res.resolve_generics(&types);
     Some(res)
 }
 
@@ -490,6 +574,32 @@ impl<'c: 's, 's> ExprTypeVisitor<'c, 's> {
             result: None,
         }
     }
+    /// Types of arguments passed to a function, used to infer its type parameters.
+    /// For closures, it's the type of the closure body.
+    fn call_arg_types(&self, args: &[P<ast::Expr>]) -> Vec<Option<Ty>> {
+        args.iter()
+            .map(|arg| {
+                let mut visitor = self.same_scope();
+                match arg.kind {
+                    ExprKind::Closure(_, asyncness, _, _, ref body, _) => {
+                        visitor.visit_expr(body);
+                        if asyncness.is_async() {
+                            let output = visitor.result.take().unwrap_or(Ty::Unsupported);
+                            return Some(Ty::Future(Box::new(output), self.scope.clone()));
+                        }
+                    }
+                    _ => visitor.visit_expr(arg),
+                }
+                visitor.result
+            })
+            .collect()
+    }
+}
+
+/// Get types given by turbofish, e.g. `u32` for `parse::<u32>`
+fn turbofish_types(seg: Option<&ast::PathSegment>, scope: &Scope) -> Vec<Ty> {
+    seg.map(|seg| PathSegment::from_ast(seg, scope).generics)
+        .unwrap_or_default()
 }
 
 impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
@@ -500,12 +610,29 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
         );
         //walk_expr(self, ex, e)
         match expr.kind {
-            ExprKind::Unary(_, ref expr) | ExprKind::AddrOf(_, _, ref expr) => {
+            ExprKind::Unary(op, ref expr) => {
+                self.visit_expr(expr);
+                let session = self.session;
+                self.result = self.result.take().and_then(|ty| match op {
+                    ast::UnOp::Deref => typeinf::get_type_of_deref(ty, session),
+                    ast::UnOp::Not | ast::UnOp::Neg => {
+                        let base_type = match ty.dereference() {
+                            Ty::Match(m) => m,
+                            Ty::PathSearch(ps) => ps.resolve_as_match(session)?,
+                            _ => return None,
+                        };
+                        nameres::resolve_unary_expr_type(&base_type, op, session)
+                    }
+                });
+            }
+            ExprKind::AddrOf(_, mutbl, ref expr) => {
                 self.visit_expr(expr);
+                self.result = self.result.take().map(|ty| Ty::RefPtr(Box::new(ty), mutbl));
             }
-            ExprKind::Path(_, ref path) => {
+            ExprKind::Path(ref qself, ref path) => {
                 let source_map::BytePos(lo) = path.span.lo();
-                self.result = resolve_ast_path(
+                self.result = resolve_qualified_ast_path(
+                    qself.as_ref(),
                     path,
                     &self.scope.filepath,
                     self.scope.point + lo.into(),
@@ -519,15 +646,35 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
             }
             ExprKind::Call(ref callee_expression, ref caller_expr) => {
                 self.visit_expr(callee_expression);
+                let turbofish = match callee_expression.kind {
+                    ExprKind::Path(_, ref path) => {
+                        turbofish_types(path.segments.last(), &self.scope)
+                    }
+                    _ => Vec::new(),
+                };
                 self.result = self.result.take().and_then(|m| {
                     if let Ty::Match(mut m) = m {
                         match m.mtype {
                             MatchType::Function => {
-                                typeinf::get_return_type_of_function(&m, &m, self.session)
-                                    .and_then(|ty| path_to_match(ty, self.session))
+                                let args = || self.call_arg_types(caller_expr);
+                                typeinf::get_return_type_of_function_call(
+                                    &m,
+                                    &m,
+                                    &turbofish,
+                                    &args,
+                                    self.session,
+                                )
+                                .and_then(|ty| path_to_match(ty, self.session))
                             }
                             MatchType::Method(ref gen) => {
-                                let mut return_ty = typeinf::get_return_type_of_function(&m, &m, self.session);
+                                let args = || self.call_arg_types(caller_expr);
+                                let mut return_ty = typeinf::get_return_type_of_function_call(
+                                    &m,
+                                    &m,
+                                    &turbofish,
+                                    &args,
+                                    self.session,
+                                );
                                 // Account for already resolved generics if the return type is Self
                                 // (in which case we return bare type as found in the `impl` header)
                                 if let (Some(Ty::Match(ref mut m)), Some(gen)) = (&mut return_ty, gen) {
@@ -620,6 +767,19 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                 let objexpr = &arguments[0];
                 self.visit_expr(objexpr);
                 let result = self.result.take();
+                let turbofish = turbofish_types(Some(method_def), &self.scope);
+                let receiver = result.clone();
+                let arg_types = RefCell::new(None);
+                let args = || {
+                    arg_types
+                        .borrow_mut()
+                        .get_or_insert_with(|| {
+                            let mut args = self.call_arg_types(&arguments[1..]);
+                            args.insert(0, receiver.clone());
+                            args
+                        })
+                        .clone()
+                };
                 let get_method_output_ty = |contextm: Match| {
                     let matching_methods = nameres::search_for_fields_and_methods(
                         contextm.clone(),
@@ -631,9 +791,11 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                     matching_methods
                         .into_iter()
                         .filter_map(|method| {
-                            let ty = typeinf::get_return_type_of_function(
+                            let ty = typeinf::get_return_type_of_function_call(
                                 &method,
                                 &contextm,
+                                &turbofish,
+                                &args,
                                 self.session,
                             )?;
                             path_to_match_including_generics(
@@ -667,9 +829,16 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                         },
                     )
                 };
-                self.result = result.and_then(|ty| {
-                    ty.resolve_as_field_match(self.session)
-                        .and_then(match_to_field_ty)
+                self.result = result.and_then(|ty| match ty.dereference() {
+                    // tuple index like `tuple.0`
+                    Ty::Tuple(elems) => fieldname
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|i| elems.into_iter().nth(i))
+                        .flatten(),
+                    ty => ty
+                        .resolve_as_field_match(self.session)
+                        .and_then(match_to_field_ty),
                 });
             }
             ExprKind::Tup(ref exprs) => {
@@ -730,6 +899,22 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                     visit::walk_stmt(self, stmt);
                 }
             }
+            ExprKind::Async(_, _, ref block) => {
+                debug!("async block expr");
+                if let Some(stmt) = block.stmts.last() {
+                    visit::walk_stmt(self, stmt);
+                }
+                let output = self.result.take().unwrap_or(Ty::Unsupported);
+                self.result = Some(Ty::Future(Box::new(output), self.scope.clone()));
+            }
+            ExprKind::Await(ref expr) => {
+                self.visit_expr(expr);
+                let session = self.session;
+                self.result = self
+                    .result
+                    .take()
+                    .and_then(|ty| typeinf::get_future_output(ty, session));
+            }
             ExprKind::Index(ref body, ref _index) => {
                 self.visit_expr(body);
                 // TODO(kngwyu) now we don't have support for literal so don't parse index
@@ -754,27 +939,66 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                     self.result = Some(Ty::Array(Box::new(Ty::Unsupported), String::new()));
                 }
             }
+            ExprKind::Repeat(ref expr, ref count) => {
+                self.visit_expr(expr);
+                let len = match count.value.kind {
+                    ExprKind::Lit(ref lit) => lit.token.symbol.to_string(),
+                    _ => String::new(),
+                };
+                let elem = self.result.take().unwrap_or(Ty::Unsupported);
+                self.result = Some(Ty::Array(Box::new(elem), len));
+            }
+            ExprKind::Cast(_, ref ty) => {
+                self.result = Ty::from_ast(ty, &self.scope);
+            }
+            ExprKind::Range(ref start, ref end, limits) => {
+                let range_name = match (start, end, limits) {
+                    (Some(_), Some(_), ast::RangeLimits::HalfOpen) => "Range",
+                    (Some(_), None, _) => "RangeFrom",
+                    (None, Some(_), ast::RangeLimits::HalfOpen) => "RangeTo",
+                    (None, None, _) => "RangeFull",
+                    (Some(_), Some(_), ast::RangeLimits::Closed) => "RangeInclusive",
+                    (None, Some(_), ast::RangeLimits::Closed) => "RangeToInclusive",
+                };
+                // the element type is taken from whichever bound we can resolve, trying
+                // literals last, as the type of `0` in `0..n` is inferred from `n`
+                let is_literal = |bound: &&P<ast::Expr>| match bound.kind {
+                    ExprKind::Lit(_) => true,
+                    ExprKind::Unary(_, ref expr) => matches!(expr.kind, ExprKind::Lit(_)),
+                    _ => false,
+                };
+                let (literals, others): (Vec<_>, Vec<_>) =
+                    start.iter().chain(end.iter()).partition(is_literal);
+                let mut elem = None;
+                for bound in others.into_iter().chain(literals) {
+                    self.visit_expr(bound);
+                    elem = self.result.take();
+                    if elem.is_some() {
+                        break;
+                    }
+                }
+                let path = RacerPath::from_iter(
+                    true,
+                    ["std", "ops", range_name].iter().map(|s| s.to_string()),
+                );
+                self.result =
+                    find_type_match(&path, &self.scope.filepath, self.scope.point, self.session)
+                        .map(|mut m| {
+                            if let (Some(param), Some(elem)) = (m.generics_mut().next(), elem) {
+                                param.resolve(elem);
+                            }
+                            Ty::Match(m)
+                        });
+            }
             ExprKind::MacCall(ref m) => {
                 if let Some(name) = m.path.segments.last().map(|seg| seg.ident) {
-                    // use some ad-hoc rules
-                    if name.as_str() == "vec" {
-                        let path = RacerPath::from_iter(
-                            true,
-                            ["std", "vec", "Vec"].iter().map(|s| s.to_string()),
-                        );
-                        self.result = find_type_match(
-                            &path,
-                            &self.scope.filepath,
-                            self.scope.point,
-                            self.session,
-                        )
-                        .map(Ty::Match);
-                    }
+                    self.result =
+                        typeinf::get_type_of_macro_call(&name.as_str(), &self.scope, self.session);
                 }
             }
             ExprKind::Binary(bin, ref left, ref right) => {
                 self.visit_expr(left);
-                let type_match = match self.result.take() {
+                let type_match = match self.result.take().map(Ty::dereference) {
                     Some(Ty::Match(m)) => m,
                     Some(Ty::PathSearch(ps)) => match ps.resolve_as_match(self.session) {
                         Some(m) => m,
@@ -788,7 +1012,7 @@ impl<'c, 's, 'ast> visit::Visitor<'ast> for ExprTypeVisitor<'c, 's> {
                 };
 
                 self.visit_expr(right);
-                let right_expr_type = match self.result.take() {
+                let right_expr_type = match self.result.take().map(Ty::dereference) {
                     Some(Ty::Match(m)) => Some(m.matchstr),
                     Some(Ty::PathSearch(ps)) => {
                         ps.resolve_as_match(self.session).map(|m| m.matchstr)
@@ -837,7 +1061,11 @@ fn find_type_match_including_generics(
     session: &Session<'_>,
 ) -> Option<Ty> {
     assert_eq!(&structm.filepath, filepath);
-    let fieldtypepath = match fieldtype {
+    let generics = match &structm.mtype {
+        MatchType::Struct(gen) => gen,
+        _ => return None,
+    };
+    let mut fieldtypepath = match fieldtype {
         Ty::PathSearch(paths) => paths.path,
         Ty::RefPtr(ty, _) => match ty.dereference() {
             Ty::PathSearch(paths) => paths.path,
@@ -846,14 +1074,14 @@ fn find_type_match_including_generics(
         },
         // already resolved
         Ty::Match(m) => return Some(Ty::Match(m)),
+        // elements are resolved when they are accessed, e.g. by `field.0`
+        Ty::Tuple(_) | Ty::Array(_, _) | Ty::Slice(_) => {
+            return Some(fieldtype.replace_by_resolved_generics_deep(generics));
+        }
         _ => {
             return None;
         }
     };
-    let generics = match &structm.mtype {
-        MatchType::Struct(gen) => gen,
-        _ => return None,
-    };
     if fieldtypepath.segments.len() == 1 {
         // could be a generic arg! - try and resolve it
         if let Some((_, param)) = generics.search_param_by_path(&fieldtypepath) {
@@ -865,6 +1093,14 @@ fn find_type_match_including_generics(
             return Some(Ty::Match(m));
         }
     }
+    // substitute type parameters nested in the field type, like `T` in `Vec<T>`
+    for seg in fieldtypepath.segments.iter_mut() {
+        seg.generics = seg
+            .generics
+            .drain(..)
+            .map(|ty| ty.replace_by_resolved_generics_deep(generics))
+            .collect();
+    }
 
     find_type_match(&fieldtypepath, filepath, pos, session).map(Ty::Match)
 }
@@ -892,6 +1128,8 @@ impl<'ast> visit::Visitor<'ast> for StructVisitor {
 pub struct TypeVisitor<'s> {
     pub name: Option<String>,
     pub type_: Option<Ty>,
+    /// type parameters of the alias, like `T` in `type Map<T> = HashMap<String, T>;`
+    pub generics: GenericsArgs,
     scope: &'s Scope,
 }
 
@@ -901,6 +1139,11 @@ impl<'ast, 's> visit::Visitor<'ast> for TypeVisitor<'s> {
             if let Some(ref ty) = ty_kind.3 {
                 self.name = Some(item.ident.name.to_string());
                 self.type_ = Ty::from_ast(&ty, self.scope);
+                self.generics = GenericsArgs::from_generics(
+                    &ty_kind.1,
+                    &self.scope.filepath,
+                    self.scope.point.0 as i32,
+                );
                 debug!("typevisitor type is {:?}", self.type_);
             }
         }
@@ -985,6 +1228,7 @@ impl<'ast> visit::Visitor<'ast> for ExternCrateVisitor {
 struct GenericsVisitor<P> {
     result: GenericsArgs,
     filepath: P,
+    offset: i32,
 }
 
 impl<'ast, P: AsRef<Path>> visit::Visitor<'ast> for GenericsVisitor<P> {
@@ -993,7 +1237,8 @@ impl<'ast, P: AsRef<Path>> visit::Visitor<'ast> for GenericsVisitor<P> {
         if !self.result.0.is_empty() {
             warn!("[visit_generics] called for multiple generics!");
         }
-        self.result.extend(GenericsArgs::from_generics(g, path, 0));
+        self.result
+            .extend(GenericsArgs::from_generics(g, path, self.offset));
     }
 }
 
@@ -1109,9 +1354,14 @@ pub fn parse_inherited_traits<P: AsRef<Path>>(
 }
 
 pub fn parse_generics(s: String, filepath: &Path) -> GenericsArgs {
+    parse_generics_with_offset(s, filepath, 0)
+}
+
+pub(crate) fn parse_generics_with_offset(s: String, filepath: &Path, offset: i32) -> GenericsArgs {
     let mut v = GenericsVisitor {
         result: GenericsArgs::default(),
         filepath: filepath,
+        offset,
     };
     with_stmt(s, |stmt| visit::walk_stmt(&mut v, stmt));
     v.result
@@ -1121,6 +1371,7 @@ pub fn parse_type<'s>(s: String, scope: &'s Scope) -> TypeVisitor<'s> {
     let mut v = TypeVisitor {
         name: None,
         type_: None,
+        generics: GenericsArgs::default(),
         scope,
     };
     with_stmt(s, |stmt| visit::walk_stmt(&mut v, stmt));
@@ -1282,11 +1533,23 @@ where
 {
     fn visit_item(&mut self, item: &ast::Item) {
         if let ItemKind::Trait(ref trait_kind) = item.kind {
-            self.result = Some(TraitBounds::from_generic_bounds(
-                &trait_kind.3,
-                &self.file_path,
-                self.offset,
-            ));
+            let mut bounds =
+                TraitBounds::from_generic_bounds(&trait_kind.3, &self.file_path, self.offset);
+            // `where Self: Trait` is the same as `trait Foo: Trait`
+            for pred in trait_kind.2.where_clause.predicates.iter() {
+                if let ast::WherePredicate::BoundPredicate(ref bound) = pred {
+                    if let ast::TyKind::Path(None, ref path) = bound.bounded_ty.kind {
+                        if path.segments.len() == 1 && &*path.segments[0].ident.as_str() == "Self" {
+                            bounds.extend(TraitBounds::from_generic_bounds(
+                                &bound.bounds,
+                                &self.file_path,
+                                self.offset,
+                            ));
+                        }
+                    }
+                }
+            }
+            self.result = Some(bounds);
         }
     }
 }
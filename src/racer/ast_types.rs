@@ -1,7 +1,7 @@
 //! type conversion between racer types and libsyntax types
 use super::ast::find_type_match;
 use crate::core::{
-    self, BytePos, ByteRange, Match, MatchType, Scope, SearchType, Session, SessionExt,
+    self, BytePos, ByteRange, Match, MatchType, Scope, SearchType, Session, SessionExt, Visibility,
 };
 use crate::matchers::ImportInfo;
 use crate::nameres;
@@ -62,6 +62,8 @@ pub enum Ty {
     Never,
     Default,
     Unsupported,
+    /// A const generic argument, like `32` in `Buf<32>`
+    Const(String),
 }
 
 impl Ty {
@@ -76,6 +78,47 @@ impl Ty {
         }
         ty.wrap_by_ref(deref_cnt)
     }
+    /// Replaces resolved type parameters, including ones nested in paths like `Vec<T>`
+    pub(crate) fn replace_by_resolved_generics_deep(self, gen: &GenericsArgs) -> Self {
+        match self {
+            Ty::PathSearch(mut paths) => {
+                if let Some((_, param)) = gen.search_param_by_path(&paths.path) {
+                    if let Some(resolved) = param.resolved() {
+                        return resolved.to_owned();
+                    }
+                    return Ty::PathSearch(paths);
+                }
+                for seg in paths.path.segments.iter_mut() {
+                    seg.generics = seg
+                        .generics
+                        .drain(..)
+                        .map(|ty| ty.replace_by_resolved_generics_deep(gen))
+                        .collect();
+                    seg.output = seg
+                        .output
+                        .take()
+                        .map(|ty| ty.replace_by_resolved_generics_deep(gen));
+                }
+                Ty::PathSearch(paths)
+            }
+            Ty::Tuple(tys) => Ty::Tuple(
+                tys.into_iter()
+                    .map(|ty| ty.map(|ty| ty.replace_by_resolved_generics_deep(gen)))
+                    .collect(),
+            ),
+            Ty::RefPtr(ty, mutbl) => {
+                Ty::RefPtr(Box::new(ty.replace_by_resolved_generics_deep(gen)), mutbl)
+            }
+            Ty::Ptr(ty, mutbl) => {
+                Ty::Ptr(Box::new(ty.replace_by_resolved_generics_deep(gen)), mutbl)
+            }
+            Ty::Slice(ty) => Ty::Slice(Box::new(ty.replace_by_resolved_generics_deep(gen))),
+            Ty::Array(ty, len) => {
+                Ty::Array(Box::new(ty.replace_by_resolved_generics_deep(gen)), len)
+            }
+            _ => self,
+        }
+    }
     pub(crate) fn replace_by_generics(self, gen: &GenericsArgs) -> Self {
         let (mut ty, deref_cnt) = self.deref_with_count(0);
         if let Ty::PathSearch(ref mut paths) = ty {
@@ -85,6 +128,12 @@ impl Ty {
                 } else {
                     return Ty::Match(param.clone().into_match());
                 }
+            } else if let Some(assoc) = gen.search_assoc_type_by_path(&paths.path) {
+                let ty = match assoc.resolved() {
+                    Some(resolved) => resolved.to_owned(),
+                    None => Ty::Match(assoc.clone().into_match()),
+                };
+                return ty.wrap_by_ref(deref_cnt);
             } else {
                 paths.path.replace_by_bounds(gen);
             }
@@ -121,8 +170,8 @@ impl Ty {
             TyKind::Rptr(ref _lifetime, ref ty) => {
                 Ty::from_ast(&ty.ty, scope).map(|ref_ty| Ty::RefPtr(Box::new(ref_ty), ty.mutbl))
             }
-            TyKind::Path(_, ref path) => Some(Ty::PathSearch(PathSearch {
-                path: Path::from_ast(path, scope),
+            TyKind::Path(ref qself, ref path) => Some(Ty::PathSearch(PathSearch {
+                path: Path::from_qualified_ast(qself.as_ref(), path, scope),
                 filepath: scope.filepath.clone(),
                 point: scope.point,
             })),
@@ -136,6 +185,9 @@ impl Ty {
             }
             TyKind::Never => None,
             TyKind::TraitObject(ref traits, _) | TyKind::ImplTrait(_, ref traits) => {
+                if let Some(output) = future_output(traits, scope) {
+                    return Some(Ty::Future(Box::new(output), scope.clone()));
+                }
                 Some(Ty::TraitObject(TraitBounds::from_generic_bounds(
                     &traits,
                     scope.filepath.clone(),
@@ -256,6 +308,7 @@ impl fmt::Display for Ty {
             Ty::Never => write!(f, "!"),
             Ty::Default => write!(f, "()"),
             Ty::Unsupported => write!(f, "_"),
+            Ty::Const(ref value) => write!(f, "{}", value),
         }
     }
 }
@@ -265,48 +318,56 @@ impl fmt::Display for Ty {
 #[derive(Clone, Debug, PartialEq)]
 pub enum Pat {
     Wild,
-    Ident(BindingMode, String),
+    /// `name` or `name @ subpattern`
+    Ident(BindingMode, String, Option<Box<Pat>>),
     Struct(Path, Vec<FieldPat>),
     TupleStruct(Path, Vec<Pat>),
     Path(Path),
     Tuple(Vec<Pat>),
-    Box,
+    Box(Box<Pat>),
     Ref(Box<Pat>, Mutability),
     Lit,
     Range,
-    Slice,
+    Slice(Vec<Pat>),
     Mac,
     Rest,
-    Or,
+    Or(Vec<Pat>),
 }
 
 impl Pat {
     pub(crate) fn search_by_name(&self, sname: &str, stype: SearchType) -> Option<String> {
         match self {
             Pat::Wild => None,
-            Pat::Ident(_, name) => {
+            Pat::Ident(_, name, sub) => {
                 if util::symbol_matches(stype, sname, name) {
                     Some(name.clone())
                 } else {
-                    None
+                    sub.as_ref()
+                        .and_then(|pat| pat.search_by_name(sname, stype))
                 }
             }
             Pat::Struct(_, pats) => pats
                 .iter()
                 .filter_map(|pat| pat.pat.search_by_name(sname, stype))
                 .next(),
-            Pat::TupleStruct(_, pats) | Pat::Tuple(pats) => pats
+            // all alternatives of an or-pattern bind the same names
+            Pat::TupleStruct(_, pats) | Pat::Tuple(pats) | Pat::Slice(pats) | Pat::Or(pats) => pats
                 .iter()
                 .filter_map(|pat| pat.search_by_name(sname, stype))
                 .next(),
-            Pat::Ref(pat, _) => pat.search_by_name(sname, stype),
+            Pat::Ref(pat, _) | Pat::Box(pat) => pat.search_by_name(sname, stype),
             _ => None,
         }
     }
     pub(crate) fn from_ast(pat: &PatKind, scope: &Scope) -> Self {
         match pat {
             PatKind::Wild => Pat::Wild,
-            PatKind::Ident(bi, ident, _) => Pat::Ident(*bi, ident.to_string()),
+            PatKind::Ident(bi, ident, sub) => Pat::Ident(
+                *bi,
+                ident.to_string(),
+                sub.as_ref()
+                    .map(|pat| Box::new(Pat::from_ast(&pat.kind, scope))),
+            ),
             PatKind::Struct(path, fields, _) => {
                 let path = Path::from_ast(path, scope);
                 let fields = fields
@@ -331,16 +392,24 @@ impl Pat {
                     .collect();
                 Pat::Tuple(pats)
             }
-            PatKind::Box(_) => Pat::Box,
+            PatKind::Box(pat) => Pat::Box(Box::new(Pat::from_ast(&pat.kind, scope))),
             PatKind::Ref(pat, mut_) => Pat::Ref(Box::new(Pat::from_ast(&pat.kind, scope)), *mut_),
             PatKind::Lit(_) => Pat::Lit,
             PatKind::Range(..) => Pat::Range,
-            PatKind::Slice(..) => Pat::Slice,
+            PatKind::Slice(pats) => Pat::Slice(
+                pats.iter()
+                    .map(|pat| Pat::from_ast(&pat.kind, scope))
+                    .collect(),
+            ),
             // ignore paren
             PatKind::Paren(pat) => Pat::from_ast(&pat.kind, scope),
             PatKind::MacCall(_) => Pat::Mac,
             PatKind::Rest => Pat::Rest,
-            PatKind::Or(_) => Pat::Or,
+            PatKind::Or(pats) => Pat::Or(
+                pats.iter()
+                    .map(|pat| Pat::from_ast(&pat.kind, scope))
+                    .collect(),
+            ),
         }
     }
 }
@@ -388,6 +457,9 @@ impl PathPrefix {
 pub struct Path {
     pub prefix: Option<PathPrefix>,
     pub segments: Vec<PathSegment>,
+    /// The self type of a qualified path, e.g. `T` for `<T as Trait>::X`,
+    /// whose segments are `Trait::X`
+    pub qself: Option<Box<Path>>,
 }
 
 impl Path {
@@ -427,42 +499,47 @@ impl Path {
         Path {
             prefix: None,
             segments,
+            qself: None,
         }
     }
 
     pub fn from_ast(path: &ast::Path, scope: &Scope) -> Path {
-        let mut segments = Vec::new();
-        for seg in path.segments.iter() {
-            let name = seg.ident.name.to_string();
-            let mut types = Vec::new();
-            let mut output = None;
-
-            if let Some(ref params) = seg.args {
-                if let ast::GenericArgs::AngleBracketed(ref angle_args) = **params {
-                    angle_args.args.iter().for_each(|arg| {
-                        if let ast::AngleBracketedArg::Arg(ast::GenericArg::Type(ty)) = arg {
-                            if let Some(ty) = Ty::from_ast(ty, scope) {
-                                types.push(ty);
-                            }
-                        }
-                    })
-                }
-                // TODO: support inputs in GenericArgs::Parenthesized (A path like `Foo(A,B) -> C`)
-                if let ast::GenericArgs::Parenthesized(ref paren_args) = **params {
-                    if let ast::FnRetTy::Ty(ref ty) = paren_args.output {
-                        output = Ty::from_ast(&*ty, scope);
-                    }
-                }
-            }
-
-            segments.push(PathSegment::new(name, types, output));
-        }
+        let segments = path
+            .segments
+            .iter()
+            .map(|seg| PathSegment::from_ast(seg, scope))
+            .collect();
         Path {
             prefix: None,
             segments,
+            qself: None,
         }
     }
 
+    /// Resolves `<T>::X` as `T::X`, and `<T as Trait>::X` as `Trait::X` qualified by `T`
+    pub(crate) fn from_qualified_ast(
+        qself: Option<&ast::QSelf>,
+        path: &ast::Path,
+        scope: &Scope,
+    ) -> Path {
+        let mut res = Path::from_ast(path, scope);
+        let qself = match qself {
+            Some(qself) => qself,
+            None => return res,
+        };
+        if let TyKind::Path(ref self_qself, ref self_path) = qself.ty.kind {
+            let self_path = Path::from_qualified_ast(self_qself.as_ref(), self_path, scope);
+            if qself.position == 0 {
+                let mut segments = self_path.segments;
+                segments.extend(res.segments);
+                res.segments = segments;
+            } else {
+                res.qself = Some(Box::new(self_path));
+            }
+        }
+        res
+    }
+
     pub fn generic_types(&self) -> impl Iterator<Item = &Ty> {
         self.segments[self.segments.len() - 1].generics.iter()
     }
@@ -471,6 +548,7 @@ impl Path {
         Path {
             prefix: None,
             segments: vec![seg],
+            qself: None,
         }
     }
 
@@ -513,7 +591,11 @@ impl Path {
                 Some(PathSegment::from(s))
             })
             .collect();
-        Path { prefix, segments }
+        Path {
+            prefix,
+            segments,
+            qself: None,
+        }
     }
 
     pub fn extend(&mut self, path: Path) -> &mut Self {
@@ -533,12 +615,18 @@ impl Path {
 impl fmt::Debug for Path {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "P[")?;
+        if let Some(ref qself) = self.qself {
+            write!(f, "<{:?} as ", qself)?;
+        }
         let mut first = true;
-        for seg in &self.segments {
+        for (i, seg) in self.segments.iter().enumerate() {
             if first {
                 write!(f, "{}", seg.name)?;
                 first = false;
             } else {
+                if self.qself.is_some() && i + 1 == self.segments.len() {
+                    write!(f, ">")?;
+                }
                 write!(f, "::{}", seg.name)?;
             }
 
@@ -571,12 +659,18 @@ impl fmt::Debug for Path {
 
 impl fmt::Display for Path {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref qself) = self.qself {
+            write!(f, "<{} as ", qself)?;
+        }
         let mut first = true;
-        for seg in &self.segments {
+        for (i, seg) in self.segments.iter().enumerate() {
             if first {
                 write!(f, "{}", seg.name)?;
                 first = false;
             } else {
+                if self.qself.is_some() && i + 1 == self.segments.len() {
+                    write!(f, ">")?;
+                }
                 write!(f, "::{}", seg.name)?;
             }
 
@@ -605,6 +699,35 @@ pub struct PathSegment {
 }
 
 impl PathSegment {
+    pub(crate) fn from_ast(seg: &ast::PathSegment, scope: &Scope) -> Self {
+        let name = seg.ident.name.to_string();
+        let mut types = Vec::new();
+        let mut output = None;
+
+        if let Some(ref params) = seg.args {
+            if let ast::GenericArgs::AngleBracketed(ref angle_args) = **params {
+                angle_args.args.iter().for_each(|arg| match arg {
+                    ast::AngleBracketedArg::Arg(ast::GenericArg::Type(ty)) => {
+                        if let Some(ty) = Ty::from_ast(ty, scope) {
+                            types.push(ty);
+                        }
+                    }
+                    // keep the position of const arguments for `GenericsArgs::apply_types`
+                    ast::AngleBracketedArg::Arg(ast::GenericArg::Const(c)) => {
+                        types.push(Ty::Const(pprust::expr_to_string(&c.value)));
+                    }
+                    _ => {}
+                })
+            }
+            // TODO: support inputs in GenericArgs::Parenthesized (A path like `Foo(A,B) -> C`)
+            if let ast::GenericArgs::Parenthesized(ref paren_args) = **params {
+                if let ast::FnRetTy::Ty(ref ty) = paren_args.output {
+                    output = Ty::from_ast(&*ty, scope);
+                }
+            }
+        }
+        PathSegment::new(name, types, output)
+    }
     pub fn new(name: String, generics: Vec<Ty>, output: Option<Ty>) -> Self {
         PathSegment {
             name,
@@ -757,13 +880,25 @@ impl TraitBounds {
             .collect();
         TraitBounds(vec)
     }
-    fn extend(&mut self, other: Self) {
+    pub(crate) fn extend(&mut self, other: Self) {
         self.0.extend(other.0)
     }
 }
 
+/// Kind of a generic parameter
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GenericKind {
+    /// `'a`
+    Lifetime,
+    /// `T`
+    Type,
+    /// `const N: usize`
+    Const,
+}
+
 /// Argument of generics like T: From<String>
-/// It's intended to use this type only for declaration of type parameter.
+/// It's intended to use this type only for declaration of type parameter,
+/// but lifetime and const parameters are also stored to keep their positions.
 // TODO: impl trait's name
 // TODO: it has too many PathBuf
 #[derive(Clone, Debug, PartialEq)]
@@ -778,6 +913,11 @@ pub struct TypeParameter {
     pub bounds: TraitBounds,
     /// Resolved Type
     pub resolved: Option<Ty>,
+    /// Associated types constrained in bounds, like `Item` in `T: Iterator<Item = u8>`
+    /// or `where T::Item: Display`
+    pub assoc_types: Vec<TypeParameter>,
+    /// lifetime, type or const
+    pub kind: GenericKind,
 }
 
 impl TypeParameter {
@@ -792,9 +932,13 @@ impl TypeParameter {
             point: self.point,
             coords: None,
             local: false,
-            mtype: MatchType::TypeParameter(Box::new(self.bounds)),
+            mtype: match self.kind {
+                GenericKind::Const => MatchType::Const,
+                _ => MatchType::TypeParameter(Box::new(self.bounds)),
+            },
             contextstr: String::new(),
             docs: String::new(),
+            visibility: Visibility::Private,
         }
     }
     pub(crate) fn resolve(&mut self, ty: Ty) {
@@ -817,6 +961,102 @@ impl TypeParameter {
             .collect();
         self.bounds.0.extend(add_bounds);
     }
+    pub(crate) fn add_assoc_type(&mut self, assoc: TypeParameter) {
+        match self.assoc_types.iter_mut().find(|tp| tp.name == assoc.name) {
+            Some(tp) => {
+                tp.add_bound(assoc.bounds);
+                if tp.resolved.is_none() {
+                    tp.resolved = assoc.resolved;
+                }
+            }
+            None => self.assoc_types.push(assoc),
+        }
+    }
+    pub(crate) fn search_assoc_type(&self, name: &str) -> Option<&TypeParameter> {
+        self.assoc_types.iter().find(|tp| tp.name == name)
+    }
+}
+
+/// Get `T` from `impl Future<Output = T>` or `dyn Future<Output = T>`
+fn future_output(bounds: &GenericBounds, scope: &Scope) -> Option<Ty> {
+    bounds.iter().find_map(|bound| {
+        let ptrait_ref = match *bound {
+            GenericBound::Trait(ref ptrait_ref, _) => ptrait_ref,
+            _ => return None,
+        };
+        let seg = ptrait_ref.trait_ref.path.segments.last()?;
+        if &*seg.ident.name.as_str() != "Future" {
+            return None;
+        }
+        let args = match seg.args.as_deref()? {
+            ast::GenericArgs::AngleBracketed(ref args) => args,
+            _ => return None,
+        };
+        args.args.iter().find_map(|arg| match arg {
+            ast::AngleBracketedArg::Constraint(ref constraint)
+                if &*constraint.ident.name.as_str() == "Output" =>
+            {
+                match constraint.kind {
+                    ast::AssocTyConstraintKind::Equality { ref ty } => Ty::from_ast(ty, scope),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Collects constraints on associated types, like `Item = u8` in `Iterator<Item = u8>`
+fn assoc_types_from_bounds<P: AsRef<FilePath>>(
+    bounds: &GenericBounds,
+    filepath: P,
+    offset: i32,
+) -> Vec<TypeParameter> {
+    let mut res = Vec::new();
+    for bound in bounds.iter() {
+        let ptrait_ref = match *bound {
+            GenericBound::Trait(ref ptrait_ref, _) => ptrait_ref,
+            _ => continue,
+        };
+        let args = match ptrait_ref.trait_ref.path.segments.last() {
+            Some(seg) => match seg.args.as_deref() {
+                Some(ast::GenericArgs::AngleBracketed(ref args)) => args,
+                _ => continue,
+            },
+            None => continue,
+        };
+        for arg in args.args.iter() {
+            let constraint = match arg {
+                ast::AngleBracketedArg::Constraint(ref constraint) => constraint,
+                _ => continue,
+            };
+            let source_map::BytePos(point) = constraint.ident.span.lo();
+            let mut type_param = TypeParameter {
+                name: constraint.ident.name.to_string(),
+                point: BytePos::from((point as i32 + offset) as u32),
+                filepath: filepath.as_ref().to_path_buf(),
+                bounds: TraitBounds(Vec::new()),
+                resolved: None,
+                assoc_types: Vec::new(),
+                kind: GenericKind::Type,
+            };
+            match constraint.kind {
+                ast::AssocTyConstraintKind::Equality { ref ty } => {
+                    let source_map::BytePos(point) = ty.span.lo();
+                    let scope = Scope::new(
+                        filepath.as_ref().to_path_buf(),
+                        BytePos::from((point as i32 + offset) as u32),
+                    );
+                    type_param.resolved = Ty::from_ast(ty, &scope);
+                }
+                ast::AssocTyConstraintKind::Bound { ref bounds } => {
+                    type_param.bounds = TraitBounds::from_generic_bounds(bounds, &filepath, offset);
+                }
+            }
+            res.push(type_param);
+        }
+    }
+    res
 }
 
 /// List of Args in generics, e.g. <T: Clone, U, P>
@@ -837,20 +1077,35 @@ impl GenericsArgs {
         let mut args = Vec::new();
         let mut closure_args = Vec::new();
         for param in generics.params.iter() {
+            let untyped_param = |kind| {
+                let source_map::BytePos(point) = param.ident.span.lo();
+                TypeParameter {
+                    name: param.ident.name.to_string(),
+                    point: BytePos::from((point as i32 + offset) as u32),
+                    filepath: filepath.as_ref().to_path_buf(),
+                    bounds: TraitBounds(Vec::new()),
+                    resolved: None,
+                    assoc_types: Vec::new(),
+                    kind,
+                }
+            };
             match param.kind {
-                // TODO: lifetime support
-                GenericParamKind::Lifetime => {}
+                // TODO: lifetime bounds support
+                GenericParamKind::Lifetime => args.push(untyped_param(GenericKind::Lifetime)),
                 // TODO: should we handle default type here?
                 GenericParamKind::Type { default: _ } => {
                     let param_name = param.ident.name.to_string();
                     let source_map::BytePos(point) = param.ident.span.lo();
                     let bounds = TraitBounds::from_generic_bounds(&param.bounds, &filepath, offset);
+                    let assoc_types = assoc_types_from_bounds(&param.bounds, &filepath, offset);
                     let type_param = TypeParameter {
                         name: param_name,
                         point: BytePos::from((point as i32 + offset) as u32),
                         filepath: filepath.as_ref().to_path_buf(),
                         bounds,
                         resolved: None,
+                        assoc_types,
+                        kind: GenericKind::Type,
                     };
                     if type_param.bounds.has_closure() {
                         closure_args.push(type_param);
@@ -858,25 +1113,57 @@ impl GenericsArgs {
                         args.push(type_param);
                     }
                 }
-                // TODO: Support const
-                GenericParamKind::Const { ty: _, .. } => {}
+                GenericParamKind::Const { .. } => args.push(untyped_param(GenericKind::Const)),
             }
         }
         for pred in generics.where_clause.predicates.iter() {
             match pred {
                 WherePredicate::BoundPredicate(bound) => match bound.bounded_ty.kind {
                     TyKind::Path(ref _qself, ref path) => {
-                        if let Some(seg) = path.segments.get(0) {
-                            let name = pprust::path_segment_to_string(&seg);
-                            let bound =
-                                TraitBounds::from_generic_bounds(&bound.bounds, &filepath, offset);
-                            if let Some(tp) = args.iter_mut().find(|tp| tp.name == name) {
-                                tp.bounds.extend(bound);
-                                continue;
+                        let name = match path.segments.get(0) {
+                            Some(seg) => pprust::path_segment_to_string(&seg),
+                            None => continue,
+                        };
+                        let tp = match args
+                            .iter_mut()
+                            .chain(closure_args.iter_mut())
+                            .find(|tp| tp.name == name)
+                        {
+                            Some(tp) => tp,
+                            None => continue,
+                        };
+                        match path.segments.len() {
+                            1 => {
+                                tp.bounds.extend(TraitBounds::from_generic_bounds(
+                                    &bound.bounds,
+                                    &filepath,
+                                    offset,
+                                ));
+                                for assoc in
+                                    assoc_types_from_bounds(&bound.bounds, &filepath, offset)
+                                {
+                                    tp.add_assoc_type(assoc);
+                                }
                             }
-                            if let Some(tp) = closure_args.iter_mut().find(|tp| tp.name == name) {
-                                tp.bounds.extend(bound);
+                            // bounds on an associated type, like `where T::Item: Display`
+                            2 => {
+                                let assoc = &path.segments[1];
+                                let source_map::BytePos(point) = assoc.ident.span.lo();
+                                tp.add_assoc_type(TypeParameter {
+                                    name: assoc.ident.name.to_string(),
+                                    point: BytePos::from((point as i32 + offset) as u32),
+                                    filepath: filepath.as_ref().to_path_buf(),
+                                    bounds: TraitBounds::from_generic_bounds(
+                                        &bound.bounds,
+                                        &filepath,
+                                        offset,
+                                    ),
+                                    resolved: None,
+                                    assoc_types: Vec::new(),
+                                    kind: GenericKind::Type,
+                                });
                             }
+                            _ => {}
                         }
                     }
                     // TODO 'self' support
@@ -929,32 +1216,60 @@ impl GenericsArgs {
     pub fn get_idents(&self) -> Vec<String> {
         self.0.iter().map(|g| g.name.clone()).collect()
     }
+    /// Type parameters, excluding lifetime and const parameters
     pub fn args(&self) -> impl Iterator<Item = &TypeParameter> {
-        self.0.iter()
+        self.0.iter().filter(|typ| typ.kind == GenericKind::Type)
     }
     pub fn args_mut(&mut self) -> impl Iterator<Item = &mut TypeParameter> {
-        self.0.iter_mut()
+        self.0
+            .iter_mut()
+            .filter(|typ| typ.kind == GenericKind::Type)
     }
     pub fn search_param_by_path(&self, path: &Path) -> Option<(usize, &TypeParameter)> {
         if !path.is_single() {
             return None;
         }
-        let query = &path.segments[0].name;
-        for (i, typ) in self.0.iter().enumerate() {
-            if typ.name() == query {
-                return Some((i, typ));
-            }
-        }
-        None
+        self.search_param_by_name(&path.segments[0].name)
     }
     pub fn search_param_by_name(&self, name: &str) -> Option<(usize, &TypeParameter)> {
         for (i, typ) in self.0.iter().enumerate() {
-            if typ.name() == name {
+            if typ.kind == GenericKind::Type && typ.name() == name {
                 return Some((i, typ));
             }
         }
         None
     }
+    /// Indices of parameters which take positional generic arguments, in declaration order.
+    /// Lifetimes are omitted since `PathSegment::generics` doesn't contain them.
+    pub(crate) fn positional_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<_> = (0..self.0.len())
+            .filter(|&i| self.0[i].kind != GenericKind::Lifetime)
+            .collect();
+        // type parameters with closure bounds are moved to the end by `from_generics`
+        indices.sort_by_key(|&i| self.0[i].point);
+        indices
+    }
+    /// Pairs type parameters with generic arguments of the same position,
+    /// e.g. `T` with `u8` for `Buf<const N: usize, T>` and `Buf<32, u8>`
+    pub(crate) fn zip_type_args<'a, 'b>(
+        &'a self,
+        args: &'b [Ty],
+    ) -> Vec<(&'a TypeParameter, &'b Ty)> {
+        self.positional_indices()
+            .into_iter()
+            .map(|i| &self.0[i])
+            .zip(args)
+            .filter(|(typ, _)| typ.kind == GenericKind::Type)
+            .collect()
+    }
+    /// Search an associated type of a type parameter, like `T::Item`
+    pub(crate) fn search_assoc_type_by_path(&self, path: &Path) -> Option<&TypeParameter> {
+        if path.segments.len() != 2 {
+            return None;
+        }
+        let (_, param) = self.search_param_by_name(&path.segments[0].name)?;
+        param.search_assoc_type(&path.segments[1].name)
+    }
     pub fn get_tbound_match(&self, name: &str) -> Option<Match> {
         Some(self.search_param_by_name(name)?.1.clone().into_match())
     }
@@ -966,9 +1281,17 @@ impl GenericsArgs {
     pub(crate) fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+    /// Type and const parameters, which can be referred by name
+    pub(crate) fn into_named_params(self) -> impl Iterator<Item = TypeParameter> {
+        self.0
+            .into_iter()
+            .filter(|typ| typ.kind != GenericKind::Lifetime)
+    }
     pub(crate) fn apply_types(&mut self, other: &[Ty]) {
-        for (l, r) in self.0.iter_mut().zip(other.iter()) {
-            l.resolve(r.clone());
+        for (i, r) in self.positional_indices().into_iter().zip(other.iter()) {
+            if self.0[i].kind == GenericKind::Type {
+                self.0[i].resolve(r.clone());
+            }
         }
     }
 }
@@ -984,6 +1307,10 @@ pub struct ImplHeader {
     local: bool,
     impl_start: BytePos,
     block_start: BytePos,
+    /// `impl dyn Trait`
+    trait_object: bool,
+    /// generated by `#[derive(..)]`, so there's no block to search
+    derived: bool,
 }
 
 impl ImplHeader {
@@ -1003,6 +1330,10 @@ impl ImplHeader {
         let trait_path = otrait
             .as_ref()
             .map(|tref| Path::from_ast(&tref.path, &scope));
+        let trait_object = match self_type.kind {
+            TyKind::TraitObject(..) => true,
+            _ => false,
+        };
         Some(ImplHeader {
             self_path,
             trait_path,
@@ -1011,8 +1342,30 @@ impl ImplHeader {
             local,
             impl_start,
             block_start,
+            trait_object,
+            derived: false,
         })
     }
+    /// Header of `impl trait_path for self_path` generated by `#[derive(..)]` at `attr_start`
+    pub(crate) fn derived(
+        self_path: Path,
+        trait_path: Path,
+        generics: GenericsArgs,
+        path: &FilePath,
+        attr_start: BytePos,
+    ) -> Self {
+        ImplHeader {
+            self_path,
+            trait_path: Some(trait_path),
+            generics,
+            filepath: path.to_owned(),
+            local: true,
+            impl_start: attr_start,
+            block_start: attr_start,
+            trait_object: false,
+            derived: true,
+        }
+    }
     pub(crate) fn self_path(&self) -> &Path {
         &self.self_path
     }
@@ -1035,6 +1388,12 @@ impl ImplHeader {
     pub(crate) fn is_trait(&self) -> bool {
         self.trait_path.is_some()
     }
+    pub(crate) fn is_trait_object(&self) -> bool {
+        self.trait_object
+    }
+    pub(crate) fn is_derived(&self) -> bool {
+        self.derived
+    }
     pub(crate) fn resolve_trait(
         &self,
         session: &Session<'_>,
@@ -1063,6 +1422,13 @@ pub(crate) fn get_self_path(ty: &TyKind, scope: &Scope) -> Option<Path> {
         TyKind::Path(_, ref path) => Some(Path::from_ast(path, &scope)),
         // HACK: treat slice as path
         TyKind::Slice(_) => Some(Path::single("[T]".to_owned().into())),
+        // `impl dyn Trait` is treated as an impl for its principal trait
+        TyKind::TraitObject(ref bounds, _) => bounds.iter().find_map(|bound| match *bound {
+            GenericBound::Trait(ref ptrait_ref, _) => {
+                Some(Path::from_ast(&ptrait_ref.trait_ref.path, scope))
+            }
+            _ => None,
+        }),
         _ => None,
     }
 }
@@ -0,0 +1,138 @@
+//! Well-known attribute macros which change the signature of the items they annotate.
+//!
+//! Racer can't run proc macros, so the effects of popular ones are hardcoded here.
+//! Unknown attribute macros are assumed to keep the item as written.
+use crate::core::{BytePos, Match, MatchType, Src};
+use crate::scopes;
+use crate::util::{attribute_path, is_ident_char, outer_attributes};
+
+/// How an attribute macro rewrites an `async fn`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AttrRewrite {
+    /// `async fn f() -> T` returns `Pin<Box<dyn Future<Output = T> + Send>>`, like `#[async_trait]`
+    BoxedFuture,
+    /// The async body is run by a runtime, so `async fn main() -> T` returns `T`,
+    /// like `#[tokio::main]`
+    BlockOn,
+}
+
+const ATTR_REWRITES: [(&str, AttrRewrite); 8] = [
+    ("async_trait::async_trait", AttrRewrite::BoxedFuture),
+    ("async_std::main", AttrRewrite::BlockOn),
+    ("async_std::test", AttrRewrite::BlockOn),
+    ("actix_rt::main", AttrRewrite::BlockOn),
+    ("actix_web::main", AttrRewrite::BlockOn),
+    ("tokio::main", AttrRewrite::BlockOn),
+    ("tokio::test", AttrRewrite::BlockOn),
+    ("futures_await_test::async_test", AttrRewrite::BlockOn),
+];
+
+/// Finds the rewrite for the attribute `attr`, written either with its full path or
+/// with the last segment of it imported by `use` in `src` (e.g. `#[async_trait]` after
+/// `use async_trait::async_trait`)
+fn find_rewrite(attr: &str, src: &str) -> Option<AttrRewrite> {
+    let path = attribute_path(attr).trim_start_matches("::");
+    ATTR_REWRITES
+        .iter()
+        .find(|(name, _)| {
+            *name == path || (name.rsplit("::").next() == Some(path) && imports_path(src, name))
+        })
+        .map(|&(_, rewrite)| rewrite)
+}
+
+/// Checks if a `use` declaration in `src` imports `path`,
+/// like `use tokio::main;`, `use tokio::{main, test};` or `use tokio::*;` for `tokio::main`
+fn imports_path(src: &str, path: &str) -> bool {
+    let (prefix, name) = match path.rfind("::") {
+        Some(i) => (&path[..i], &path[i + 2..]),
+        None => return false,
+    };
+    src.match_indices("use ").any(|(start, _)| {
+        if src[..start]
+            .chars()
+            .next_back()
+            .map_or(false, is_ident_char)
+        {
+            return false;
+        }
+        let end = src[start..].find(';').map_or(src.len(), |len| start + len);
+        let decl: String = src[start + "use ".len()..end]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let rest = decl.trim_start_matches("::");
+        if !rest.starts_with(prefix) || !rest[prefix.len()..].starts_with("::") {
+            return false;
+        }
+        let rest = &rest[prefix.len() + 2..];
+        if rest.starts_with('{') && rest.ends_with('}') {
+            rest[1..rest.len() - 1]
+                .split(',')
+                .any(|item| item == name || item == "*")
+        } else {
+            rest == name || rest == "*"
+        }
+    })
+}
+
+/// Returns the rewrite applied to the function `fnmatch`, whose statement starts at `point`.
+/// Attributes on the enclosing `impl` or `trait` are also considered for methods.
+pub(crate) fn rewrite_of_fn(msrc: Src<'_>, point: BytePos, fnmatch: &Match) -> Option<AttrRewrite> {
+    let own = outer_attributes(&msrc, point);
+    let block = match fnmatch.mtype {
+        MatchType::Method(_) => scopes::find_impl_start(msrc, point, BytePos::ZERO)
+            .filter(|&start| start < point)
+            .map(|start| outer_attributes(&msrc, start)),
+        _ => None,
+    };
+    let rewrite = own
+        .into_iter()
+        .chain(block.into_iter().flatten())
+        .find_map(|attr| find_rewrite(attr, &msrc));
+    debug!("[rewrite_of_fn] {:?}: {:?}", fnmatch.matchstr, rewrite);
+    rewrite
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_rewrite_by_path_or_name() {
+        let src = "use async_trait::async_trait;";
+        assert_eq!(
+            find_rewrite("async_trait", src),
+            Some(AttrRewrite::BoxedFuture)
+        );
+        assert_eq!(
+            find_rewrite("async_trait(?Send)", src),
+            Some(AttrRewrite::BoxedFuture)
+        );
+        assert_eq!(
+            find_rewrite("tokio::main(flavor = \"current_thread\")", ""),
+            Some(AttrRewrite::BlockOn)
+        );
+        assert_eq!(find_rewrite("tracing::instrument(skip(self))", ""), None);
+        assert_eq!(find_rewrite("inline", ""), None);
+    }
+
+    #[test]
+    fn requires_import_for_last_segment() {
+        assert_eq!(find_rewrite("test", ""), None);
+        assert_eq!(find_rewrite("main", "use std::io;"), None);
+        assert_eq!(find_rewrite("async_trait", ""), None);
+        assert_eq!(
+            find_rewrite("test", "use tokio::{self, test};"),
+            Some(AttrRewrite::BlockOn)
+        );
+        assert_eq!(
+            find_rewrite("main", "pub use ::async_std :: main ;"),
+            Some(AttrRewrite::BlockOn)
+        );
+        assert_eq!(
+            find_rewrite("main", "use actix_web::*;"),
+            Some(AttrRewrite::BlockOn)
+        );
+        assert_eq!(find_rewrite("main", "reuse tokio::main;"), None);
+    }
+}
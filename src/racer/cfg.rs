@@ -0,0 +1,505 @@
+//! Evaluation of `#[cfg(..)]` attributes.
+//!
+//! Options come from the target triple (the host by default) and the features
+//! enabled for the package in `cargo metadata`. Predicates racer can't decide,
+//! like `test` or custom `--cfg` flags, are treated as enabled.
+use crate::core::{BytePos, Session};
+use crate::util::{attribute_path, find_ident_end, is_whitespace_byte, outer_attributes};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// How items disabled by `#[cfg(..)]` are treated
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CfgFilter {
+    /// Disabled items are not returned
+    Hide,
+    /// Disabled items are returned, and `Session::is_disabled_by_cfg` reports them,
+    /// so that clients can show them dimmed
+    Dim,
+}
+
+impl Default for CfgFilter {
+    fn default() -> Self {
+        CfgFilter::Hide
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum CfgExpr {
+    Atom(String),
+    KeyValue(String, String),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Not(Box<CfgExpr>),
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CfgOptions {
+    atoms: HashSet<String>,
+    key_values: HashSet<(String, String)>,
+    /// Keys whose all values are known
+    known_keys: HashSet<String>,
+}
+
+impl CfgOptions {
+    /// Options for `triple`, or for the host if it's `None`
+    pub(crate) fn for_target(triple: Option<&str>) -> Self {
+        let mut opts = CfgOptions::default();
+        match triple {
+            Some(triple) => opts.set_target(triple),
+            None => opts.set_host(),
+        }
+        opts
+    }
+
+    /// Enables exactly `features`
+    pub(crate) fn set_features<I: IntoIterator<Item = String>>(&mut self, features: I) {
+        for feature in features {
+            self.key_values.insert(("feature".to_owned(), feature));
+        }
+        self.known_keys.insert("feature".to_owned());
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        self.key_values.insert((key.to_owned(), value.to_owned()));
+        self.known_keys.insert(key.to_owned());
+    }
+
+    fn set_family(&mut self, family: &str) {
+        if family.is_empty() {
+            self.known_keys.insert("target_family".to_owned());
+            return;
+        }
+        self.set("target_family", family);
+        if family == "unix" || family == "windows" {
+            self.atoms.insert(family.to_owned());
+        }
+    }
+
+    fn set_host(&mut self) {
+        use std::env::consts;
+        self.set("target_arch", consts::ARCH);
+        self.set("target_os", consts::OS);
+        self.set_family(consts::FAMILY);
+        let width = (std::mem::size_of::<usize>() * 8).to_string();
+        self.set("target_pointer_width", &width);
+        let endian = if cfg!(target_endian = "big") {
+            "big"
+        } else {
+            "little"
+        };
+        self.set("target_endian", endian);
+    }
+
+    fn set_target(&mut self, triple: &str) {
+        let parts: Vec<_> = triple.split('-').collect();
+        let arch = parts[0];
+        let (vendor, os, env) = match parts[1..] {
+            [vendor, os, env] => (vendor, os, env),
+            [vendor, os] if ["unknown", "pc", "apple", "none"].contains(&vendor) => {
+                (vendor, os, "")
+            }
+            [os, env] => ("unknown", os, env),
+            [os] => ("unknown", os, ""),
+            _ => ("unknown", "unknown", ""),
+        };
+        let (os, env) = match (os, env) {
+            ("darwin", env) => ("macos", env),
+            // e.g. `aarch64-linux-android` and `armv7-linux-androideabi`
+            ("linux", env) if env.starts_with("android") => ("android", ""),
+            (os, env) => (os, env),
+        };
+        self.set("target_arch", normalize_arch(arch));
+        self.set("target_vendor", vendor);
+        self.set("target_os", os);
+        self.set("target_env", env);
+        let family = match os {
+            "windows" => "windows",
+            "linux" | "macos" | "ios" | "android" | "freebsd" | "netbsd" | "openbsd"
+            | "dragonfly" | "solaris" | "illumos" | "haiku" | "redox" => "unix",
+            _ if arch.starts_with("wasm") => "wasm",
+            _ => "",
+        };
+        self.set_family(family);
+        let width = match normalize_arch(arch) {
+            "x86_64" | "aarch64" | "powerpc64" | "mips64" | "s390x" | "sparc64" | "riscv64"
+            | "wasm64" => "64",
+            "avr" | "msp430" => "16",
+            _ => "32",
+        };
+        self.set("target_pointer_width", width);
+        let big_endian = (arch.starts_with("powerpc") && !arch.ends_with("le"))
+            || (arch.starts_with("mips") && !arch.ends_with("el"))
+            || arch == "s390x"
+            || arch.starts_with("sparc");
+        self.set("target_endian", if big_endian { "big" } else { "little" });
+    }
+
+    /// Evaluates `expr`, or returns `None` if it depends on an unknown option
+    fn eval(&self, expr: &CfgExpr) -> Option<bool> {
+        match expr {
+            CfgExpr::Atom(name) => match name.as_str() {
+                "unix" | "windows" if self.known_keys.contains("target_family") => {
+                    Some(self.atoms.contains(name))
+                }
+                _ => None,
+            },
+            CfgExpr::KeyValue(key, value) => {
+                if self.known_keys.contains(key) {
+                    Some(self.key_values.contains(&(key.clone(), value.clone())))
+                } else {
+                    None
+                }
+            }
+            CfgExpr::All(exprs) => self.eval_list(exprs, false),
+            CfgExpr::Any(exprs) => self.eval_list(exprs, true),
+            CfgExpr::Not(e) => self.eval(e).map(|v| !v),
+        }
+    }
+
+    /// Evaluates `all(..)` or `any(..)`: the result is `decisive` if any of `exprs` is,
+    /// and unknown if any of the others is
+    fn eval_list(&self, exprs: &[CfgExpr], decisive: bool) -> Option<bool> {
+        let mut unknown = false;
+        for e in exprs {
+            match self.eval(e) {
+                Some(v) if v == decisive => return Some(decisive),
+                Some(_) => {}
+                None => unknown = true,
+            }
+        }
+        if unknown {
+            None
+        } else {
+            Some(!decisive)
+        }
+    }
+}
+
+fn normalize_arch(arch: &str) -> &str {
+    match arch {
+        "i386" | "i586" | "i686" => "x86",
+        _ if arch.starts_with("armv") || arch.starts_with("thumbv") => "arm",
+        _ if arch.starts_with("riscv64") => "riscv64",
+        _ if arch.starts_with("riscv32") => "riscv32",
+        "powerpc64le" => "powerpc64",
+        "mipsel" => "mips",
+        "mips64el" => "mips64",
+        _ => arch,
+    }
+}
+
+/// Parses the predicate of `cfg(..)`
+fn parse_cfg(src: &str) -> Option<CfgExpr> {
+    let mut parser = CfgParser { src, pos: 0 };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    if parser.pos == src.len() {
+        Some(expr)
+    } else {
+        None
+    }
+}
+
+struct CfgParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> CfgParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .src
+            .as_bytes()
+            .get(self.pos)
+            .map_or(false, |&b| is_whitespace_byte(b))
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_whitespace();
+        if self.src.as_bytes().get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let end = find_ident_end(self.src, BytePos(self.pos)).0;
+        if end == self.pos {
+            return None;
+        }
+        let ident = &self.src[self.pos..end];
+        self.pos = end;
+        Some(ident)
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        if !self.eat(b'"') {
+            return None;
+        }
+        let len = self.src[self.pos..].find('"')?;
+        let s = &self.src[self.pos..self.pos + len];
+        self.pos += len + 1;
+        Some(s)
+    }
+
+    fn expr(&mut self) -> Option<CfgExpr> {
+        let name = self.ident()?;
+        if self.eat(b'=') {
+            return Some(CfgExpr::KeyValue(
+                name.to_owned(),
+                self.string()?.to_owned(),
+            ));
+        }
+        if !self.eat(b'(') {
+            return Some(CfgExpr::Atom(name.to_owned()));
+        }
+        let mut args = Vec::new();
+        while !self.eat(b')') {
+            args.push(self.expr()?);
+            if !self.eat(b',') {
+                if !self.eat(b')') {
+                    return None;
+                }
+                break;
+            }
+        }
+        match name {
+            "all" => Some(CfgExpr::All(args)),
+            "any" => Some(CfgExpr::Any(args)),
+            "not" if args.len() == 1 => Some(CfgExpr::Not(Box::new(args.pop()?))),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the predicates of `#[cfg(..)]` attributes on the item starting at `point`
+fn outer_cfgs(src: &str, point: BytePos) -> Vec<CfgExpr> {
+    outer_attributes(src, point)
+        .into_iter()
+        .filter(|attr| attribute_path(attr) == "cfg")
+        .filter_map(|attr| {
+            let args = attr[3..].trim_start();
+            if args.starts_with('(') && args.ends_with(')') {
+                parse_cfg(&args[1..args.len() - 1])
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Splits `args` at top-level commas, ignoring a trailing one
+fn split_args(args: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut level = 0;
+    let mut in_str = false;
+    let mut start = 0;
+    let bytes = args.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'"' if i == 0 || bytes[i - 1] != b'\\' => in_str = !in_str,
+            _ if in_str => {}
+            b'(' | b'[' => level += 1,
+            b')' | b']' => level -= 1,
+            b',' if level == 0 => {
+                out.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args[start..].trim().is_empty() {
+        out.push(args[start..].trim());
+    }
+    out
+}
+
+/// Returns the attributes `attr` applies, i.e. `attr` itself, or for
+/// `cfg_attr(pred, attrs..)` the attributes in it unless `pred` is false
+fn expand_cfg_attr<'a>(attr: &'a str, opts: &CfgOptions, out: &mut Vec<&'a str>) {
+    if attribute_path(attr) != "cfg_attr" {
+        out.push(attr);
+        return;
+    }
+    let args = attr["cfg_attr".len()..].trim();
+    if !args.starts_with('(') || !args.ends_with(')') {
+        return;
+    }
+    let args = split_args(&args[1..args.len() - 1]);
+    let pred = match args.first().and_then(|pred| parse_cfg(pred)) {
+        Some(pred) => pred,
+        None => return,
+    };
+    if opts.eval(&pred) == Some(false) {
+        return;
+    }
+    for attr in &args[1..] {
+        expand_cfg_attr(attr, opts, out);
+    }
+}
+
+/// Replaces `cfg_attr(..)`s in `attrs` with the attributes they apply in `filepath`
+pub(crate) fn applied_attributes<'a, I>(
+    attrs: I,
+    filepath: &Path,
+    session: &Session<'_>,
+) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let opts = session.cfg_options(filepath);
+    let mut out = Vec::new();
+    for attr in attrs {
+        expand_cfg_attr(attr, &opts, &mut out);
+    }
+    out
+}
+
+/// Checks if the item starting at `point` of `src` is disabled by a `#[cfg(..)]`
+pub(crate) fn is_disabled(
+    src: &str,
+    point: BytePos,
+    filepath: &Path,
+    session: &Session<'_>,
+) -> bool {
+    let cfgs = outer_cfgs(src, point);
+    if cfgs.is_empty() {
+        return false;
+    }
+    let opts = session.cfg_options(filepath);
+    let disabled = cfgs.iter().any(|expr| opts.eval(expr) == Some(false));
+    if disabled {
+        debug!(
+            "[cfg::is_disabled] {:?} is disabled in {:?}",
+            cfgs, filepath
+        );
+    }
+    disabled
+}
+
+/// Builds the options for the package of `manifest`
+pub(crate) fn options_for(
+    manifest: Option<&Path>,
+    target: Option<&str>,
+    session: &Session<'_>,
+) -> CfgOptions {
+    let mut opts = CfgOptions::for_target(target);
+    let features = manifest.and_then(|m| session.project_model.enabled_features(m));
+    if let Some(features) = features {
+        opts.set_features(features);
+    }
+    opts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(triple: &str, cfg: &str) -> Option<bool> {
+        let mut opts = CfgOptions::for_target(Some(triple));
+        opts.set_features(vec!["std".to_owned()]);
+        opts.eval(&parse_cfg(cfg).unwrap())
+    }
+
+    #[test]
+    fn parses_nested_predicates() {
+        assert_eq!(
+            parse_cfg("all(unix, not(target_os = \"macos\"))"),
+            Some(CfgExpr::All(vec![
+                CfgExpr::Atom("unix".to_owned()),
+                CfgExpr::Not(Box::new(CfgExpr::KeyValue(
+                    "target_os".to_owned(),
+                    "macos".to_owned()
+                ))),
+            ]))
+        );
+        assert_eq!(parse_cfg("any(unix,)").is_some(), true);
+        assert_eq!(parse_cfg("not(unix, windows)"), None);
+    }
+
+    #[test]
+    fn evaluates_target_predicates() {
+        let linux = "x86_64-unknown-linux-gnu";
+        let windows = "x86_64-pc-windows-msvc";
+        assert_eq!(eval(linux, "unix"), Some(true));
+        assert_eq!(eval(linux, "windows"), Some(false));
+        assert_eq!(
+            eval(windows, "all(windows, target_env = \"msvc\")"),
+            Some(true)
+        );
+        assert_eq!(
+            eval("aarch64-apple-darwin", "target_os = \"macos\""),
+            Some(true)
+        );
+        assert_eq!(
+            eval("i686-linux-android", "target_arch = \"x86\""),
+            Some(true)
+        );
+        for android in &["aarch64-linux-android", "armv7-linux-androideabi"] {
+            assert_eq!(
+                eval(
+                    android,
+                    "all(unix, target_os = \"android\", target_env = \"\")"
+                ),
+                Some(true)
+            );
+            assert_eq!(eval(android, "target_os = \"linux\""), Some(false));
+        }
+        assert_eq!(eval(linux, "target_pointer_width = \"32\""), Some(false));
+    }
+
+    #[test]
+    fn evaluates_features() {
+        let linux = "x86_64-unknown-linux-gnu";
+        assert_eq!(eval(linux, "feature = \"std\""), Some(true));
+        assert_eq!(eval(linux, "feature = \"alloc\""), Some(false));
+    }
+
+    #[test]
+    fn unknown_predicates_are_undecided() {
+        let linux = "x86_64-unknown-linux-gnu";
+        assert_eq!(eval(linux, "test"), None);
+        assert_eq!(eval(linux, "any(test, windows)"), None);
+        assert_eq!(eval(linux, "any(test, unix)"), Some(true));
+        assert_eq!(eval(linux, "all(test, windows)"), Some(false));
+        assert_eq!(eval(linux, "not(debug_assertions)"), None);
+    }
+
+    #[test]
+    fn expands_cfg_attr() {
+        let mut opts = CfgOptions::for_target(Some("x86_64-unknown-linux-gnu"));
+        opts.set_features(vec!["std".to_owned()]);
+        let expand = |attr: &'static str| {
+            let mut out = Vec::new();
+            expand_cfg_attr(attr, &opts, &mut out);
+            out
+        };
+        assert_eq!(expand("no_std"), vec!["no_std"]);
+        assert_eq!(expand("cfg_attr(windows, no_std)"), Vec::<&str>::new());
+        assert_eq!(
+            expand("cfg_attr(not(feature = \"alloc\"), no_std, derive(Clone, Debug))"),
+            vec!["no_std", "derive(Clone, Debug)"]
+        );
+        assert_eq!(
+            expand("cfg_attr(test, cfg_attr(unix, derive(Debug)),)"),
+            vec!["derive(Debug)"]
+        );
+    }
+
+    #[test]
+    fn collects_outer_cfg_attributes() {
+        let src = "#[inline]\n#[cfg(unix)]\n#[cfg_attr(test, derive(Debug))]\nfn foo() {}";
+        let point = BytePos(src.find("fn").unwrap());
+        assert_eq!(
+            outer_cfgs(src, point),
+            vec![CfgExpr::Atom("unix".to_owned())]
+        );
+    }
+}
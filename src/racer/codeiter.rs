@@ -36,7 +36,8 @@ impl<'a> Iterator for StmtIndicesIter<'a> {
         }
         let start = pos;
         // test attribute   #[foo = bar]
-        if pos < self.end && src_bytes[pos.0] == b'#' {
+        let is_attribute = pos < self.end && src_bytes[pos.0] == b'#';
+        if is_attribute {
             enddelim = b']'
         };
         // iterate through the chunk, looking for stmt end
@@ -60,6 +61,7 @@ impl<'a> Iterator for StmtIndicesIter<'a> {
                     // closebrace finishes the stmt
                     if bracelevel == 0
                         && parenlevel == 0
+                        && !is_attribute
                         && !(is_a_use_stmt(src_bytes, start, pos)
                             || is_a_let_stmt(src_bytes, start, pos))
                     {
@@ -78,7 +80,12 @@ impl<'a> Iterator for StmtIndicesIter<'a> {
                 b'!' => {
                     // macro if followed by at least one space or (
                     // FIXME: test with boolean 'not' expression
-                    if parenlevel == 0 && bracelevel == 0 && pos < self.end && (pos - start).0 > 1 {
+                    if parenlevel == 0
+                        && bracelevel == 0
+                        && !is_attribute
+                        && pos < self.end
+                        && (pos - start).0 > 1
+                    {
                         match src_bytes[pos.0] {
                             b' ' | b'\r' | b'\n' | b'\t' | b'(' => {
                                 enddelim = b')';
@@ -206,6 +213,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn iterates_let_else_stmt() {
+        let src = rejustify(
+            "
+            let Some(a) = foo() else { return; };
+            let b = a;
+        ",
+        );
+        let mut it = iter_stmts(src.as_ref());
+        assert_eq!(
+            "let Some(a) = foo() else { return; };",
+            slice(&src, it.next().unwrap())
+        );
+        assert_eq!("let b = a;", slice(&src, it.next().unwrap()));
+    }
+
     #[test]
     fn iterates_while_stmt() {
         let src = rejustify(
@@ -306,6 +329,27 @@ mod test {
         assert_eq!("#[test]", slice(&src, it.next().unwrap()));
     }
 
+    #[test]
+    fn iterates_attribute_containing_macro_call() {
+        let src = rejustify(
+            "
+            #[doc = include_str!(\"README.md\")]
+            #[tracing::instrument(fields(a = {}))]
+            fn foo() {}
+        ",
+        );
+        let mut it = iter_stmts(src.as_ref());
+        assert_eq!(
+            "#[doc = include_str!(\"README.md\")]",
+            slice(&src, it.next().unwrap())
+        );
+        assert_eq!(
+            "#[tracing::instrument(fields(a = {}))]",
+            slice(&src, it.next().unwrap())
+        );
+        assert_eq!("fn foo() {}", slice(&src, it.next().unwrap()));
+    }
+
     #[test]
     fn iterates_half_open_subscope_if_is_the_last_thing() {
         let src = "
@@ -1,18 +1,21 @@
 use crate::ast_types::{GenericsArgs, ImplHeader, Pat, TraitBounds, Ty, TypeParameter};
+use crate::cfg::{CfgFilter, CfgOptions};
 use crate::codecleaner;
 use crate::codeiter::StmtIndicesIter;
 use crate::matchers::ImportInfo;
+use crate::nameres::{MacroFiles, PreludeKind};
 use crate::project_model::ProjectModelProvider;
 use rls_span;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
 use std::io;
 use std::io::Read;
 use std::iter::{Fuse, Iterator};
 use std::ops::{Deref, Range};
 use std::rc::Rc;
+use std::time::SystemTime;
 use std::{fmt, vec};
 use std::{path, str};
 use rustc_span::source_map;
@@ -99,6 +102,21 @@ impl fmt::Display for MatchType {
     }
 }
 
+/// Visibility of an item or a field, as written in its declaration
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// No visibility, or `pub(self)`
+    Private,
+    /// `pub`
+    Public,
+    /// `pub(crate)`, or `crate`
+    Crate,
+    /// `pub(super)`
+    Super,
+    /// `pub(in path)`, with the path as written
+    InPath(String),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SearchType {
     ExactMatch,
@@ -313,6 +331,7 @@ pub struct Match {
     pub mtype: MatchType,
     pub contextstr: String,
     pub docs: String,
+    pub visibility: Visibility,
 }
 
 impl Match {
@@ -363,13 +382,14 @@ impl Match {
             MatchType::Struct(ref mut gen_arg) | MatchType::Enum(ref mut gen_arg) => {
                 gen_arg.apply_types(types);
             }
+            // method-level type parameters are resolved in `get_return_type_of_function_call`
             _ => {}
         };
     }
-    // currently we can't resolve method's type parameter
     pub(crate) fn generics_mut(&mut self) -> impl Iterator<Item = &mut TypeParameter> {
         let opt = match &mut self.mtype {
             MatchType::Struct(gen_arg) | MatchType::Enum(gen_arg) => Some(&mut **gen_arg),
+            MatchType::Method(Some(gen_arg)) => Some(&mut **gen_arg),
             _ => None,
         };
         opt.into_iter().flat_map(|gen_arg| gen_arg.args_mut())
@@ -429,12 +449,13 @@ impl fmt::Debug for Match {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "Match [{:?}, {:?}, {:?}, {:?}, {:?}, |{}|]",
+            "Match [{:?}, {:?}, {:?}, {:?}, {:?}, {:?}, |{}|]",
             self.matchstr,
             self.filepath.display(),
             self.point,
             self.local,
             self.mtype,
+            self.visibility,
             self.contextstr
         )
     }
@@ -713,6 +734,12 @@ pub struct FileCache {
     /// aren't found when scanning the source for signatures.
     masked_map: RefCell<HashMap<path::PathBuf, Rc<MaskedSource>>>,
 
+    /// number of cached files below each directory
+    ///
+    /// so that directories only made of cached files are found without
+    /// scanning all cached paths.
+    cached_dirs: RefCell<HashMap<path::PathBuf, usize>>,
+
     /// The file loader
     pub(crate) loader: Box<dyn FileLoader>,
 }
@@ -721,9 +748,38 @@ pub struct FileCache {
 ///
 /// Implement one of these and pass it to `FileCache::new()` to override Racer's
 /// file loading behavior.
+///
+/// Besides loading files, racer uses the loader to check for files and directories
+/// and to list directories, so a loader overriding all methods can serve a virtual tree.
+/// The default implementations of these query the disk.
+///
+/// Locating the standard library sources (`RUST_SRC_PATH` or the sysroot) is outside
+/// of this abstraction and always queries the disk, as does `cargo metadata`.
 pub trait FileLoader {
     /// Load a single file
     fn load_file(&self, path: &path::Path) -> io::Result<String>;
+
+    /// Checks if a file or a directory exists at `path`
+    fn exists(&self, path: &path::Path) -> bool {
+        path.exists()
+    }
+
+    /// Checks if `path` is a directory
+    fn is_dir(&self, path: &path::Path) -> bool {
+        path.is_dir()
+    }
+
+    /// Lists the paths of the entries in the directory `path`
+    fn read_dir(&self, path: &path::Path) -> io::Result<Vec<path::PathBuf>> {
+        fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect()
+    }
+
+    /// Returns the last modification time of `path`, if known
+    fn modified(&self, path: &path::Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
 }
 
 /// Provide a blanket impl for Arc<T> since Rls uses that
@@ -731,6 +787,22 @@ impl<T: FileLoader> FileLoader for ::std::sync::Arc<T> {
     fn load_file(&self, path: &path::Path) -> io::Result<String> {
         (&self as &T).load_file(path)
     }
+
+    fn exists(&self, path: &path::Path) -> bool {
+        (&self as &T).exists(path)
+    }
+
+    fn is_dir(&self, path: &path::Path) -> bool {
+        (&self as &T).is_dir(path)
+    }
+
+    fn read_dir(&self, path: &path::Path) -> io::Result<Vec<path::PathBuf>> {
+        (&self as &T).read_dir(path)
+    }
+
+    fn modified(&self, path: &path::Path) -> Option<SystemTime> {
+        (&self as &T).modified(path)
+    }
 }
 
 /// The default file loader
@@ -772,6 +844,7 @@ impl FileCache {
         FileCache {
             raw_map: RefCell::new(HashMap::new()),
             masked_map: RefCell::new(HashMap::new()),
+            cached_dirs: RefCell::new(HashMap::new()),
             loader: Box::new(loader),
         }
     }
@@ -781,9 +854,32 @@ impl FileCache {
     /// Returns true if a file was removed
     pub fn remove_file<P: AsRef<path::Path>>(&self, path: &P) -> bool {
         let path = path.as_ref();
-        let mut raw = self.raw_map.borrow_mut();
-        let mut masked = self.masked_map.borrow_mut();
-        raw.remove(path).is_some() || masked.remove(path).is_some()
+        let removed_raw = self.raw_map.borrow_mut().remove(path).is_some();
+        if removed_raw {
+            let mut dirs = self.cached_dirs.borrow_mut();
+            for dir in path.ancestors().skip(1) {
+                if let Some(count) = dirs.get_mut(dir) {
+                    *count -= 1;
+                    if *count == 0 {
+                        dirs.remove(dir);
+                    }
+                }
+            }
+        }
+        let removed_masked = self.masked_map.borrow_mut().remove(path).is_some();
+        removed_raw || removed_masked
+    }
+
+    /// Adds `src` as the raw source of `filepath`, counting it in its directories
+    fn insert_raw(&self, filepath: path::PathBuf, src: Rc<RawSource>) {
+        let mut raw_map = self.raw_map.borrow_mut();
+        if !raw_map.contains_key(&filepath) {
+            let mut dirs = self.cached_dirs.borrow_mut();
+            for dir in filepath.ancestors().skip(1) {
+                *dirs.entry(dir.to_owned()).or_insert(0) += 1;
+            }
+        }
+        raw_map.insert(filepath, src);
     }
 
     /// Add/Replace a file in both versions.
@@ -795,14 +891,53 @@ impl FileCache {
         let pathbuf = filepath.into();
         let src = buf.into();
         let masked_src = MaskedSource::new(&src);
-        self.raw_map
-            .borrow_mut()
-            .insert(pathbuf.clone(), Rc::new(RawSource::new(src)));
+        self.insert_raw(pathbuf.clone(), Rc::new(RawSource::new(src)));
         self.masked_map
             .borrow_mut()
             .insert(pathbuf, Rc::new(masked_src));
     }
 
+    /// Checks if `filepath` is a cached file or a file according to the `FileLoader`
+    fn file_exists(&self, filepath: &path::Path) -> bool {
+        self.raw_map.borrow().contains_key(filepath)
+            || (self.loader.exists(filepath) && !self.loader.is_dir(filepath))
+    }
+
+    /// Checks if `path` is a cached file, a directory containing one, or exists
+    /// according to the `FileLoader`
+    pub(crate) fn exists(&self, path: &path::Path) -> bool {
+        self.raw_map.borrow().contains_key(path) || self.is_dir(path) || self.loader.exists(path)
+    }
+
+    /// Checks if `path` is a directory according to the `FileLoader`, or contains cached files
+    pub(crate) fn is_dir(&self, path: &path::Path) -> bool {
+        self.cached_dirs.borrow().contains_key(path) || self.loader.is_dir(path)
+    }
+
+    /// Returns the last modification time of `path` according to the `FileLoader`
+    pub(crate) fn modified(&self, path: &path::Path) -> Option<SystemTime> {
+        self.loader.modified(path)
+    }
+
+    /// Lists the directory `path` with the `FileLoader`, adding the entries of cached files
+    /// which don't exist there
+    pub(crate) fn read_dir(&self, path: &path::Path) -> Vec<path::PathBuf> {
+        let mut entries = self.loader.read_dir(path).unwrap_or_default();
+        for cached in self.raw_map.borrow().keys() {
+            let first = cached
+                .strip_prefix(path)
+                .ok()
+                .and_then(|rest| rest.components().next());
+            if let Some(first) = first {
+                let entry = path.join(first);
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries
+    }
+
     fn load_file(&self, filepath: &path::Path) -> Rc<RawSource> {
         if let Some(src) = self.raw_map.borrow().get(filepath) {
             return src.clone();
@@ -815,9 +950,7 @@ impl FileCache {
             .load_file(filepath)
             .expect(&format!("Failed load file {:?}", filepath));
         let source = Rc::new(RawSource::new(source));
-        self.raw_map
-            .borrow_mut()
-            .insert(filepath.to_path_buf(), Rc::clone(&source));
+        self.insert_raw(filepath.to_path_buf(), Rc::clone(&source));
         source
     }
 
@@ -851,6 +984,9 @@ pub trait SessionExt {
     fn load_source_file(&self, _: &path::Path) -> Rc<MaskedSource>;
 }
 
+/// Raw and masked source of a macro expansion
+type ExpandedSource = (Rc<RawSource>, Rc<MaskedSource>);
+
 /// Context for a Racer operation
 pub struct Session<'c> {
     /// Cache for files
@@ -860,7 +996,31 @@ pub struct Session<'c> {
     cache: &'c FileCache,
     /// Cache for generic impls
     pub generic_impls: RefCell<HashMap<(path::PathBuf, BytePos), Vec<Rc<ImplHeader>>>>,
+    /// Cache for the `Deref` impl of each type, or `None` if it has none
+    pub(crate) deref_impls: RefCell<HashMap<(path::PathBuf, BytePos), Option<Rc<ImplHeader>>>>,
+    /// Cache for the scopes of all modules of each crate root
+    pub(crate) module_scopes: RefCell<HashMap<path::PathBuf, Rc<Vec<(path::PathBuf, BytePos)>>>>,
+    /// Cache for the prelude of each crate root
+    pub(crate) prelude_kinds: RefCell<HashMap<path::PathBuf, PreludeKind>>,
+    /// Files defining `macro_rules!` macros by name, for each crate root
+    pub(crate) crate_macros: RefCell<HashMap<path::PathBuf, Rc<MacroFiles>>>,
+    /// Macro expansions searched as virtual files, or `None` for invocations which
+    /// couldn't be expanded
+    expansions: RefCell<HashMap<path::PathBuf, Option<ExpandedSource>>>,
+    /// Files included by `include!` which are being searched, to stop at cycles
+    pub(crate) pending_includes: RefCell<Vec<path::PathBuf>>,
     pub project_model: Box<dyn ProjectModelProvider + 'c>,
+    /// Result types of macro calls registered by the user
+    macro_types: RefCell<HashMap<String, String>>,
+    /// Target triple used to evaluate `#[cfg(..)]`, or the host if `None`
+    cfg_target: RefCell<Option<String>>,
+    cfg_filter: Cell<CfgFilter>,
+    /// Cfg options for each manifest
+    cfg_options: RefCell<HashMap<Option<path::PathBuf>, Rc<CfgOptions>>>,
+    /// Matches of the current query returned in spite of being disabled by `#[cfg(..)]`
+    cfg_disabled: RefCell<HashSet<(path::PathBuf, BytePos)>>,
+    /// Location of the current query, where traits in scope are looked up
+    cursor: RefCell<Option<(path::PathBuf, BytePos)>>,
 }
 
 impl<'c> fmt::Debug for Session<'c> {
@@ -887,7 +1047,31 @@ impl<'c> Session<'c> {
     /// [`FileCache`]: struct.FileCache.html
     #[cfg(feature = "metadata")]
     pub fn new(cache: &'c FileCache, project_path: Option<&path::Path>) -> Session<'c> {
-        let project_model = crate::metadata::project_model(project_path);
+        let project_model = crate::metadata::project_model(cache, project_path);
+        Session::with_project_model(cache, project_model)
+    }
+
+    /// Like `Session::new`, but resolves the project with `features` instead of
+    /// all features of the package, so that `#[cfg(feature = "..")]` is evaluated
+    /// against them
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate racer;
+    ///
+    /// let cache = racer::FileCache::default();
+    /// let features = racer::Features::NoDefault(vec!["std".to_owned()]);
+    /// let session = racer::Session::with_features(&cache, None, features);
+    /// ```
+    #[cfg(feature = "metadata")]
+    pub fn with_features(
+        cache: &'c FileCache,
+        project_path: Option<&path::Path>,
+        features: crate::metadata::Features,
+    ) -> Session<'c> {
+        let project_model =
+            crate::metadata::project_model_with_features(cache, project_path, features);
         Session::with_project_model(cache, project_model)
     }
 
@@ -898,7 +1082,19 @@ impl<'c> Session<'c> {
         Session {
             cache,
             generic_impls: Default::default(),
+            deref_impls: Default::default(),
+            module_scopes: Default::default(),
+            prelude_kinds: Default::default(),
+            crate_macros: Default::default(),
+            expansions: Default::default(),
+            pending_includes: Default::default(),
             project_model,
+            macro_types: Default::default(),
+            cfg_target: Default::default(),
+            cfg_filter: Default::default(),
+            cfg_options: Default::default(),
+            cfg_disabled: Default::default(),
+            cursor: Default::default(),
         }
     }
     /// Specify the contents of a file to be used in completion operations
@@ -923,21 +1119,180 @@ impl<'c> Session<'c> {
         self.cache.cache_file_contents(filepath, buf);
     }
 
+    /// Specify the result type of calling a macro
+    ///
+    /// `result_type` is written as a Rust type, e.g. `std::string::String` or
+    /// `&'static str`. Use `!` for macros which never return.
+    /// Registered types take precedence over racer's builtin table for std macros.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate racer;
+    ///
+    /// let cache = racer::FileCache::default();
+    /// let session = racer::Session::new(&cache, None);
+    ///
+    /// session.add_macro_type("json", "serde_json::Value");
+    /// ```
+    pub fn add_macro_type<N, T>(&self, name: N, result_type: T)
+    where
+        N: Into<String>,
+        T: Into<String>,
+    {
+        let name = name.into();
+        let name = name.trim_end_matches('!').to_owned();
+        self.macro_types
+            .borrow_mut()
+            .insert(name, result_type.into());
+    }
+
+    pub(crate) fn macro_type(&self, name: &str) -> Option<String> {
+        self.macro_types.borrow().get(name).cloned()
+    }
+
+    /// Specify the target triple `#[cfg(..)]` attributes are evaluated for
+    ///
+    /// By default, the host is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate racer;
+    ///
+    /// let cache = racer::FileCache::default();
+    /// let session = racer::Session::new(&cache, None);
+    ///
+    /// session.set_cfg_target("x86_64-pc-windows-msvc");
+    /// ```
+    pub fn set_cfg_target<T: Into<String>>(&self, triple: T) {
+        *self.cfg_target.borrow_mut() = Some(triple.into());
+        self.cfg_options.borrow_mut().clear();
+    }
+
+    /// Specify how items disabled by `#[cfg(..)]` are treated
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate racer;
+    ///
+    /// let cache = racer::FileCache::default();
+    /// let session = racer::Session::new(&cache, None);
+    ///
+    /// session.set_cfg_filter(racer::CfgFilter::Dim);
+    /// ```
+    pub fn set_cfg_filter(&self, filter: CfgFilter) {
+        self.cfg_filter.set(filter);
+    }
+
+    pub(crate) fn cfg_filter(&self) -> CfgFilter {
+        self.cfg_filter.get()
+    }
+
+    /// Checks if `m` is disabled by `#[cfg(..)]`
+    ///
+    /// Only matches returned with `CfgFilter::Dim` by the last query are reported.
+    pub fn is_disabled_by_cfg(&self, m: &Match) -> bool {
+        self.cfg_disabled
+            .borrow()
+            .contains(&(m.filepath.clone(), m.point))
+    }
+
+    /// Resets the state of the previous query, for a query at `cursor` if it has one
+    pub(crate) fn start_query(&self, cursor: Option<(&path::Path, BytePos)>) {
+        *self.cursor.borrow_mut() = cursor.map(|(filepath, pos)| (filepath.to_owned(), pos));
+        self.cfg_disabled.borrow_mut().clear();
+    }
+
+    pub(crate) fn cursor(&self) -> Option<(path::PathBuf, BytePos)> {
+        self.cursor.borrow().clone()
+    }
+
+    pub(crate) fn mark_disabled_by_cfg(&self, m: &Match) {
+        self.cfg_disabled
+            .borrow_mut()
+            .insert((m.filepath.clone(), m.point));
+    }
+
+    pub(crate) fn cfg_options(&self, filepath: &path::Path) -> Rc<CfgOptions> {
+        let manifest = self.project_model.discover_project_manifest(filepath);
+        if let Some(opts) = self.cfg_options.borrow().get(&manifest) {
+            return Rc::clone(opts);
+        }
+        let target = self.cfg_target.borrow().clone();
+        let opts = Rc::new(crate::cfg::options_for(
+            manifest.as_ref().map(|m| m.as_path()),
+            target.as_ref().map(|t| t.as_str()),
+            self,
+        ));
+        self.cfg_options
+            .borrow_mut()
+            .insert(manifest, Rc::clone(&opts));
+        opts
+    }
+
+    /// Checks if `path` is a file, in the cache or according to the `FileLoader`
+    pub(crate) fn file_exists(&self, path: &path::Path) -> bool {
+        self.cache.file_exists(path)
+    }
+
+    /// Checks if `path` is a file or a directory, in the cache or according to the `FileLoader`
+    pub(crate) fn exists(&self, path: &path::Path) -> bool {
+        self.cache.exists(path)
+    }
+
+    /// Checks if `path` is a directory, in the cache or according to the `FileLoader`
+    pub(crate) fn is_dir(&self, path: &path::Path) -> bool {
+        self.cache.is_dir(path)
+    }
+
+    /// Lists the entries of the directory `path`, including the cached files in it
+    pub(crate) fn read_dir(&self, path: &path::Path) -> Vec<path::PathBuf> {
+        self.cache.read_dir(path)
+    }
+
     pub fn contains_file<P: AsRef<path::Path>>(&self, path: P) -> bool {
         let path = path.as_ref();
         let raw = self.cache.raw_map.borrow();
         let masked = self.cache.masked_map.borrow();
         raw.contains_key(path) && masked.contains_key(path)
     }
+
+    /// Stores the expansion of a macro invocation as the virtual file `path`, or `None` if it
+    /// couldn't be expanded. Unlike files in the `FileCache`, it's dropped with the session.
+    pub(crate) fn cache_expansion(&self, path: path::PathBuf, code: Option<String>) {
+        let sources = code.map(|code| {
+            let masked = MaskedSource::new(&code);
+            (Rc::new(RawSource::new(code)), Rc::new(masked))
+        });
+        self.expansions.borrow_mut().insert(path, sources);
+    }
+
+    /// Returns if the invocation expanded to `path` could be expanded, or `None` if it
+    /// wasn't tried yet
+    pub(crate) fn has_expansion(&self, path: &path::Path) -> Option<bool> {
+        self.expansions
+            .borrow()
+            .get(path)
+            .map(|sources| sources.is_some())
+    }
+
+    fn expansion(&self, path: &path::Path) -> Option<ExpandedSource> {
+        self.expansions.borrow().get(path).cloned().flatten()
+    }
 }
 
 impl<'c> SessionExt for Session<'c> {
     fn load_raw_file(&self, filepath: &path::Path) -> Rc<RawSource> {
-        self.cache.load_file(filepath)
+        match self.expansion(filepath) {
+            Some((raw, _)) => raw,
+            None => self.cache.load_file(filepath),
+        }
     }
 
     fn load_raw_src_ranged(&self, src: &Src<'_>, filepath: &path::Path) -> RangedRawSrc {
-        let inner = self.cache.load_file(filepath);
+        let inner = self.load_raw_file(filepath);
         RangedRawSrc {
             inner,
             range: src.range,
@@ -945,7 +1300,10 @@ impl<'c> SessionExt for Session<'c> {
     }
 
     fn load_source_file(&self, filepath: &path::Path) -> Rc<MaskedSource> {
-        self.cache.load_file_and_mask_comments(filepath)
+        match self.expansion(filepath) {
+            Some((_, masked)) => masked,
+            None => self.cache.load_file_and_mask_comments(filepath),
+        }
     }
 }
 
@@ -1014,6 +1372,7 @@ fn complete_fully_qualified_name_(
     path: &path::Path,
     session: &Session<'_>,
 ) -> Vec<Match> {
+    session.start_query(None);
     let p: Vec<&str> = query.split("::").collect();
 
     let mut matches = Vec::new();
@@ -1110,7 +1469,9 @@ fn complete_from_file_(
             return Vec::new();
         }
     };
+    session.start_query(Some((filepath, pos)));
     let start = scopes::get_start_of_search_expr(src_text, pos);
+    let start = scopes::get_start_of_qualified_path(src_text, start);
     let expr = &src_text[start.0..pos.0];
     let (contextstr, searchstr, completetype) = scopes::split_into_context_and_completion(expr);
 
@@ -1153,7 +1514,7 @@ fn complete_from_file_(
             } else if let Some(str_path) = scopes::is_in_struct_ctor(src.as_src(), *stmtstart, pos)
             {
                 let path = scopes::expr_to_path(&src[str_path.to_range()]).0;
-                return nameres::get_struct_fields(
+                let mut fields = nameres::get_struct_fields(
                     &path,
                     searchstr,
                     filepath,
@@ -1161,6 +1522,8 @@ fn complete_from_file_(
                     SearchType::StartsWith,
                     session,
                 );
+                nameres::retain_accessible(&mut fields, filepath, pos, session);
+                return fields;
             } else {
                 scopes::expr_to_path(expr)
             };
@@ -1174,6 +1537,10 @@ fn complete_from_file_(
                 session,
                 &ImportInfo::default(),
             ));
+            // names in scope are accessible, but items reached through a path may not be
+            if !path.is_single() || path.prefix.is_some() {
+                nameres::retain_accessible(&mut out, filepath, pos, session);
+            }
         }
         CompletionType::Field => {
             let context = ast::get_type_of(contextstr.to_owned(), filepath, pos, session);
@@ -1185,6 +1552,7 @@ fn complete_from_file_(
                     SearchType::StartsWith,
                     session,
                 ));
+                nameres::retain_accessible(&mut out, filepath, pos, session);
             }
         }
     }
@@ -1318,6 +1686,7 @@ pub fn find_definition_(
             return None;
         }
     };
+    session.start_query(Some((filepath, pos)));
 
     // Make sure `src` is in the cache
     let range = scopes::expand_search_expr(src_txt, pos);
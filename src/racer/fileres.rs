@@ -1,7 +1,11 @@
-use crate::core::{BytePos, Coordinate, Match, MatchType, SearchType, Session, SessionExt};
+use crate::core::{
+    BytePos, Coordinate, Match, MatchType, SearchType, Session, SessionExt, Visibility,
+};
 use crate::matchers;
 use crate::nameres::RUST_SRC_PATH;
 use crate::project_model::Edition;
+use crate::scopes;
+use crate::util::{attribute_path, outer_attribute_ranges};
 use std::path::{Path, PathBuf};
 
 /// get crate file from current path & crate name
@@ -15,12 +19,12 @@ pub fn get_std_file(name: &str, session: &Session<'_>) -> Option<PathBuf> {
         // try lib<name>/lib.rs, like in the rust source dir
         let cratelibname = format!("lib{}", name);
         let filepath = std_path.join(cratelibname).join("lib.rs");
-        if filepath.exists() || session.contains_file(&filepath) {
+        if session.file_exists(&filepath) {
             return Some(filepath);
         }
         // If not found, try using the new standard library directory layout
         let filepath = std_path.join(name).join("src").join("lib.rs");
-        if filepath.exists() || session.contains_file(&filepath) {
+        if session.file_exists(&filepath) {
             return Some(filepath);
         }
     }
@@ -71,6 +75,7 @@ pub fn search_crate_names(
                 mtype: MatchType::Crate,
                 contextstr: String::new(),
                 docs: matchers::find_mod_doc(&raw_src, BytePos::ZERO),
+                visibility: Visibility::Public,
             }
         })
         .collect()
@@ -80,17 +85,80 @@ pub fn search_crate_names(
 pub fn get_module_file(name: &str, parentdir: &Path, session: &Session<'_>) -> Option<PathBuf> {
     // try just <name>.rs
     let filepath = parentdir.join(format!("{}.rs", name));
-    if filepath.exists() || session.contains_file(&filepath) {
+    if session.file_exists(&filepath) {
         return Some(filepath);
     }
     // try <name>/mod.rs
     let filepath = parentdir.join(name).join("mod.rs");
-    if filepath.exists() || session.contains_file(&filepath) {
+    if session.file_exists(&filepath) {
         return Some(filepath);
     }
     None
 }
 
+/// Resolves the file of the module declared by `mod name;` at `decl_point` in `filepath`.
+///
+/// This follows rustc's rules:
+/// - Submodules of a "mod-rs" file (`mod.rs`, `lib.rs` or `main.rs`) live in the directory
+///   of the file, and submodules of other files like `foo.rs` live in `foo/`.
+/// - Inline modules around the declaration add their names as directories, so `b` in
+///   `mod a { mod b; }` is `a/b.rs` or `a/b/mod.rs` relative to that directory.
+/// - `#[path = ".."]` on the declaration is relative to the directory of `filepath`,
+///   or to the directory of the inline modules around it, computed as above.
+///
+/// Racer doesn't know which files are crate roots (e.g. `src/bin/foo.rs` or `tests/foo.rs`),
+/// so other files fall back to the rules of mod-rs files if the module isn't found.
+/// Files are looked up in the `FileCache`, so modules which only exist in the editor
+/// buffer are found.
+pub(crate) fn resolve_module_file(
+    name: &str,
+    decl_point: BytePos,
+    filepath: &Path,
+    session: &Session<'_>,
+) -> Option<PathBuf> {
+    let msrc = session.load_source_file(filepath);
+    let raw_src = session.load_raw_file(filepath);
+    let inline_path = scopes::get_local_module_path(msrc.as_src(), decl_point);
+    let path_attr = outer_attribute_ranges(&msrc, decl_point)
+        .into_iter()
+        .find_map(|range| path_attribute_value(&raw_src[range.to_range()]));
+    let file_dir = filepath.parent()?;
+    let mut module_dirs = vec![file_dir.to_owned()];
+    if !is_mod_rs(filepath) {
+        module_dirs.insert(0, file_dir.join(filepath.file_stem()?));
+    }
+    debug!(
+        "[resolve_module_file] {} in {:?}, inline: {:?}, path: {:?}",
+        name, filepath, inline_path, path_attr
+    );
+    module_dirs.into_iter().find_map(|mut dir| {
+        dir.extend(&inline_path);
+        match path_attr {
+            Some(path) if inline_path.is_empty() => {
+                Some(file_dir.join(path)).filter(|p| session.file_exists(p))
+            }
+            Some(path) => Some(dir.join(path)).filter(|p| session.file_exists(p)),
+            None => get_module_file(name, &dir, session),
+        }
+    })
+}
+
+fn is_mod_rs(filepath: &Path) -> bool {
+    ["mod.rs", "lib.rs", "main.rs"]
+        .iter()
+        .any(|name| filepath.file_name().map_or(false, |f| f == *name))
+}
+
+/// Returns `p` from the contents of `#[path = "p"]`
+fn path_attribute_value(attr: &str) -> Option<&str> {
+    let attr = attr.trim();
+    if attribute_path(attr) != "path" {
+        return None;
+    }
+    let value = attr[4..].trim_start().strip_prefix('=')?.trim();
+    value.strip_prefix('"')?.strip_suffix('"')
+}
+
 /// try to get outer crates
 /// if we have dependencies in cache, use it.
 /// else, call cargo-metadata(default) or fall back to rls
@@ -18,10 +18,13 @@ mod testutils;
 mod util;
 mod ast;
 mod ast_types;
+mod attr_macros;
+mod cfg;
 mod codecleaner;
 mod codeiter;
 mod core;
 mod fileres;
+mod macros;
 mod matchers;
 #[cfg(feature = "metadata")]
 mod metadata;
@@ -33,13 +36,17 @@ mod snippets;
 mod typeinf;
 
 pub use crate::ast_types::PathSearch;
+pub use crate::cfg::CfgFilter;
 pub use crate::core::{
     complete_from_file, complete_fully_qualified_name, find_definition, is_use_stmt, to_coords,
     to_point,
 };
 pub use crate::core::{
     BytePos, ByteRange, Coordinate, FileCache, FileLoader, Location, Match, MatchType, Session,
+    Visibility,
 };
+#[cfg(feature = "metadata")]
+pub use crate::metadata::Features;
 pub use crate::primitive::PrimKind;
 pub use crate::project_model::{Edition, ProjectModelProvider};
 pub use crate::snippets::snippet_for_match;
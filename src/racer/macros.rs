@@ -0,0 +1,1007 @@
+//! A lightweight expander for `macro_rules!` macros defined in the crate.
+//!
+//! It only aims to find items generated by macros, so hygiene, fragment
+//! specifiers' exact grammar and backtracking are not supported.
+use crate::core::{BytePos, Session, SessionExt};
+use crate::nameres;
+use crate::util;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Maximum depth of nested macro invocations, like rustc's `recursion_limit`
+const RECURSION_LIMIT: usize = 32;
+
+/// Maximum number of macros expanded for one invocation
+const MAX_EXPANSIONS: usize = 1024;
+
+/// Maximum number of tokens produced for one invocation
+const MAX_TOKENS: usize = 1 << 16;
+
+/// Macros of std and builtin ones, which are never expanded
+const STD_MACROS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "cfg",
+    "column",
+    "compile_error",
+    "concat",
+    "dbg",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "env",
+    "eprint",
+    "eprintln",
+    "file",
+    "format",
+    "format_args",
+    "include_bytes",
+    "include_str",
+    "line",
+    "matches",
+    "module_path",
+    "option_env",
+    "panic",
+    "print",
+    "println",
+    "stringify",
+    "thread_local",
+    "todo",
+    "unimplemented",
+    "unreachable",
+    "vec",
+    "write",
+    "writeln",
+];
+
+/// Suffix of the virtual files expansions are cached in
+const EXPANSION_SUFFIX: &str = ".racer-expansion.rs";
+
+const MULTI_CHAR_PUNCTS: [&str; 24] = [
+    "<<=", ">>=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenTree {
+    Token(String),
+    /// tokens surrounded by the delimiter, which is one of `(`, `[` or `{`
+    Group(char, Vec<TokenTree>),
+}
+
+fn closing_delim(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+fn is_ident(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+fn is_literal(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_digit() || c == '"')
+        || (token.starts_with('\'') && token.len() > 2 && token.ends_with('\''))
+        || token.starts_with("b\"")
+        || token.starts_with("b'")
+        || token.starts_with("r\"")
+        || token.starts_with("r#\"")
+        || token == "true"
+        || token == "false"
+}
+
+fn is_repetition_op(token: &str) -> bool {
+    token == "*" || token == "+" || token == "?"
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str, pos: usize) -> Self {
+        Lexer { src, pos }
+    }
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or_else(|| trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                self.pos += trimmed.find("*/").map_or(trimmed.len(), |n| n + 2);
+            } else {
+                return;
+            }
+        }
+    }
+    /// Lexes a token or a delimited group of tokens
+    fn next_tree(&mut self) -> Option<TokenTree> {
+        self.skip_trivia();
+        let c = self.rest().chars().next()?;
+        match c {
+            '(' | '[' | '{' => {
+                self.pos += 1;
+                let mut inner = Vec::new();
+                loop {
+                    self.skip_trivia();
+                    match self.rest().chars().next() {
+                        Some(close) if close == closing_delim(c) => {
+                            self.pos += 1;
+                            break;
+                        }
+                        Some(_) => inner.push(self.next_tree()?),
+                        None => break,
+                    }
+                }
+                Some(TokenTree::Group(c, inner))
+            }
+            _ => self.next_token().map(TokenTree::Token),
+        }
+    }
+    fn next_token(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let c = rest.chars().next()?;
+        let len = if c.is_alphabetic() || c == '_' {
+            let ident_len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or_else(|| rest.len());
+            let prefix = &rest[..ident_len];
+            let after = &rest[ident_len..];
+            match prefix {
+                "r" | "br" if after.starts_with('"') || after.starts_with("#\"") => {
+                    ident_len + raw_str_len(after)?
+                }
+                "r" if after.starts_with('#') => {
+                    // raw identifier like `r#type`
+                    let raw = &after[1..];
+                    1 + ident_len
+                        + raw
+                            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                            .unwrap_or_else(|| raw.len())
+                }
+                "b" if after.starts_with('"') => ident_len + quoted_len(after, '"')?,
+                "b" if after.starts_with('\'') => ident_len + quoted_len(after, '\'')?,
+                _ => ident_len,
+            }
+        } else if c.is_ascii_digit() {
+            let mut len = 0;
+            let bytes = rest.as_bytes();
+            while len < bytes.len() {
+                let b = bytes[len];
+                let is_fraction =
+                    b == b'.' && bytes.get(len + 1).map_or(false, |b| b.is_ascii_digit());
+                if !(b.is_ascii_alphanumeric() || b == b'_' || is_fraction) {
+                    break;
+                }
+                len += 1;
+            }
+            len
+        } else if c == '"' {
+            quoted_len(rest, '"')?
+        } else if c == '\'' {
+            let mut chars = rest.char_indices().skip(1);
+            match (chars.next(), chars.next()) {
+                (Some((_, '\\')), _) => quoted_len(rest, '\'')?,
+                (Some(_), Some((i, '\''))) => i + 1,
+                _ => {
+                    // lifetime
+                    1 + rest[1..]
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or_else(|| rest.len() - 1)
+                }
+            }
+        } else {
+            MULTI_CHAR_PUNCTS
+                .iter()
+                .find(|punct| rest.starts_with(*punct))
+                .map_or(c.len_utf8(), |punct| punct.len())
+        };
+        let token = rest[..len].to_owned();
+        self.pos += len;
+        Some(token)
+    }
+}
+
+/// Length of a string or char literal starting with `quote`
+fn quoted_len(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            _ if c == quote && !escaped => return Some(i + 1),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Length of a raw string literal like `#"..."#`, without the `r` prefix
+fn raw_str_len(s: &str) -> Option<usize> {
+    let hashes = s.len() - s.trim_start_matches('#').len();
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let body_start = hashes + 1;
+    s[body_start..]
+        .find(&closing)
+        .map(|n| body_start + n + closing.len())
+}
+
+fn tokenize(src: &str) -> Vec<TokenTree> {
+    let mut lexer = Lexer::new(src, 0);
+    let mut tts = Vec::new();
+    while let Some(tt) = lexer.next_tree() {
+        tts.push(tt);
+    }
+    tts
+}
+
+fn write_tokens(tts: &[TokenTree], out: &mut String) {
+    for tt in tts {
+        match tt {
+            TokenTree::Token(token) => out.push_str(token),
+            TokenTree::Group(open, inner) => {
+                out.push(*open);
+                out.push(' ');
+                write_tokens(inner, out);
+                out.push(closing_delim(*open));
+            }
+        }
+        out.push(' ');
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Matcher {
+    Token(String),
+    /// `$name:kind`
+    Fragment(String, String),
+    Group(char, Vec<Matcher>),
+    /// `$( ... ) sep op`
+    Repeat {
+        matchers: Vec<Matcher>,
+        separator: Option<String>,
+        op: char,
+    },
+}
+
+#[derive(Debug)]
+struct MacroRule {
+    pattern: Vec<Matcher>,
+    body: Vec<TokenTree>,
+}
+
+/// Returns the separator, the operator and the number of tokens consumed
+/// for a repetition `$( ... ) sep op` starting at `tts[i]`
+fn parse_repetition(tts: &[TokenTree], i: usize) -> (Option<String>, char, usize) {
+    match (tts.get(i + 2), tts.get(i + 3)) {
+        (Some(TokenTree::Token(op)), _) if is_repetition_op(op) => {
+            (None, op.chars().next().unwrap_or('*'), 3)
+        }
+        (Some(TokenTree::Token(sep)), Some(TokenTree::Token(op))) if is_repetition_op(op) => {
+            (Some(sep.to_owned()), op.chars().next().unwrap_or('*'), 4)
+        }
+        _ => (None, '*', 2),
+    }
+}
+
+fn parse_matchers(tts: &[TokenTree]) -> Vec<Matcher> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < tts.len() {
+        match &tts[i] {
+            TokenTree::Token(dollar) if dollar == "$" => {
+                match (tts.get(i + 1), tts.get(i + 2), tts.get(i + 3)) {
+                    (
+                        Some(TokenTree::Token(name)),
+                        Some(TokenTree::Token(colon)),
+                        Some(TokenTree::Token(kind)),
+                    ) if colon == ":" => {
+                        res.push(Matcher::Fragment(name.to_owned(), kind.to_owned()));
+                        i += 4;
+                    }
+                    (Some(TokenTree::Group('(', inner)), _, _) => {
+                        let (separator, op, consumed) = parse_repetition(tts, i);
+                        res.push(Matcher::Repeat {
+                            matchers: parse_matchers(inner),
+                            separator,
+                            op,
+                        });
+                        i += consumed;
+                    }
+                    _ => {
+                        res.push(Matcher::Token(dollar.to_owned()));
+                        i += 1;
+                    }
+                }
+            }
+            TokenTree::Token(token) => {
+                res.push(Matcher::Token(token.to_owned()));
+                i += 1;
+            }
+            TokenTree::Group(delim, inner) => {
+                res.push(Matcher::Group(*delim, parse_matchers(inner)));
+                i += 1;
+            }
+        }
+    }
+    res
+}
+
+/// Parses the body of `macro_rules! name { (..) => { .. }; .. }`
+fn parse_rules(tts: &[TokenTree]) -> Vec<MacroRule> {
+    let mut rules = Vec::new();
+    let mut i = 0;
+    while let (Some(TokenTree::Group(_, pattern)), Some(TokenTree::Token(arrow)), Some(body)) =
+        (tts.get(i), tts.get(i + 1), tts.get(i + 2))
+    {
+        let body = match body {
+            TokenTree::Group(_, body) if arrow == "=>" => body,
+            _ => break,
+        };
+        rules.push(MacroRule {
+            pattern: parse_matchers(pattern),
+            body: body.to_owned(),
+        });
+        i += 3;
+        if tts.get(i) == Some(&TokenTree::Token(";".to_owned())) {
+            i += 1;
+        }
+    }
+    rules
+}
+
+#[derive(Clone, Debug)]
+enum Binding {
+    Leaf(Vec<TokenTree>),
+    /// bindings in each iteration of a repetition
+    Seq(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+fn matcher_names(matchers: &[Matcher], names: &mut Vec<String>) {
+    for m in matchers {
+        match m {
+            Matcher::Fragment(name, _) => names.push(name.to_owned()),
+            Matcher::Group(_, inner)
+            | Matcher::Repeat {
+                matchers: inner, ..
+            } => matcher_names(inner, names),
+            Matcher::Token(_) => {}
+        }
+    }
+}
+
+/// Number of token trees a fragment `$x:kind` takes from the head of `tts`.
+/// Fragments like `ty` or `expr` take tokens until `follow` appears.
+fn fragment_len(kind: &str, tts: &[TokenTree], follow: Option<&str>) -> Option<usize> {
+    let token_at = |i: usize| match tts.get(i) {
+        Some(TokenTree::Token(token)) => Some(token.as_str()),
+        _ => None,
+    };
+    match kind {
+        "ident" => token_at(0).filter(|t| is_ident(t)).map(|_| 1),
+        "lifetime" => token_at(0)
+            .filter(|t| t.starts_with('\'') && !is_literal(t))
+            .map(|_| 1),
+        "literal" => match (token_at(0), token_at(1)) {
+            (Some(t), _) if is_literal(t) => Some(1),
+            (Some("-"), Some(t)) if is_literal(t) => Some(2),
+            _ => None,
+        },
+        "tt" => tts.first().map(|_| 1),
+        "block" => match tts.first() {
+            Some(TokenTree::Group('{', _)) => Some(1),
+            _ => None,
+        },
+        "vis" => match (token_at(0), tts.get(1)) {
+            (Some("pub"), Some(TokenTree::Group('(', _))) => Some(2),
+            (Some("pub"), _) => Some(1),
+            _ => Some(0),
+        },
+        "item" => {
+            let end = tts.iter().position(|tt| match tt {
+                TokenTree::Token(t) => t == ";",
+                TokenTree::Group(delim, _) => *delim == '{',
+            })?;
+            Some(end + 1)
+        }
+        _ => {
+            let count_angles = kind == "ty" || kind == "path";
+            let mut depth = 0;
+            let mut len = 0;
+            for tt in tts {
+                if let TokenTree::Token(t) = tt {
+                    if depth <= 0 && Some(t.as_str()) == follow {
+                        break;
+                    }
+                    if count_angles {
+                        match t.as_str() {
+                            "<" => depth += 1,
+                            ">" => depth -= 1,
+                            ">>" => depth -= 2,
+                            _ => {}
+                        }
+                    }
+                }
+                len += 1;
+            }
+            Some(len).filter(|&len| len > 0)
+        }
+    }
+}
+
+/// Matches `matchers` against `tts[pos..]`, and returns the position matching ends
+fn match_tokens<'m>(
+    matchers: &'m [Matcher],
+    tts: &[TokenTree],
+    mut pos: usize,
+    follow: Option<&'m str>,
+    bindings: &mut Bindings,
+) -> Option<usize> {
+    for (i, matcher) in matchers.iter().enumerate() {
+        let next_follow = match matchers.get(i + 1) {
+            Some(Matcher::Token(t)) => Some(t.as_str()),
+            Some(_) => None,
+            None => follow,
+        };
+        match matcher {
+            Matcher::Token(token) => match tts.get(pos) {
+                Some(TokenTree::Token(t)) if t == token => pos += 1,
+                _ => return None,
+            },
+            Matcher::Group(delim, inner) => match tts.get(pos) {
+                Some(TokenTree::Group(d, inner_tts)) if d == delim => {
+                    if match_tokens(inner, inner_tts, 0, None, bindings)? != inner_tts.len() {
+                        return None;
+                    }
+                    pos += 1;
+                }
+                _ => return None,
+            },
+            Matcher::Fragment(name, kind) => {
+                let len = fragment_len(kind, &tts[pos..], next_follow)?;
+                bindings.insert(name.to_owned(), Binding::Leaf(tts[pos..pos + len].to_vec()));
+                pos += len;
+            }
+            Matcher::Repeat {
+                matchers: inner,
+                separator,
+                op,
+            } => {
+                let inner_follow = separator.as_deref().or(next_follow);
+                let mut iterations: Vec<Bindings> = Vec::new();
+                loop {
+                    let mut p = pos;
+                    if let (Some(sep), false) = (separator, iterations.is_empty()) {
+                        match tts.get(p) {
+                            Some(TokenTree::Token(t)) if t == sep => p += 1,
+                            _ => break,
+                        }
+                    }
+                    let mut iteration = Bindings::new();
+                    match match_tokens(inner, tts, p, inner_follow, &mut iteration) {
+                        Some(next) if next > pos => {
+                            iterations.push(iteration);
+                            pos = next;
+                        }
+                        _ => break,
+                    }
+                    if *op == '?' {
+                        break;
+                    }
+                }
+                if *op == '+' && iterations.is_empty() {
+                    return None;
+                }
+                let mut names = Vec::new();
+                matcher_names(inner, &mut names);
+                for name in names {
+                    let seq = iterations
+                        .iter_mut()
+                        .map(|it| it.remove(&name).unwrap_or(Binding::Leaf(Vec::new())))
+                        .collect();
+                    bindings.insert(name, Binding::Seq(seq));
+                }
+            }
+        }
+    }
+    Some(pos)
+}
+
+/// Names of metavariables used in a macro body
+fn metavar_names(tts: &[TokenTree], names: &mut Vec<String>) {
+    for (i, tt) in tts.iter().enumerate() {
+        match tt {
+            TokenTree::Token(dollar) if dollar == "$" => {
+                if let Some(TokenTree::Token(name)) = tts.get(i + 1) {
+                    names.push(name.to_owned());
+                }
+            }
+            TokenTree::Group(_, inner) => metavar_names(inner, names),
+            _ => {}
+        }
+    }
+}
+
+/// Substitutes metavariables in a macro body
+fn transcribe(body: &[TokenTree], bindings: &Bindings, out: &mut Vec<TokenTree>) {
+    let mut i = 0;
+    while i < body.len() {
+        match (&body[i], body.get(i + 1)) {
+            (TokenTree::Token(dollar), Some(TokenTree::Token(name))) if dollar == "$" => {
+                match bindings.get(name) {
+                    Some(Binding::Leaf(tts)) => out.extend(tts.iter().cloned()),
+                    _ if name == "crate" => out.push(TokenTree::Token("crate".to_owned())),
+                    _ => out.extend(body[i..i + 2].iter().cloned()),
+                }
+                i += 2;
+            }
+            (TokenTree::Token(dollar), Some(TokenTree::Group('(', inner))) if dollar == "$" => {
+                let (separator, _, consumed) = parse_repetition(body, i);
+                let mut names = Vec::new();
+                metavar_names(inner, &mut names);
+                let count = names
+                    .iter()
+                    .filter_map(|name| match bindings.get(name) {
+                        Some(Binding::Seq(seq)) => Some(seq.len()),
+                        _ => None,
+                    })
+                    .max()
+                    .unwrap_or(0);
+                for k in 0..count {
+                    let mut iteration = bindings.clone();
+                    for name in &names {
+                        if let Some(Binding::Seq(seq)) = bindings.get(name) {
+                            if let Some(binding) = seq.get(k) {
+                                iteration.insert(name.to_owned(), binding.to_owned());
+                            }
+                        }
+                    }
+                    if let (Some(sep), true) = (&separator, k > 0) {
+                        out.push(TokenTree::Token(sep.to_owned()));
+                    }
+                    transcribe(inner, &iteration, out);
+                }
+                i += consumed;
+            }
+            (TokenTree::Group(delim, inner), _) => {
+                let mut group = Vec::new();
+                transcribe(inner, bindings, &mut group);
+                out.push(TokenTree::Group(*delim, group));
+                i += 1;
+            }
+            (tt, _) => {
+                out.push(tt.to_owned());
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Finds the rules of `macro_rules! name` in `filepath`
+fn find_macro_rules_in_file(
+    name: &str,
+    filepath: &Path,
+    session: &Session<'_>,
+) -> Option<Vec<MacroRule>> {
+    let masked = session.load_source_file(filepath);
+    let raw = session.load_raw_file(filepath);
+    let mut search_from = 0;
+    while let Some(n) = masked[search_from..].find("macro_rules!") {
+        search_from += n + "macro_rules!".len();
+        let mut lexer = Lexer::new(&raw, search_from);
+        match lexer.next_tree() {
+            Some(TokenTree::Token(ref ident)) if ident == name => {}
+            _ => continue,
+        }
+        if let Some(TokenTree::Group(_, body)) = lexer.next_tree() {
+            return Some(parse_rules(&body));
+        }
+    }
+    None
+}
+
+/// Finds the rules of `macro_rules! name` defined in the crate of `filepath`,
+/// preferring a definition in `filepath` itself
+fn find_macro_rules(name: &str, filepath: &Path, session: &Session<'_>) -> Option<Vec<MacroRule>> {
+    let macros = nameres::crate_macro_files(filepath, session)?;
+    let files = macros.get(name)?;
+    let (local, others): (Vec<_>, Vec<_>) = files.iter().partition(|file| *file == filepath);
+    local
+        .into_iter()
+        .chain(others)
+        .find_map(|file| find_macro_rules_in_file(name, file, session))
+}
+
+/// Work left for expanding one invocation, so that macros which grow on each step,
+/// like `() => { m!(); m!(); }`, stop quickly instead of hitting the recursion
+/// limit on every branch
+struct Budget {
+    expansions: usize,
+    tokens: usize,
+    /// set once a limit is reached, which aborts the whole expansion
+    exhausted: bool,
+}
+
+impl Budget {
+    fn new() -> Self {
+        Budget {
+            expansions: MAX_EXPANSIONS,
+            tokens: MAX_TOKENS,
+            exhausted: false,
+        }
+    }
+
+    /// Accounts for one expansion producing `tts`. Returns false if it exceeds the budget.
+    fn spend(&mut self, tts: &[TokenTree]) -> bool {
+        let tokens = count_tokens(tts);
+        if self.expansions == 0 || tokens > self.tokens {
+            self.exhausted = true;
+            return false;
+        }
+        self.expansions -= 1;
+        self.tokens -= tokens;
+        true
+    }
+}
+
+fn count_tokens(tts: &[TokenTree]) -> usize {
+    tts.iter()
+        .map(|tt| match tt {
+            TokenTree::Token(_) => 1,
+            TokenTree::Group(_, inner) => 2 + count_tokens(inner),
+        })
+        .sum()
+}
+
+/// Expands `name!(args)`. Returns `None` if no rule matches or, with
+/// `budget.exhausted` set, if the expansion has to be aborted.
+fn expand_macro(
+    name: &str,
+    args: &[TokenTree],
+    filepath: &Path,
+    session: &Session<'_>,
+    depth: usize,
+    budget: &mut Budget,
+) -> Option<Vec<TokenTree>> {
+    if depth >= RECURSION_LIMIT {
+        debug!("[expand_macro] recursion limit reached for {}!", name);
+        budget.exhausted = true;
+        return None;
+    }
+    let rules = find_macro_rules(name, filepath, session)?;
+    for rule in &rules {
+        let mut bindings = Bindings::new();
+        if match_tokens(&rule.pattern, args, 0, None, &mut bindings) != Some(args.len()) {
+            continue;
+        }
+        let mut expanded = Vec::new();
+        transcribe(&rule.body, &bindings, &mut expanded);
+        if !budget.spend(&expanded) {
+            debug!("[expand_macro] expansion budget exhausted at {}!", name);
+            return None;
+        }
+        return expand_nested(expanded, filepath, session, depth + 1, budget);
+    }
+    debug!("[expand_macro] no rules of {}! matched", name);
+    None
+}
+
+/// Expands macro invocations in item position of `tts`.
+/// Returns `None` if the expansion had to be aborted.
+fn expand_nested(
+    tts: Vec<TokenTree>,
+    filepath: &Path,
+    session: &Session<'_>,
+    depth: usize,
+    budget: &mut Budget,
+) -> Option<Vec<TokenTree>> {
+    let mut res = Vec::new();
+    let mut i = 0;
+    while i < tts.len() {
+        if let (
+            TokenTree::Token(name),
+            Some(TokenTree::Token(bang)),
+            Some(TokenTree::Group(_, args)),
+        ) = (&tts[i], tts.get(i + 1), tts.get(i + 2))
+        {
+            if is_ident(name) && name != "macro_rules" && bang == "!" {
+                match expand_macro(name, args, filepath, session, depth, budget) {
+                    Some(expanded) => {
+                        res.extend(expanded);
+                        i += 3;
+                        if tts.get(i) == Some(&TokenTree::Token(";".to_owned())) {
+                            i += 1;
+                        }
+                        continue;
+                    }
+                    None if budget.exhausted => return None,
+                    None => {}
+                }
+            }
+        }
+        res.push(tts[i].to_owned());
+        i += 1;
+    }
+    Some(res)
+}
+
+/// Returns the name of the macro if `blob` is a macro invocation like `newtype!(Foo);`
+pub(crate) fn invoked_macro(blob: &str) -> Option<&str> {
+    let name_end = match blob.find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        Some(end) if end > 0 => end,
+        _ => return None,
+    };
+    let name = &blob[..name_end];
+    if name == "macro_rules" || !blob[name_end..].starts_with('!') {
+        return None;
+    }
+    let is_invocation = blob[name_end + 1..]
+        .trim_start()
+        .starts_with(|c| c == '(' || c == '[' || c == '{');
+    if is_invocation {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Checks if invocations of `name` in `filepath` are worth expanding, i.e. if it's a
+/// `macro_rules!` macro defined in the crate. Macros of std and builtin ones never are.
+pub(crate) fn is_crate_macro(name: &str, filepath: &Path, session: &Session<'_>) -> bool {
+    !STD_MACROS.contains(&name)
+        && nameres::crate_macro_files(filepath, session)
+            .map_or(false, |macros| macros.contains_key(name))
+}
+
+/// Checks if `path` is a virtual file made by `expand_to_file`
+pub(crate) fn is_expansion_file(path: &Path) -> bool {
+    path.to_str()
+        .map_or(false, |path| path.ends_with(EXPANSION_SUFFIX))
+}
+
+/// Returns the file and the position of the invocation expanded to `path` by `expand_to_file`
+pub(crate) fn expansion_origin(path: &Path) -> Option<(PathBuf, BytePos)> {
+    let name = path.file_name()?.to_str()?.strip_suffix(EXPANSION_SUFFIX)?;
+    let (file_name, point_and_hash) = name.rsplit_once('.')?;
+    let point = point_and_hash.split('-').next()?.parse().ok()?;
+    Some((path.with_file_name(file_name), BytePos(point)))
+}
+
+/// Expands the macro invocation `blob` at `point` in `filepath` into a virtual file
+/// next to `filepath`, so that generated items can be searched like normal source code.
+///
+/// The expansion depends on macros defined anywhere in the crate, so it's only cached
+/// in the session, not in the `FileCache`.
+pub(crate) fn expand_to_file(
+    blob: &str,
+    point: BytePos,
+    filepath: &Path,
+    session: &Session<'_>,
+) -> Option<PathBuf> {
+    let file_name = filepath.file_name()?.to_str()?;
+    let path = filepath.with_file_name(format!(
+        "{}.{}-{:x}{}",
+        file_name,
+        point.0,
+        util::calculate_str_hash(blob),
+        EXPANSION_SUFFIX
+    ));
+    if let Some(expanded) = session.has_expansion(&path) {
+        return if expanded { Some(path) } else { None };
+    }
+    let tts = tokenize(blob);
+    let expanded = match (tts.get(0), tts.get(2)) {
+        (Some(TokenTree::Token(name)), Some(TokenTree::Group(_, args))) => {
+            expand_macro(name, args, filepath, session, 0, &mut Budget::new())
+        }
+        _ => None,
+    };
+    let mut code = String::new();
+    if let Some(expanded) = expanded {
+        write_tokens(&expanded, &mut code);
+    }
+    debug!("[expand_to_file] {} expanded to |{}|", blob, code);
+    if code.trim().is_empty() {
+        session.cache_expansion(path, None);
+        return None;
+    }
+    session.cache_expansion(path.clone(), Some(code));
+    Some(path)
+}
+
+/// Resolves the file included by the `include!` invocation `blob` in `filepath`.
+///
+/// The path can be built with `concat!` and `env!`, as in
+/// `include!(concat!(env!("OUT_DIR"), "/bindings.rs"))`.
+pub(crate) fn include_path(blob: &str, filepath: &Path, session: &Session<'_>) -> Option<PathBuf> {
+    let tts = tokenize(blob);
+    let args = match (tts.get(0), tts.get(1), tts.get(2)) {
+        (
+            Some(TokenTree::Token(name)),
+            Some(TokenTree::Token(bang)),
+            Some(TokenTree::Group(_, args)),
+        ) if name == "include" && bang == "!" => args,
+        _ => return None,
+    };
+    let included = eval_str_expr(args, filepath, session)?;
+    let included = Path::new(&included);
+    let path = if included.is_absolute() {
+        included.to_owned()
+    } else {
+        filepath.parent()?.join(included)
+    };
+    debug!("[include_path] {} includes {:?}", blob, path);
+    if path != filepath && session.file_exists(&path) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Evaluates a string expression made of literals, `concat!` and `env!`
+fn eval_str_expr(tts: &[TokenTree], filepath: &Path, session: &Session<'_>) -> Option<String> {
+    match tts {
+        [TokenTree::Token(lit)] => unquote(lit),
+        [TokenTree::Token(name), TokenTree::Token(bang), TokenTree::Group(_, args)]
+            if bang == "!" =>
+        {
+            let comma = TokenTree::Token(",".to_owned());
+            let mut args = args.split(|tt| *tt == comma).filter(|arg| !arg.is_empty());
+            match name.as_str() {
+                "concat" => args
+                    .map(|arg| eval_str_expr(arg, filepath, session))
+                    .collect(),
+                "env" => {
+                    let var = eval_str_expr(args.next()?, filepath, session)?;
+                    env_var(&var, filepath, session)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the content of a string literal
+fn unquote(lit: &str) -> Option<String> {
+    if lit.starts_with('"') && lit.len() >= 2 && lit.ends_with('"') {
+        Some(lit[1..lit.len() - 1].replace("\\\\", "\\"))
+    } else if lit.starts_with('r') {
+        let body = lit[1..].trim_matches('#');
+        if body.len() >= 2 {
+            Some(body[1..body.len() - 1].to_owned())
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+/// Value of the environment variable `var` set by cargo when compiling `filepath`
+fn env_var(var: &str, filepath: &Path, session: &Session<'_>) -> Option<String> {
+    let manifest = session.project_model.discover_project_manifest(filepath);
+    let from_project = manifest.and_then(|manifest| match var {
+        "OUT_DIR" => session.project_model.out_dir(&manifest),
+        "CARGO_MANIFEST_DIR" => manifest.parent().map(Path::to_owned),
+        _ => None,
+    });
+    match from_project {
+        Some(path) => path.to_str().map(str::to_owned),
+        None => std::env::var(var).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_with(def: &str, invocation: &str) -> Option<String> {
+        let def = tokenize(def);
+        let rules = match def.get(2) {
+            Some(TokenTree::Group(_, body)) => parse_rules(body),
+            _ => return None,
+        };
+        let args = match tokenize(invocation).get(2) {
+            Some(TokenTree::Group(_, args)) => args.to_owned(),
+            _ => return None,
+        };
+        rules.iter().find_map(|rule| {
+            let mut bindings = Bindings::new();
+            if match_tokens(&rule.pattern, &args, 0, None, &mut bindings) != Some(args.len()) {
+                return None;
+            }
+            let mut out = Vec::new();
+            transcribe(&rule.body, &bindings, &mut out);
+            let mut code = String::new();
+            write_tokens(&out, &mut code);
+            Some(code)
+        })
+    }
+
+    #[test]
+    fn tokenizes_literals_and_lifetimes() {
+        let tts = tokenize("'a 'b' \"s\\\"\" r#\"raw\"# 1.0 x::y // comment\n => b'c'");
+        let tokens: Vec<_> = tts
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTree::Token(t) => t,
+                _ => panic!("unexpected group"),
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                "'a",
+                "'b'",
+                "\"s\\\"\"",
+                "r#\"raw\"#",
+                "1.0",
+                "x",
+                "::",
+                "y",
+                "=>",
+                "b'c'"
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_fragments() {
+        let def = "macro_rules! newtype { ($name:ident, $ty:ty) => { struct $name($ty); } }";
+        assert_eq!(
+            expand_with(def, "newtype!(Meters, Vec<f64>)").unwrap(),
+            "struct Meters ( Vec < f64 > ) ; "
+        );
+    }
+
+    #[test]
+    fn expands_repetitions() {
+        let def = "macro_rules! structs { ($($name:ident),* $(,)?) => { $(struct $name;)* } }";
+        assert_eq!(
+            expand_with(def, "structs!(A, B,)").unwrap(),
+            "struct A ; struct B ; "
+        );
+    }
+
+    #[test]
+    fn selects_matching_rule() {
+        let def = "macro_rules! m {
+            (fn $f:ident) => { fn $f() {} };
+            ($s:ident) => { struct $s; }
+        }";
+        assert_eq!(expand_with(def, "m!(Foo)").unwrap(), "struct Foo ; ");
+        assert_eq!(expand_with(def, "m!(fn foo)").unwrap(), "fn foo ( ) { } ");
+    }
+
+    #[test]
+    fn finds_name_of_invoked_macro() {
+        assert_eq!(invoked_macro("newtype!(Foo);"), Some("newtype"));
+        assert_eq!(invoked_macro("items! { struct A; }"), Some("items"));
+        assert_eq!(invoked_macro("macro_rules! m { () => {} }"), None);
+        assert_eq!(invoked_macro("a != b"), None);
+        assert_eq!(invoked_macro("struct Foo;"), None);
+    }
+
+    #[test]
+    fn finds_origin_of_expansion() {
+        let path = Path::new("src/lib.rs.42-1f.racer-expansion.rs.7-a0.racer-expansion.rs");
+        let (origin, point) = expansion_origin(path).unwrap();
+        assert_eq!(point, BytePos(7));
+        assert_eq!(
+            expansion_origin(&origin),
+            Some((PathBuf::from("src/lib.rs"), BytePos(42)))
+        );
+        assert_eq!(expansion_origin(Path::new("src/lib.rs")), None);
+    }
+}
@@ -5,8 +5,8 @@ use crate::core::MatchType::{
 };
 use crate::core::Namespace;
 use crate::core::SearchType::{self, ExactMatch, StartsWith};
-use crate::core::{BytePos, ByteRange, Coordinate, Match, Session, SessionExt, Src};
-use crate::fileres::{get_crate_file, get_module_file};
+use crate::core::{BytePos, ByteRange, Coordinate, Match, Session, SessionExt, Src, Visibility};
+use crate::fileres::{get_crate_file, resolve_module_file};
 use crate::nameres::resolve_path;
 use crate::util::*;
 use crate::{ast, scopes, typeinf};
@@ -95,9 +95,9 @@ fn find_keyword_impl(
     search_type: SearchType,
     is_local: bool,
 ) -> Option<BytePos> {
-    let mut start = BytePos::ZERO;
+    let mut start = skip_attributes(src);
 
-    if let Some(offset) = strip_visibility(&src[..]) {
+    if let Some(offset) = strip_visibility(&src[start.0..]) {
         start += offset;
     } else if !is_local {
         // TODO: too about
@@ -176,6 +176,7 @@ fn match_pattern_start(
                     mtype: mtype,
                     contextstr: first_line(blob),
                     docs: String::new(),
+                    visibility: visibility_of(blob),
                 });
             }
         }
@@ -213,6 +214,7 @@ fn match_let_impl(msrc: &str, context: &MatchCxt<'_, '_>, mtype: MatchType) -> V
                 mtype: mtype.clone(),
                 contextstr: msrc.to_owned(),
                 docs: String::new(),
+                visibility: Visibility::Private,
             });
             if context.search_type == ExactMatch {
                 break;
@@ -232,11 +234,53 @@ pub fn match_while_let(msrc: &str, start: BytePos, context: &MatchCxt<'_, '_>) -
 
 pub fn match_let(msrc: &str, start: BytePos, context: &MatchCxt<'_, '_>) -> Vec<Match> {
     let blob = &msrc[context.range.to_range()];
-    if blob.starts_with("let ") && txt_matches(context.search_type, context.search_str, blob) {
-        match_let_impl(blob, context, Let(start))
-    } else {
-        Vec::new()
+    if !blob.starts_with("let ") || !txt_matches(context.search_type, context.search_str, blob) {
+        return Vec::new();
+    }
+    // our parser doesn't know `let PAT = EXPR else { .. };`,
+    // so treat it as `if let PAT = EXPR {}`
+    if let Some(else_start) = find_let_else(blob) {
+        let let_stmt = blob[..else_start].trim_end();
+        let mut out = match_let_impl(&format!("{};", let_stmt), context, IfLet(start));
+        for m in &mut out {
+            m.contextstr = blob.to_owned();
+        }
+        return out;
+    }
+    match_let_impl(blob, context, Let(start))
+}
+
+/// Finds the `else` of a let-else statement
+pub(crate) fn find_let_else(blob: &str) -> Option<usize> {
+    let bytes = blob.as_bytes();
+    let mut level = 0isize;
+    let mut seen_eq = false;
+    let mut last_non_ws = b' ';
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'(' | b'[' | b'{' => level += 1,
+            b')' | b']' | b'}' => level -= 1,
+            b'=' if level == 0 => seen_eq = true,
+            b'e' if level == 0
+                && seen_eq
+                && blob[i..].starts_with("else")
+                && i > 0
+                && is_whitespace_byte(bytes[i - 1])
+                // `let a = if b { c } else { d };` isn't let-else
+                && last_non_ws != b'}' =>
+            {
+                let rest = blob[i + 4..].trim_start();
+                if rest.starts_with('{') {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        if !is_whitespace_byte(b) {
+            last_non_ws = b;
+        }
     }
+    None
 }
 
 pub fn match_for(msrc: &str, for_start: BytePos, context: &MatchCxt<'_, '_>) -> Vec<Match> {
@@ -257,6 +301,7 @@ pub fn match_for(msrc: &str, for_start: BytePos, context: &MatchCxt<'_, '_>) ->
                 mtype: For(for_start),
                 contextstr: blob.to_owned(),
                 docs: String::new(),
+                visibility: Visibility::Private,
             });
         }
     }
@@ -324,6 +369,7 @@ pub fn match_extern_crate(
                     mtype: Module,
                     contextstr: cratepath.to_str().unwrap().to_owned(),
                     docs: find_mod_doc(&raw_src, BytePos::ZERO),
+                    visibility: Visibility::Public,
                 });
             }
         }
@@ -349,68 +395,15 @@ pub fn match_mod(
             mtype: Module,
             contextstr: context.filepath.to_str().unwrap().to_owned(),
             docs: String::new(),
+            visibility: visibility_of(blob),
         });
     } else {
         debug!("found a module declaration: |{}|", blob);
-        // the name of the file where we found the module declaration (foo.rs)
-        // without its extension!
-        let filename = context.filepath.file_stem()?;
-        let parent_path = context.filepath.parent()?;
-        // if we found the declaration in `src/foo.rs`, then let's look for the
-        // submodule in `src/foo/` as well!
-        let filename_subdir = parent_path.join(filename);
-        // if we are looking for "foo::bar", we have two cases:
-        //   1. we found `pub mod bar;` in either `src/foo/mod.rs`
-        // (or `src/lib.rs`). As such we are going to search for `bar.rs` in
-        // the same directory (`src/foo/`, or `src/` respectively).
-        //   2. we found `pub mod bar;` in `src/foo.rs`. This means that we also
-        // need to seach in `src/foo/` if it exists!
-        let search_path = if filename_subdir.exists() {
-            filename_subdir.as_path()
-        } else {
-            parent_path
-        };
-        match_mod_inner(msrc, context, session, search_path, s)
-    }
-}
-
-fn match_mod_inner(
-    msrc: Src<'_>,
-    context: &MatchCxt<'_, '_>,
-    session: &Session<'_>,
-    search_path: &Path,
-    s: String,
-) -> Option<Match> {
-    let ranged_raw = session.load_raw_src_ranged(&msrc, context.filepath);
-    // get module from path attribute
-    if let Some(modpath) =
-        scopes::get_module_file_from_path(msrc, context.range.start, search_path, ranged_raw)
-    {
-        let doc_src = session.load_raw_file(&modpath);
-        return Some(Match {
-            matchstr: s,
-            filepath: modpath.to_path_buf(),
-            point: BytePos::ZERO,
-            coords: Some(Coordinate::start()),
-            local: false,
-            mtype: Module,
-            contextstr: modpath.to_str().unwrap().to_owned(),
-            docs: find_mod_doc(&doc_src, BytePos::ZERO),
-        });
-    }
-    // get internal module nesting
-    // e.g. is this in an inline submodule?  mod foo{ mod bar; }
-    // because if it is then we need to search further down the
-    // directory hierarchy - e.g. <cwd>/foo/bar.rs
-    let internalpath = scopes::get_local_module_path(msrc, context.range.start);
-    let mut searchdir = (*search_path).to_owned();
-    for s in internalpath {
-        searchdir.push(&s);
-    }
-    if let Some(modpath) = get_module_file(&s, &searchdir, session) {
+        let decl_point = msrc.range.start + context.range.start;
+        let modpath = resolve_module_file(&s, decl_point, context.filepath, session)?;
         let doc_src = session.load_raw_file(&modpath);
         let context = modpath.to_str().unwrap().to_owned();
-        return Some(Match {
+        Some(Match {
             matchstr: s,
             filepath: modpath,
             point: BytePos::ZERO,
@@ -419,34 +412,24 @@ fn match_mod_inner(
             mtype: Module,
             contextstr: context,
             docs: find_mod_doc(&doc_src, BytePos::ZERO),
-        });
+            visibility: visibility_of(blob),
+        })
     }
-    None
 }
 
 fn find_generics_end(blob: &str) -> Option<BytePos> {
-    // Naive version that attempts to skip over attributes
-    let mut in_attr = false;
-    let mut attr_level = 0;
-
+    let bytes = blob.as_bytes();
     let mut level = 0;
-    for (i, b) in blob.as_bytes().into_iter().enumerate() {
-        // Naively skip attributes `#[...]`
-        if in_attr {
-            match b {
-                b'[' => attr_level += 1,
-                b']' => {
-                    attr_level -=1;
-                    if attr_level == 0 {
-                        in_attr = false;
-                        continue;
-                    }
-                },
-                _ => continue,
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // skip attributes on generic parameters
+            b'#' => {
+                if let Some(len) = attribute_len(&blob[i..]) {
+                    i += len;
+                    continue;
+                }
             }
-        }
-        // ...otherwise just try to find the last `>`
-        match b {
             b'{' | b'(' | b';' => return None,
             b'<' => level += 1,
             b'>' => {
@@ -455,9 +438,9 @@ fn find_generics_end(blob: &str) -> Option<BytePos> {
                     return Some(i.into());
                 }
             }
-            b'#' if blob.bytes().nth(i + 1) == Some(b'[') => in_attr = true,
             _ => {}
         }
+        i += 1;
     }
     None
 }
@@ -487,6 +470,7 @@ pub fn match_struct(
         mtype: Struct(Box::new(generics)),
         contextstr: get_context(blob, "{"),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -515,6 +499,7 @@ pub fn match_union(
         mtype: MatchType::Union(Box::new(generics)),
         contextstr: get_context(blob, "{"),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -538,6 +523,7 @@ pub fn match_type(
         mtype: Type,
         contextstr: first_line(blob),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -560,6 +546,7 @@ pub fn match_trait(
         mtype: Trait,
         contextstr: get_context(blob, "{"),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -579,6 +566,7 @@ pub fn match_enum_variants(msrc: &str, context: &MatchCxt<'_, '_>) -> Vec<Match>
                 mtype: EnumVariant(None),
                 contextstr: first_line(&blob[offset.0..]),
                 docs: find_doc(msrc, start),
+                visibility: Visibility::Public,
             };
             out.push(m);
         }
@@ -612,6 +600,7 @@ pub fn match_enum(
         mtype: Enum(Box::new(generics)),
         contextstr: first_line(blob),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -649,8 +638,15 @@ pub fn match_use(
         "[match_use] found item: {:?}, searchstr: {}",
         use_item, context.search_str
     );
+    // `use crate::my_macro;` imports `my_macro!`, but not items of other namespaces
+    let search_str = context.search_str.trim_end_matches('!');
+    let ident_namespace = if search_str.len() != context.search_str.len() {
+        Namespace::Macro
+    } else {
+        Namespace::Path
+    };
     // for speed up!
-    if !use_item.contains_glob && !txt_matches(context.search_type, context.search_str, blob) {
+    if !use_item.contains_glob && !txt_matches(context.search_type, search_str, blob) {
         return out;
     }
     let mut import_info = ImportInfo {
@@ -666,6 +662,7 @@ pub fn match_use(
         mtype: MatchType::UseAlias(Box::new(inner)),
         contextstr: cstr,
         docs: String::new(),
+        visibility: visibility_of(blob),
     };
     // common utilities
     macro_rules! with_match {
@@ -697,10 +694,10 @@ pub fn match_use(
         alias_path.set_prefix();
         match alias_kind {
             PathAliasKind::Ident(ref ident, rename_start) => {
-                if !symbol_matches(context.search_type, context.search_str, &ident) {
+                if !symbol_matches(context.search_type, search_str, &ident) {
                     continue;
                 }
-                with_match!(&alias_path, Namespace::Path, |m: Match| {
+                with_match!(&alias_path, ident_namespace, |m: Match| {
                     debug!("[match_use] PathAliasKind::Ident {:?} was found", ident);
                     let rename_start = match rename_start {
                         Some(r) => r,
@@ -809,6 +806,7 @@ fn match_fn_common(
         mtype: Function,
         contextstr: get_context(blob, "{"),
         docs: find_doc(&doc_src, start),
+        visibility: visibility_of(blob),
     })
 }
 
@@ -834,9 +832,17 @@ pub fn match_macro(
         mtype: Macro,
         contextstr: first_line(blob),
         docs: find_doc(&doc_src, context.range.start),
+        visibility: Visibility::Public,
     })
 }
 
+/// Checks if the item starting at `point` is annotated with `#[attr]` or `#[attr(..)]`
+pub(crate) fn has_outer_attribute(src: &str, point: BytePos, attr: &str) -> bool {
+    outer_attributes(src, point)
+        .into_iter()
+        .any(|a| attribute_path(a) == attr)
+}
+
 pub fn find_doc(msrc: &str, match_point: BytePos) -> String {
     let blob = &msrc[0..match_point.0];
     blob.lines()
@@ -883,7 +889,7 @@ pub fn match_impl(decl: String, context: &MatchCxt<'_, '_>, offset: BytePos) ->
     let ImplHeader { generics, .. } =
         ast::parse_impl(decl, context.filepath, offset, true, offset)?;
     let mut out = Vec::new();
-    for type_param in generics.0 {
+    for type_param in generics.into_named_params() {
         if !symbol_matches(context.search_type, context.search_str, &type_param.name) {
             continue;
         }
@@ -892,6 +898,21 @@ pub fn match_impl(decl: String, context: &MatchCxt<'_, '_>, offset: BytePos) ->
     Some(out)
 }
 
+pub fn match_trait_generics(
+    decl: String,
+    context: &MatchCxt<'_, '_>,
+    offset: BytePos,
+) -> Vec<Match> {
+    let generics = ast::parse_generics_with_offset(decl, context.filepath, offset.0 as i32);
+    generics
+        .into_named_params()
+        .filter(|type_param| {
+            symbol_matches(context.search_type, context.search_str, &type_param.name)
+        })
+        .map(|type_param| type_param.into_match())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
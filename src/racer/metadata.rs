@@ -2,33 +2,40 @@ use lazycell;
 extern crate racer_cargo_metadata as metadata;
 use self::lazycell::LazyCell;
 use self::metadata::mapping::{Edition as Ed, PackageIdx, PackageMap};
+pub use self::metadata::Features;
+use crate::core::FileCache;
 use crate::project_model::{Edition, ProjectModelProvider};
 use std::cell::Cell;
 use std::path::{Path, PathBuf};
 
-struct MetadataCache {
+struct MetadataCache<'c> {
     pkg_map: LazyCell<PackageMap>,
     manifest_path: Option<PathBuf>,
+    features: Features,
     failed_to_fill: Cell<bool>,
+    /// Answers file system queries, so that manifests can be found in a virtual tree
+    file_cache: &'c FileCache,
 }
 
-impl MetadataCache {
-    fn new(manifest_path: Option<PathBuf>) -> Self {
+impl<'c> MetadataCache<'c> {
+    fn new(manifest_path: Option<PathBuf>, features: Features, file_cache: &'c FileCache) -> Self {
         MetadataCache {
             pkg_map: LazyCell::new(),
             manifest_path,
+            features,
             failed_to_fill: Cell::new(false),
+            file_cache,
         }
     }
     fn fill_impl(&self, manifest: &Path) -> Result<(), ()> {
-        let meta = metadata::run(manifest, true)
+        let meta = metadata::run_with_features(manifest, true, &self.features)
             .or_else(|e| {
                 if let metadata::ErrorKind::Subprocess(ref s) = e {
                     // HACK: if --frozen failed, try again without --frozen
                     // see https://github.com/rust-lang/cargo/blob/master/src/cargo/ops/registry.rs#L344
                     if s.contains("--frozen") {
                         info!("MetadataCache: try again without --frozen");
-                        return metadata::run(manifest, false);
+                        return metadata::run_with_features(manifest, false, &self.features);
                     }
                 }
                 Err(e)
@@ -48,7 +55,8 @@ impl MetadataCache {
     }
     fn setup(&self, manifest: &Path) -> Option<(&PackageMap, PackageIdx)> {
         self.fill(manifest);
-        let pkg_map: &PackageMap = self.pkg_map.borrow().unwrap();
+        // `cargo metadata` fails for manifests which only exist in a virtual tree
+        let pkg_map: &PackageMap = self.pkg_map.borrow()?;
         let idx = if manifest.is_relative() {
             let path = manifest.canonicalize().ok()?;
             pkg_map.get_idx(&path)?
@@ -59,7 +67,7 @@ impl MetadataCache {
     }
 }
 
-impl ProjectModelProvider for MetadataCache {
+impl<'c> ProjectModelProvider for MetadataCache<'c> {
     fn edition(&self, manifest: &Path) -> Option<Edition> {
         let (pkg_map, idx) = self.setup(manifest)?;
         let edition = pkg_map.get_edition(idx);
@@ -70,7 +78,7 @@ impl ProjectModelProvider for MetadataCache {
         })
     }
     fn discover_project_manifest(&self, path: &Path) -> Option<PathBuf> {
-        let cur_manifest = metadata::find_manifest(path)?;
+        let cur_manifest = find_manifest(path, self.file_cache)?;
         let manifest = self.manifest_path.as_ref()?;
         self.fill(manifest);
         Some(cur_manifest)
@@ -117,9 +125,70 @@ impl ProjectModelProvider for MetadataCache {
             })
             .map(|p| p.to_owned())
     }
+    fn enabled_features(&self, manifest: &Path) -> Option<Vec<String>> {
+        let (pkg_map, idx) = self.setup(manifest)?;
+        let features = pkg_map.get_features(idx)?;
+        Some(features.iter().map(|f| f.to_string()).collect())
+    }
+    fn out_dir(&self, manifest: &Path) -> Option<PathBuf> {
+        let (pkg_map, idx) = self.setup(manifest)?;
+        let target_dir = pkg_map.target_directory()?;
+        let name = pkg_map.get_id(idx).name();
+        // build scripts write to `target/<profile>/build/<name>-<hash>/out`,
+        // or `target/<triple>/<profile>/build/..` when cross compiling
+        let cache = self.file_cache;
+        let profiles = read_dirs(target_dir, cache).into_iter().flat_map(|dir| {
+            let nested = read_dirs(&dir, cache);
+            std::iter::once(dir).chain(nested)
+        });
+        // the most recently built one wins; modification times are unknown
+        // in a virtual tree, so any directory will do there
+        profiles
+            .flat_map(|profile| read_dirs(&profile.join("build"), cache))
+            .filter(|build| is_build_dir_of(build, name))
+            .map(|build| build.join("out"))
+            .filter(|out| cache.is_dir(out))
+            .max_by_key(|out| cache.modified(out))
+    }
+}
+
+fn find_manifest(path: &Path, cache: &FileCache) -> Option<PathBuf> {
+    metadata::find_manifest_with(path, |p| cache.is_dir(p), |p| cache.exists(p))
+}
+
+/// Subdirectories of `path`
+fn read_dirs(path: &Path, cache: &FileCache) -> Vec<PathBuf> {
+    let mut dirs = cache.read_dir(path);
+    dirs.retain(|dir| cache.is_dir(dir));
+    dirs
+}
+
+/// Checks if `dir` is named `<name>-<hash>`
+fn is_build_dir_of(dir: &Path, name: &str) -> bool {
+    let dir_name = match dir.file_name().and_then(|n| n.to_str()) {
+        Some(n) => n,
+        None => return false,
+    };
+    dir_name.starts_with(name)
+        && dir_name[name.len()..]
+            .strip_prefix('-')
+            .map_or(false, |hash| hash.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+pub fn project_model<'c>(
+    cache: &'c FileCache,
+    project_path: Option<&Path>,
+) -> Box<dyn ProjectModelProvider + 'c> {
+    project_model_with_features(cache, project_path, Features::All)
 }
 
-pub fn project_model(project_path: Option<&Path>) -> Box<dyn ProjectModelProvider> {
-    let manifest = project_path.and_then(|p| metadata::find_manifest(p));
-    Box::new(MetadataCache::new(manifest))
+/// Like `project_model`, but resolves the package graph with `features`
+/// instead of `--all-features`
+pub fn project_model_with_features<'c>(
+    cache: &'c FileCache,
+    project_path: Option<&Path>,
+    features: Features,
+) -> Box<dyn ProjectModelProvider + 'c> {
+    let manifest = project_path.and_then(|p| find_manifest(p, cache));
+    Box::new(MetadataCache::new(manifest, features, cache))
 }
@@ -1,26 +1,30 @@
 //! Name resolving
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::{self, vec};
 
 use crate::primitive::PrimKind;
-use rustc_ast::ast::BinOpKind;
+use rustc_ast::ast::{BinOpKind, UnOp};
 
-use crate::ast_types::{ImplHeader, Path as RacerPath, PathPrefix, PathSegment, Ty};
+use crate::ast_types::{GenericsArgs, ImplHeader, Path as RacerPath, PathPrefix, PathSegment, Ty};
+use crate::cfg::{self, CfgFilter};
 use crate::core::Namespace;
 use crate::core::SearchType::{self, ExactMatch, StartsWith};
 use crate::core::{
-    BytePos, ByteRange, Coordinate, Match, MatchType, Scope, Session, SessionExt, Src,
+    BytePos, ByteRange, Coordinate, Match, MatchType, Scope, Session, SessionExt, Src, Visibility,
+};
+use crate::fileres::{
+    get_crate_file, get_module_file, get_std_file, resolve_module_file, search_crate_names,
 };
-use crate::fileres::{get_crate_file, get_module_file, get_std_file, search_crate_names};
 use crate::matchers::{find_doc, ImportInfo, MatchCxt};
 use crate::primitive;
+use crate::project_model::Edition;
 use crate::util::{
     self, calculate_str_hash, find_ident_end, get_rust_src_path, strip_words, symbol_matches,
-    trim_visibility, txt_matches, txt_matches_with_pos,
+    trim_visibility, txt_matches, txt_matches_with_pos, visibility_of,
 };
-use crate::{ast, core, matchers, scopes, typeinf};
+use crate::{ast, core, macros, matchers, scopes, typeinf};
 
 lazy_static! {
     pub static ref RUST_SRC_PATH: Option<PathBuf> = get_rust_src_path().ok();
@@ -54,6 +58,11 @@ pub(crate) fn search_struct_fields(
         if symbol_matches(search_type, searchstr, &field) {
             let raw_src = session.load_raw_file(&structmatch.filepath);
             let contextstr = src[field_range.shift(struct_start).to_range()].to_owned();
+            // fields of enum variants are as visible as the enum
+            let visibility = match structmatch.mtype {
+                MatchType::EnumVariant(_) => Visibility::Public,
+                _ => visibility_of(&contextstr),
+            };
             out.push(Match {
                 matchstr: field,
                 filepath: structmatch.filepath.clone(),
@@ -63,6 +72,7 @@ pub(crate) fn search_struct_fields(
                 mtype: MatchType::StructField,
                 contextstr,
                 docs: find_doc(&raw_src[struct_range.clone()], field_range.start),
+                visibility,
             });
         }
     }
@@ -88,42 +98,38 @@ pub fn search_for_impl_methods(
     );
 
     let mut out = Vec::new();
+    let mut impled_traits = Vec::new();
 
-    for header in search_for_impls(point, implsearchstr, fpath, local, session) {
+    let impls = search_for_impls(point, implsearchstr, fpath, local, session);
+    for header in impls
+        .into_iter()
+        .chain(search_for_derived_impls(match_request, session))
+    {
         debug!("found impl!! |{:?}| looking for methods", header);
         let mut found_methods = HashSet::new();
-        let src = session.load_source_file(header.file_path());
-        for m in search_scope_for_methods(
-            header.scope_start(),
-            src.as_src(),
-            fieldsearchstr,
-            header.file_path(),
-            false,
-            false,
-            search_type,
-            session,
-        ) {
-            found_methods.insert(calculate_str_hash(&m.matchstr));
-            out.push(m);
+        if !header.is_derived() {
+            let src = session.load_source_file(header.file_path());
+            for m in search_scope_for_methods(
+                header.scope_start(),
+                src.as_src(),
+                fieldsearchstr,
+                header.file_path(),
+                false,
+                false,
+                search_type,
+                session,
+            ) {
+                found_methods.insert(calculate_str_hash(&m.matchstr));
+                if header.is_trait() {
+                    out.push(into_trait_item(m));
+                } else {
+                    out.push(m);
+                }
+            }
         }
         let trait_path = try_continue!(header.trait_path());
-        // search methods coerced by deref
+        // methods coerced by deref are searched in `search_for_fields_and_methods`
         if trait_path.name() == Some("Deref") {
-            let target = search_scope_for_impled_assoc_types(
-                &header,
-                "Target",
-                SearchType::ExactMatch,
-                session,
-            );
-            if let Some((_, target_ty)) = target.into_iter().next() {
-                out.extend(search_for_deref_matches(
-                    target_ty,
-                    match_request,
-                    &header,
-                    fieldsearchstr,
-                    session,
-                ));
-            }
             continue;
         }
         let trait_match = try_continue!(header.resolve_trait(session, &ImportInfo::default()));
@@ -133,22 +139,57 @@ pub fn search_for_impl_methods(
                 out.push(m);
             }
         }
-        for gen_impl_header in search_for_generic_impls(
-            trait_match.point,
-            &trait_match.matchstr,
-            &trait_match.filepath,
+        impled_traits.push(trait_match);
+    }
+    let found_methods: HashSet<_> = out.iter().map(|m| m.matchstr.clone()).collect();
+    out.extend(
+        search_for_blanket_impl_methods(
+            &impled_traits,
+            fpath,
+            point,
+            fieldsearchstr,
+            search_type,
             session,
+        )
+        .into_iter()
+        .filter(|m| !found_methods.contains(&m.matchstr)),
+    );
+    out
+}
+
+/// Search methods provided by blanket impls, like `impl<T: Iterator> Itertools for T`,
+/// for a type which implements `traits`.
+/// Only traits in scope at the cursor are considered, as in rustc; `filepath` and `point`,
+/// the location of the type, stand in for the cursor outside of a query.
+fn search_for_blanket_impl_methods(
+    traits: &[Match],
+    filepath: &Path,
+    point: BytePos,
+    searchstr: &str,
+    search_type: SearchType,
+    session: &Session<'_>,
+) -> Vec<Match> {
+    let mut out = Vec::new();
+    let mut found_methods = HashSet::new();
+    for header in search_for_blanket_impls(traits, filepath, point, session) {
+        debug!("found blanket impl!! {:?}", header);
+        let src = session.load_source_file(header.file_path());
+        for m in search_generic_impl_scope_for_methods(
+            header.scope_start(),
+            src.as_src(),
+            searchstr,
+            &header,
+            search_type,
         ) {
-            debug!("found generic impl!! {:?}", gen_impl_header);
-            let src = session.load_source_file(gen_impl_header.file_path());
-            for gen_method in search_generic_impl_scope_for_methods(
-                gen_impl_header.scope_start(),
-                src.as_src(),
-                fieldsearchstr,
-                &gen_impl_header,
-                search_type,
-            ) {
-                out.push(gen_method);
+            if found_methods.insert(m.matchstr.clone()) {
+                out.push(m);
+            }
+        }
+        // default methods of the implemented trait
+        let trait_match = try_continue!(header.resolve_trait(session, &ImportInfo::default()));
+        for m in search_for_trait_methods(trait_match, searchstr, search_type, session) {
+            if found_methods.insert(m.matchstr.clone()) {
+                out.push(m);
             }
         }
     }
@@ -173,15 +214,6 @@ fn search_scope_for_methods(
     );
     let scopesrc = src.shift_start(point);
     let mut out = Vec::new();
-    macro_rules! ret_or_continue {
-        () => {
-            if search_type == ExactMatch {
-                return out;
-            } else {
-                continue;
-            }
-        };
-    }
     for blob_range in scopesrc.iter_stmts() {
         let matchcxt = MatchCxt {
             filepath,
@@ -190,6 +222,25 @@ fn search_scope_for_methods(
             search_type,
             is_local: true,
         };
+        let disabled = cfg::is_disabled(&src, matchcxt.range.start, filepath, session);
+        if disabled && session.cfg_filter() == CfgFilter::Hide {
+            continue;
+        }
+        let out_len = out.len();
+        macro_rules! ret_or_continue {
+            () => {
+                if disabled {
+                    out[out_len..]
+                        .iter()
+                        .for_each(|m| session.mark_disabled_by_cfg(m));
+                }
+                if search_type == ExactMatch {
+                    return out;
+                } else {
+                    continue;
+                }
+            };
+        }
         let method = matchers::match_method(src, &matchcxt, includes_assoc_fn, session);
         if let Some(mut m) = method {
             // for backward compatibility
@@ -254,6 +305,7 @@ fn search_generic_impl_scope_for_methods(
                     mtype: MatchType::Method(Some(Box::new(impl_header.generics.clone()))),
                     contextstr: signature.to_owned(),
                     docs: find_doc(&scopesrc, blob_range.start + start),
+                    visibility: Visibility::Public,
                 };
                 out.push(m);
             }
@@ -342,10 +394,11 @@ fn search_for_impls(
                 local,
                 start + n.into(),
             ));
-            let matched = impl_header
-                .self_path()
-                .name()
-                .map_or(false, |name| symbol_matches(ExactMatch, searchstr, name));
+            let matched = !impl_header.is_trait_object()
+                && impl_header
+                    .self_path()
+                    .name()
+                    .map_or(false, |name| symbol_matches(ExactMatch, searchstr, name));
             if matched {
                 out.push(impl_header);
             }
@@ -354,6 +407,81 @@ fn search_for_impls(
     out
 }
 
+/// Traits implemented by the built-in derive macros.
+/// They're written with `core` paths, which resolve in `#![no_std]` crates too.
+const STD_DERIVES: [(&str, &str); 9] = [
+    ("Clone", "core::clone::Clone"),
+    ("Copy", "core::marker::Copy"),
+    ("Debug", "core::fmt::Debug"),
+    ("Default", "core::default::Default"),
+    ("Eq", "core::cmp::Eq"),
+    ("Hash", "core::hash::Hash"),
+    ("Ord", "core::cmp::Ord"),
+    ("PartialEq", "core::cmp::PartialEq"),
+    ("PartialOrd", "core::cmp::PartialOrd"),
+];
+
+// get impl headers generated by `#[derive(..)]` on the type
+fn search_for_derived_impls(type_match: &Match, session: &Session<'_>) -> Vec<ImplHeader> {
+    match type_match.mtype {
+        MatchType::Struct(_) | MatchType::Enum(_) | MatchType::Union(_) => {}
+        _ => return Vec::new(),
+    }
+    let s = session.load_source_file(&type_match.filepath);
+    let item_start = try_vec!(scopes::find_stmt_start(s.as_src(), type_match.point));
+    // string literals in `cfg_attr` predicates are masked in `s`
+    let raw_src = session.load_raw_file(&type_match.filepath);
+    let mut attrs = util::outer_attribute_ranges(&s, item_start);
+    attrs.reverse();
+    let generics = type_match.to_generics().cloned().unwrap_or_default();
+    let self_path = RacerPath::single(PathSegment::new(type_match.matchstr.clone(), vec![], None));
+    let mut out = Vec::new();
+    let applied = attrs.iter().flat_map(|range| {
+        // the position of `#[`
+        let attr_start = range.start - BytePos(2);
+        let attr = raw_src[range.to_range()].trim();
+        cfg::applied_attributes(Some(attr), &type_match.filepath, session)
+            .into_iter()
+            .map(move |attr| (attr_start, attr))
+    });
+    for (attr_start, attr) in applied {
+        for derive in derive_paths(attr) {
+            let trait_path = STD_DERIVES
+                .iter()
+                .find(|(name, _)| *name == derive)
+                .map_or(derive, |&(_, path)| path);
+            debug!(
+                "[search_for_derived_impls] {} derives {}",
+                self_path, trait_path
+            );
+            out.push(ImplHeader::derived(
+                self_path.clone(),
+                RacerPath::from_vec(false, trait_path.split("::").collect()),
+                generics.clone(),
+                &type_match.filepath,
+                attr_start,
+            ));
+        }
+    }
+    out
+}
+
+/// Returns the paths listed in the attribute `derive(..)`
+fn derive_paths(attr: &str) -> Vec<&str> {
+    if util::attribute_path(attr) != "derive" {
+        return Vec::new();
+    }
+    let args = attr["derive".len()..].trim();
+    if !args.starts_with('(') || !args.ends_with(')') {
+        return Vec::new();
+    }
+    args[1..args.len() - 1]
+        .split(',')
+        .map(|path| path.trim().trim_start_matches("::"))
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
 // trait_only version of search_for_impls
 // needs both `Self` type name and trait name
 pub(crate) fn search_trait_impls(
@@ -443,40 +571,205 @@ fn cached_generic_impls(
         .clone()
 }
 
-// Find trait impls
-fn search_for_generic_impls(
-    pos: BytePos,
-    searchstr: &str,
+// Find blanket impls like `impl<T: Bound> Trait for T` for types bounded by `traits`
+// (or their supertraits), where `Trait` is in scope at the cursor.
+// They are searched in all modules of the crate defining `Trait`, which may be a dependency.
+// std is too large to scan, so for std traits only the scope of the trait is searched (e.g.
+// `IntoIterator for I: Iterator` lives next to `IntoIterator`).
+// `filepath` and `pos` are used instead of the cursor if the session has none.
+fn search_for_blanket_impls(
+    traits: &[Match],
     filepath: &Path,
+    pos: BytePos,
     session: &Session<'_>,
 ) -> Vec<Rc<ImplHeader>> {
     debug!(
-        "search_for_generic_impls {:?}, {}, {:?}",
+        "search_for_blanket_impls {:?}, {:?}, {:?}",
+        traits,
+        filepath.display(),
         pos,
-        searchstr,
-        filepath.display()
     );
-    let s = session.load_source_file(filepath);
-    let scope_start = scopes::scope_start(s.as_src(), pos);
+    if traits.is_empty() {
+        return Vec::new();
+    }
+    let mut bound_names = HashSet::new();
+    for tr in traits {
+        for tr in collect_inherited_traits(tr.clone(), session) {
+            bound_names.insert(tr.matchstr);
+        }
+    }
+    let (filepath, pos) = session
+        .cursor()
+        .unwrap_or_else(|| (filepath.to_owned(), pos));
+    let mut trait_names = HashSet::new();
+    let mut seen_scopes = HashSet::new();
+    let mut scopes_to_search = Vec::new();
+    for tr in traits_in_scope(&filepath, pos, session) {
+        // impls of the bound traits themselves, like `impl<T: Eq> PartialEq for T`,
+        // are already covered
+        if bound_names.contains(&tr.matchstr) || !trait_names.insert(tr.matchstr.clone()) {
+            continue;
+        }
+        let scopes = match crate_root_of(&tr.filepath, session) {
+            Some(crateroot) => crate_module_scopes(&crateroot, session).to_vec(),
+            None => {
+                let src = session.load_source_file(&tr.filepath);
+                let scope_start = scopes::scope_start(src.as_src(), tr.point);
+                vec![(tr.filepath, scope_start)]
+            }
+        };
+        for scope in scopes {
+            if seen_scopes.insert(scope.clone()) {
+                scopes_to_search.push(scope);
+            }
+        }
+    }
 
     let mut out = Vec::new();
+    for (path, scope_start) in scopes_to_search {
+        for header in cached_generic_impls(&path, session, scope_start) {
+            if !header.is_trait() || !header.self_path().is_single() {
+                continue;
+            }
+            let trait_name = try_continue!(header.trait_path().and_then(|p| p.name()));
+            if !trait_names.contains(trait_name) {
+                continue;
+            }
+            let self_name = try_continue!(header.self_path().name());
+            let is_blanket = header.generics().args().any(|type_param| {
+                type_param.name() == self_name
+                    && type_param.bounds.iter().any(|ps| {
+                        ps.path
+                            .name()
+                            .map_or(false, |name| bound_names.contains(name))
+                    })
+            });
+            if is_blanket {
+                out.push(header);
+            }
+        }
+    }
+    out
+}
 
-    for header in cached_generic_impls(filepath, session, scope_start).iter() {
-        let name_path = header.self_path();
-        if !header.is_trait() {
+// Traits declared or imported in the scopes enclosing `pos`, or in the prelude
+fn traits_in_scope(filepath: &Path, pos: BytePos, session: &Session<'_>) -> Vec<Match> {
+    let pathseg = PathSegment::new(String::new(), vec![], None);
+    let import_info = ImportInfo::default();
+    let src = session.load_source_file(filepath);
+    let mut matches = search_local_scopes(
+        &pathseg,
+        filepath,
+        src.as_src(),
+        pos,
+        StartsWith,
+        Namespace::Trait,
+        session,
+        &import_info,
+    );
+    matches.extend(search_prelude_file(
+        &pathseg,
+        filepath,
+        StartsWith,
+        Namespace::Trait,
+        session,
+        &import_info,
+    ));
+    matches
+        .into_iter()
+        .filter_map(|m| match m.mtype {
+            MatchType::Trait => Some(m),
+            // `use Trait as _;` brings the trait into scope as well
+            MatchType::UseAlias(inner) => match inner.mtype {
+                MatchType::Trait => Some(*inner),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+// The crate root of `filepath`, if it isn't in std
+fn crate_root_of(filepath: &Path, session: &Session<'_>) -> Option<PathBuf> {
+    if let Some(ref std_path) = *RUST_SRC_PATH {
+        if filepath.starts_with(std_path) {
+            return None;
+        }
+    }
+    let filepath = match macros::expansion_origin(filepath) {
+        Some((origin, _)) => origin,
+        None => filepath.to_owned(),
+    };
+    filepath
+        .parent()
+        .and_then(|dir| find_possible_crate_root_modules(dir, session).pop())
+}
+
+// Scopes of all modules reachable from `crateroot`, i.e. module files and the bodies of
+// inline modules, cached in the session
+fn crate_module_scopes(crateroot: &Path, session: &Session<'_>) -> Rc<Vec<(PathBuf, BytePos)>> {
+    if let Some(scopes) = session.module_scopes.borrow().get(crateroot) {
+        return Rc::clone(scopes);
+    }
+    let mut scopes = Vec::new();
+    let mut files = vec![crateroot.to_owned()];
+    let mut visited = HashSet::new();
+    while let Some(file) = files.pop() {
+        if !visited.insert(file.clone()) {
             continue;
         }
-        if let Some(name) = name_path.segments.last() {
-            for type_param in header.generics().args() {
-                if symbol_matches(ExactMatch, type_param.name(), &name.name)
-                    && type_param.bounds.find_by_name(searchstr).is_some()
-                {
-                    out.push(header.to_owned());
-                }
-            }
+        let src = session.load_source_file(&file);
+        collect_module_scopes(
+            &file,
+            src.as_src(),
+            BytePos::ZERO,
+            session,
+            &mut scopes,
+            &mut files,
+        );
+    }
+    debug!("[crate_module_scopes] {:?}: {:?}", crateroot, scopes);
+    let scopes = Rc::new(scopes);
+    session
+        .module_scopes
+        .borrow_mut()
+        .insert(crateroot.to_owned(), Rc::clone(&scopes));
+    scopes
+}
+
+fn collect_module_scopes(
+    filepath: &Path,
+    src: Src<'_>,
+    scope_start: BytePos,
+    session: &Session<'_>,
+    scopes: &mut Vec<(PathBuf, BytePos)>,
+    files: &mut Vec<PathBuf>,
+) {
+    scopes.push((filepath.to_owned(), scope_start));
+    for range in src.iter_stmts() {
+        let blob = &src[range.to_range()];
+        let item = trim_visibility(blob);
+        let name = match item_name(item, "mod ") {
+            Some((_, name)) => name,
+            None => continue,
+        };
+        if let Some(brace) = item.find('{') {
+            let body_start = range.start + BytePos(blob.len() - item.len() + brace + 1);
+            let body = ByteRange::new(body_start, range.end - BytePos(1));
+            collect_module_scopes(
+                filepath,
+                src.shift_range(body),
+                scope_start + body_start,
+                session,
+                scopes,
+                files,
+            );
+        } else if let Some(modfile) =
+            resolve_module_file(name, scope_start + range.start, filepath, session)
+        {
+            files.push(modfile);
         }
     }
-    out
 }
 
 // scope headers include fn decls, if let, while let etc..
@@ -566,6 +859,18 @@ fn search_scope_headers(
             }
             return out;
         }
+    } else if preblock_is_trait(preblock) {
+        let trimed = msrc[stmtstart.0..scopestart.0].trim();
+        if txt_matches(search_type, search_str, trimed) {
+            let src = trimed.to_owned() + "{}";
+            let match_cxt = get_cxt(0);
+            let mut out = matchers::match_trait_generics(src, &match_cxt, stmtstart);
+            for m in &mut out {
+                m.local = true;
+                m.contextstr = trimed.to_owned();
+            }
+            return out;
+        }
     } else if let Some(n) = preblock.rfind("match ") {
         // TODO: this code is crufty. refactor me!
         let matchstart = stmtstart + n.into();
@@ -636,6 +941,7 @@ fn search_scope_headers(
                     mtype: MatchType::MatchArm,
                     contextstr: lhs.trim().to_owned(),
                     docs: String::new(),
+                    visibility: Visibility::Private,
                 });
                 if let SearchType::ExactMatch = search_type {
                     break;
@@ -668,6 +974,23 @@ fn preblock_is_fn(preblock: &str) -> bool {
     }
 }
 
+fn preblock_is_trait(preblock: &str) -> bool {
+    let s = trim_visibility(preblock);
+    let p = strip_words(s, &["unsafe", "auto"]);
+    if p.0 < s.len() {
+        s[p.0..].starts_with("trait ")
+    } else {
+        false
+    }
+}
+
+#[test]
+fn is_trait() {
+    assert!(preblock_is_trait("pub trait Foo<T>"));
+    assert!(preblock_is_trait("unsafe trait Foo<T> where T: Clone"));
+    assert!(!preblock_is_trait("fn trait_method()"));
+}
+
 #[test]
 fn is_fn() {
     assert!(preblock_is_fn("pub fn bar()"));
@@ -738,6 +1061,7 @@ fn search_fn_args_and_generics(
                     mtype: MatchType::FnArg(Box::new((pat, ty))),
                     contextstr: context_str.to_owned(),
                     docs: String::new(),
+                    visibility: Visibility::Private,
                 };
                 out.push(m);
                 if search_type == SearchType::ExactMatch {
@@ -746,7 +1070,7 @@ fn search_fn_args_and_generics(
             }
         }
     }
-    for type_param in generics.0 {
+    for type_param in generics.into_named_params() {
         if symbol_matches(search_type, searchstr, type_param.name()) {
             out.push(type_param.into_match());
             if search_type == SearchType::ExactMatch {
@@ -797,34 +1121,53 @@ pub fn do_file_search(searchstr: &str, currentdir: &Path, session: &Session<'_>)
 
     debug!("do_file_search v: {:?}", v);
     for srcpath in v {
-        if let Ok(iter) = std::fs::read_dir(srcpath) {
-            for fpath_buf in iter.filter_map(|res| res.ok().map(|entry| entry.path())) {
-                // skip filenames that can't be decoded
-                let fname = match fpath_buf.file_name().and_then(|n| n.to_str()) {
-                    Some(fname) => fname,
-                    None => continue,
-                };
-                // Firstly, try the original layout, e.g. libstd/lib.rs
-                if fname.starts_with(&format!("lib{}", searchstr)) {
-                    let filepath = fpath_buf.join("lib.rs");
-                    if filepath.exists() || session.contains_file(&filepath) {
-                        let m = Match {
-                            matchstr: fname[3..].to_owned(),
-                            filepath: filepath.to_path_buf(),
-                            point: BytePos::ZERO,
-                            coords: Some(Coordinate::start()),
-                            local: false,
-                            mtype: MatchType::Module,
-                            contextstr: fname[3..].to_owned(),
-                            docs: String::new(),
-                        };
-                        out.push(m);
-                    }
+        for fpath_buf in session.read_dir(srcpath) {
+            // skip filenames that can't be decoded
+            let fname = match fpath_buf.file_name().and_then(|n| n.to_str()) {
+                Some(fname) => fname,
+                None => continue,
+            };
+            // Firstly, try the original layout, e.g. libstd/lib.rs
+            if fname.starts_with(&format!("lib{}", searchstr)) {
+                let filepath = fpath_buf.join("lib.rs");
+                if session.file_exists(&filepath) {
+                    let m = Match {
+                        matchstr: fname[3..].to_owned(),
+                        filepath: filepath.to_path_buf(),
+                        point: BytePos::ZERO,
+                        coords: Some(Coordinate::start()),
+                        local: false,
+                        mtype: MatchType::Module,
+                        contextstr: fname[3..].to_owned(),
+                        docs: String::new(),
+                        visibility: Visibility::Public,
+                    };
+                    out.push(m);
+                }
+            }
+            // Secondly, try the new standard library layout, e.g. std/src/lib.rs
+            if fname.starts_with(searchstr) {
+                let filepath = fpath_buf.join("src").join("lib.rs");
+                if session.file_exists(&filepath) {
+                    let m = Match {
+                        matchstr: fname.to_owned(),
+                        filepath: filepath.to_path_buf(),
+                        point: BytePos::ZERO,
+                        coords: Some(Coordinate::start()),
+                        local: false,
+                        mtype: MatchType::Module,
+                        contextstr: fname.to_owned(),
+                        docs: String::new(),
+                        visibility: Visibility::Public,
+                    };
+                    out.push(m);
                 }
-                // Secondly, try the new standard library layout, e.g. std/src/lib.rs
-                if fname.starts_with(searchstr) {
-                    let filepath = fpath_buf.join("src").join("lib.rs");
-                    if filepath.exists() || session.contains_file(&filepath) {
+            }
+
+            if fname.starts_with(searchstr) {
+                for name in &[&format!("{}.rs", fname)[..], "mod.rs", "lib.rs"] {
+                    let filepath = fpath_buf.join(name);
+                    if session.file_exists(&filepath) {
                         let m = Match {
                             matchstr: fname.to_owned(),
                             filepath: filepath.to_path_buf(),
@@ -832,46 +1175,27 @@ pub fn do_file_search(searchstr: &str, currentdir: &Path, session: &Session<'_>)
                             coords: Some(Coordinate::start()),
                             local: false,
                             mtype: MatchType::Module,
-                            contextstr: fname.to_owned(),
+                            contextstr: filepath.to_str().unwrap().to_owned(),
                             docs: String::new(),
+                            visibility: Visibility::Public,
                         };
                         out.push(m);
                     }
                 }
-
-                if fname.starts_with(searchstr) {
-                    for name in &[&format!("{}.rs", fname)[..], "mod.rs", "lib.rs"] {
-                        let filepath = fpath_buf.join(name);
-                        if filepath.exists() || session.contains_file(&filepath) {
-                            let m = Match {
-                                matchstr: fname.to_owned(),
-                                filepath: filepath.to_path_buf(),
-                                point: BytePos::ZERO,
-                                coords: Some(Coordinate::start()),
-                                local: false,
-                                mtype: MatchType::Module,
-                                contextstr: filepath.to_str().unwrap().to_owned(),
-                                docs: String::new(),
-                            };
-                            out.push(m);
-                        }
-                    }
-                    // try just <name>.rs
-                    if fname.ends_with(".rs")
-                        && (fpath_buf.exists() || session.contains_file(&fpath_buf))
-                    {
-                        let m = Match {
-                            matchstr: fname[..(fname.len() - 3)].to_owned(),
-                            filepath: fpath_buf.clone(),
-                            point: BytePos::ZERO,
-                            coords: Some(Coordinate::start()),
-                            local: false,
-                            mtype: MatchType::Module,
-                            contextstr: fpath_buf.to_str().unwrap().to_owned(),
-                            docs: String::new(),
-                        };
-                        out.push(m);
-                    }
+                // try just <name>.rs
+                if fname.ends_with(".rs") && session.file_exists(&fpath_buf) {
+                    let m = Match {
+                        matchstr: fname[..(fname.len() - 3)].to_owned(),
+                        filepath: fpath_buf.clone(),
+                        point: BytePos::ZERO,
+                        coords: Some(Coordinate::start()),
+                        local: false,
+                        mtype: MatchType::Module,
+                        contextstr: fpath_buf.to_str().unwrap().to_owned(),
+                        docs: String::new(),
+                        visibility: Visibility::Public,
+                    };
+                    out.push(m);
                 }
             }
         }
@@ -931,7 +1255,7 @@ pub fn find_possible_crate_root_modules(currentdir: &Path, session: &Session<'_>
 
     for root in &["lib.rs", "main.rs"] {
         let filepath = currentdir.join(root);
-        if filepath.exists() || session.contains_file(&filepath) {
+        if session.file_exists(&filepath) {
             res.push(filepath);
             return res; // for now stop at the first match
         }
@@ -946,6 +1270,116 @@ pub fn find_possible_crate_root_modules(currentdir: &Path, session: &Session<'_>
     res
 }
 
+/// The directory of a crate root and the path of a module in the crate
+type ModuleLocation = (PathBuf, Vec<String>);
+
+/// Returns the directory of the crate root and the path of the module enclosing `point`
+/// in `filepath`, guessed from the file layout and inline modules.
+/// The location of each file is memoized in `files`.
+fn module_location(
+    filepath: &Path,
+    point: BytePos,
+    session: &Session<'_>,
+    files: &mut HashMap<PathBuf, ModuleLocation>,
+) -> ModuleLocation {
+    // items generated by a macro belong to the module of its invocation
+    if let Some((origin, origin_point)) = macros::expansion_origin(filepath) {
+        return module_location(&origin, origin_point, session, files);
+    }
+    let (cratedir, mut modpath) = files
+        .entry(filepath.to_owned())
+        .or_insert_with(|| file_module_location(filepath, session))
+        .clone();
+    let src = session.load_source_file(filepath);
+    modpath.extend(scopes::get_local_module_path(src.as_src(), point));
+    (cratedir, modpath)
+}
+
+/// Returns the directory of the crate root and the path of the module of `filepath`
+fn file_module_location(filepath: &Path, session: &Session<'_>) -> ModuleLocation {
+    let crateroot = filepath
+        .parent()
+        .and_then(|dir| {
+            find_possible_crate_root_modules(dir, session)
+                .into_iter()
+                .next()
+        })
+        .unwrap_or_else(|| filepath.to_owned());
+    let cratedir = crateroot
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_owned();
+    let mut modpath: Vec<String> = match filepath.strip_prefix(&cratedir) {
+        Ok(relpath) if filepath != crateroot => relpath
+            .with_extension("")
+            .iter()
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect(),
+        _ => Vec::new(),
+    };
+    if modpath.last().map_or(false, |name| name == "mod") {
+        modpath.pop();
+    }
+    (cratedir, modpath)
+}
+
+/// Returns the module which `path` in `pub(in path)` refers to, for an item in `item_mod`
+fn restricted_module(path: &str, item_mod: &[String]) -> Vec<String> {
+    let mut segments = path.split("::").map(str::trim).peekable();
+    let mut module = match segments.peek() {
+        Some(&"self") | Some(&"super") => item_mod.to_vec(),
+        _ => Vec::new(),
+    };
+    for segment in segments {
+        match segment {
+            "crate" | "self" | "" => {}
+            "super" => {
+                module.pop();
+            }
+            name => module.push(name.to_owned()),
+        }
+    }
+    module
+}
+
+/// Removes the matches which aren't visible from the module enclosing `point` in `filepath`
+pub(crate) fn retain_accessible(
+    matches: &mut Vec<Match>,
+    filepath: &Path,
+    point: BytePos,
+    session: &Session<'_>,
+) {
+    if matches.iter().all(|m| m.visibility == Visibility::Public) {
+        return;
+    }
+    let mut files = HashMap::new();
+    let (cratedir, curmod) = module_location(filepath, point, session, &mut files);
+    matches.retain(|m| {
+        if m.visibility == Visibility::Public {
+            return true;
+        }
+        let (item_cratedir, mut item_mod) =
+            module_location(&m.filepath, m.point, session, &mut files);
+        if item_cratedir != cratedir {
+            return false;
+        }
+        // a module declared by `mod name;` is an item of its parent
+        if m.mtype == MatchType::Module && m.point == BytePos::ZERO {
+            item_mod.pop();
+        }
+        let visible_in = match &m.visibility {
+            Visibility::Public | Visibility::Crate => return true,
+            Visibility::Private => item_mod,
+            Visibility::Super => {
+                item_mod.pop();
+                item_mod
+            }
+            Visibility::InPath(path) => restricted_module(path, &item_mod),
+        };
+        curmod.starts_with(&visible_in)
+    });
+}
+
 pub fn search_next_scope(
     mut startpoint: BytePos,
     pathseg: &PathSegment,
@@ -1083,10 +1517,41 @@ pub fn search_scope(
                     mtype: MatchType::Module,
                     contextstr: context,
                     docs: String::new(),
+                    visibility: Visibility::Public,
                 });
             }
         }
 
+        // Items generated by a macro may not appear in its invocation,
+        // so expand it before failing fast
+        let expands_to_items = match macros::invoked_macro(blob) {
+            Some(name) if !macros::is_expansion_file(filepath) => {
+                name == "include" || macros::is_crate_macro(name, filepath, session)
+            }
+            _ => false,
+        };
+        if expands_to_items {
+            let search_fn = if blob.starts_with("include!") {
+                search_included_file
+            } else {
+                search_macro_expansion
+            };
+            out.extend(search_fn(
+                blob_range.shift(start),
+                pathseg,
+                filepath,
+                search_type,
+                is_local,
+                namespace,
+                session,
+                import_info,
+            ));
+            if search_type == ExactMatch && !out.is_empty() {
+                return out;
+            }
+            continue;
+        }
+
         // Optimisation: if the search string is not in the blob,
         // this cannot match so fail fast!
         if !blob.contains(search_str.trim_end_matches('!')) {
@@ -1177,6 +1642,81 @@ pub fn search_scope(
     out
 }
 
+/// Searches items generated by the macro invocation at `range`.
+/// Found matches point to the invocation, since the expansion has no location in the file.
+fn search_macro_expansion(
+    range: ByteRange,
+    pathseg: &PathSegment,
+    filepath: &Path,
+    search_type: SearchType,
+    is_local: bool,
+    namespace: Namespace,
+    session: &Session<'_>,
+    import_info: &ImportInfo<'_, '_>,
+) -> Vec<Match> {
+    let raw_src = session.load_raw_file(filepath);
+    let blob = &raw_src[range.to_range()];
+    let expanded_path = try_vec!(macros::expand_to_file(blob, range.start, filepath, session));
+    let msrc = session.load_source_file(&expanded_path);
+    search_scope(
+        BytePos::ZERO,
+        None,
+        msrc.as_src(),
+        pathseg,
+        &expanded_path,
+        search_type,
+        is_local,
+        namespace,
+        session,
+        import_info,
+    )
+    .into_iter()
+    .map(|mut m| {
+        m.filepath = filepath.to_owned();
+        m.point = range.start;
+        m.coords = None;
+        m
+    })
+    .collect()
+}
+
+/// Searches the file included by the `include!` invocation at `range`,
+/// as if its items were written at that point.
+fn search_included_file(
+    range: ByteRange,
+    pathseg: &PathSegment,
+    filepath: &Path,
+    search_type: SearchType,
+    is_local: bool,
+    namespace: Namespace,
+    session: &Session<'_>,
+    import_info: &ImportInfo<'_, '_>,
+) -> Vec<Match> {
+    let raw_src = session.load_raw_file(filepath);
+    let blob = &raw_src[range.to_range()];
+    let included = try_vec!(macros::include_path(blob, filepath, session));
+    if session.pending_includes.borrow().contains(&included) {
+        debug!("[search_included_file] {:?} includes itself", included);
+        return Vec::new();
+    }
+    session.pending_includes.borrow_mut().push(included.clone());
+    let msrc = session.load_source_file(&included);
+    let out = search_scope(
+        BytePos::ZERO,
+        None,
+        msrc.as_src(),
+        pathseg,
+        &included,
+        search_type,
+        is_local,
+        namespace,
+        session,
+        import_info,
+    );
+    session.pending_includes.borrow_mut().pop();
+    out
+}
+
 fn search_closure_args(
     search_str: &str,
     scope_src: &str,
@@ -1227,6 +1767,7 @@ fn search_closure_args(
                         // TODO: context_str(without pipe) is better?
                         contextstr: pipe_str.to_owned(),
                         docs: String::new(),
+                        visibility: Visibility::Private,
                     };
                     debug!("search_closure_args matched: {:?}", m);
                     out.push(m);
@@ -1244,6 +1785,26 @@ fn run_matchers_on_blob(
     namespace: Namespace,
     session: &Session<'_>,
     import_info: &ImportInfo<'_, '_>,
+) -> Vec<Match> {
+    if !cfg::is_disabled(&src, context.range.start, context.filepath, session) {
+        return run_matchers_on_blob_(src, context, namespace, session, import_info);
+    }
+    match session.cfg_filter() {
+        CfgFilter::Hide => Vec::new(),
+        CfgFilter::Dim => {
+            let out = run_matchers_on_blob_(src, context, namespace, session, import_info);
+            out.iter().for_each(|m| session.mark_disabled_by_cfg(m));
+            out
+        }
+    }
+}
+
+fn run_matchers_on_blob_(
+    src: Src<'_>,
+    context: &MatchCxt<'_, '_>,
+    namespace: Namespace,
+    session: &Session<'_>,
+    import_info: &ImportInfo<'_, '_>,
 ) -> Vec<Match> {
     debug!(
         "[run_matchers_on_blob] cxt: {:?}, namespace: {:?}",
@@ -1366,8 +1927,96 @@ fn search_local_scopes(
     }
 }
 
+/// The implicit prelude of a crate, decided by attributes of the crate root
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum PreludeKind {
+    Std,
+    /// `#![no_std]`
+    Core,
+    /// `#![no_implicit_prelude]`
+    Nothing,
+}
+
+fn get_prelude_kind(filepath: &Path, session: &Session<'_>) -> PreludeKind {
+    let crateroot = filepath
+        .parent()
+        .and_then(|dir| {
+            find_possible_crate_root_modules(dir, session)
+                .into_iter()
+                .next()
+        })
+        .unwrap_or_else(|| filepath.to_owned());
+    if let Some(&kind) = session.prelude_kinds.borrow().get(&crateroot) {
+        return kind;
+    }
+    let msrc = session.load_source_file(&crateroot);
+    let raw_src = session.load_raw_file(&crateroot);
+    let attrs = util::inner_attribute_ranges(&msrc)
+        .into_iter()
+        .map(|range| raw_src[range.to_range()].trim());
+    let mut kind = PreludeKind::Std;
+    for attr in cfg::applied_attributes(attrs, &crateroot, session) {
+        match attr {
+            "no_implicit_prelude" => {
+                kind = PreludeKind::Nothing;
+                break;
+            }
+            "no_std" => kind = PreludeKind::Core,
+            _ => {}
+        }
+    }
+    debug!("[get_prelude_kind] {:?}: {:?}", crateroot, kind);
+    session.prelude_kinds.borrow_mut().insert(crateroot, kind);
+    kind
+}
+
+/// Finds the start of the body of `mod name { .. }` defined in `filepath`
+fn find_inline_mod_start(filepath: &Path, name: &str, session: &Session<'_>) -> Option<BytePos> {
+    let msrc = session.load_source_file(filepath);
+    let decl = format!("pub mod {}", name);
+    let start = msrc.as_src().find(&decl)?;
+    let open_brace = msrc[start..].find('{')?;
+    Some(BytePos(start + open_brace + 1))
+}
+
+/// Files and scopes which make up the prelude for `filepath`, e.g.
+/// `std::prelude::v1` and `core::prelude::rust_2021` for edition 2021 crates
+fn get_prelude_scopes(filepath: &Path, session: &Session<'_>) -> Vec<Scope> {
+    let std_path = match *RUST_SRC_PATH {
+        Some(ref path) => path,
+        None => return Vec::new(),
+    };
+    let prelude_crate = match get_prelude_kind(filepath, session) {
+        PreludeKind::Std => "std",
+        PreludeKind::Core => "core",
+        PreludeKind::Nothing => return Vec::new(),
+    };
+    let prelude_dir = |krate: &str| std_path.join(krate).join("src").join("prelude");
+    let mut scopes = Vec::new();
+    let v1 = prelude_dir(prelude_crate).join("v1.rs");
+    if session.file_exists(&v1) {
+        scopes.push(Scope::new(v1, BytePos::ZERO));
+    }
+    let edition = session
+        .project_model
+        .discover_project_manifest(filepath)
+        .and_then(|manifest| session.project_model.edition(&manifest))
+        .unwrap_or(Edition::Ed2015);
+    if edition >= Edition::Ed2021 {
+        // items added in 2021 are re-exported from core in both std and core
+        let mod_rs = prelude_dir("core").join("mod.rs");
+        if session.file_exists(&mod_rs) {
+            if let Some(point) = find_inline_mod_start(&mod_rs, "rust_2021", session) {
+                scopes.push(Scope::new(mod_rs, point));
+            }
+        }
+    }
+    scopes
+}
+
 pub fn search_prelude_file(
     pathseg: &PathSegment,
+    filepath: &Path,
     search_type: SearchType,
     namespace: Namespace,
     session: &Session<'_>,
@@ -1378,25 +2027,26 @@ pub fn search_prelude_file(
         pathseg, search_type, namespace
     );
     let mut out: Vec<Match> = Vec::new();
-
-    // find the prelude file from the search path and scan it
-    if let Some(ref std_path) = *RUST_SRC_PATH {
-        let filepath = std_path.join("std").join("src").join("prelude").join("v1.rs");
-        if filepath.exists() || session.contains_file(&filepath) {
-            let msrc = session.load_source_file(&filepath);
-            let is_local = true;
-            for m in search_scope(
-                BytePos::ZERO,
-                None,
-                msrc.as_src(),
-                pathseg,
-                &filepath,
-                search_type,
-                is_local,
-                namespace,
-                session,
-                import_info,
-            ) {
+    let mut found = HashSet::new();
+
+    // find the prelude files from the search path and scan them
+    for scope in get_prelude_scopes(filepath, session) {
+        let msrc = session.load_source_file(&scope.filepath);
+        let is_local = true;
+        for m in search_scope(
+            scope.point,
+            None,
+            msrc.as_src(),
+            pathseg,
+            &scope.filepath,
+            search_type,
+            is_local,
+            namespace,
+            session,
+            import_info,
+        ) {
+            // `rust_2021` re-exports `v1` as well
+            if found.insert(m.matchstr.clone()) {
                 out.push(m);
             }
         }
@@ -1474,8 +2124,11 @@ pub fn resolve_name(
         }
     }
 
-    if (is_exact_match && &searchstr[..] == "std")
-        || (!is_exact_match && "std".starts_with(searchstr))
+    // `std` isn't linked implicitly in `#![no_std]` crates
+    let std_is_linked = || get_prelude_kind(filepath, session) != PreludeKind::Core;
+    if ((is_exact_match && &searchstr[..] == "std")
+        || (!is_exact_match && "std".starts_with(searchstr)))
+        && std_is_linked()
     {
         if let Some(cratepath) = get_std_file("std", session) {
             let context = cratepath.to_str().unwrap().to_owned();
@@ -1488,6 +2141,7 @@ pub fn resolve_name(
                 mtype: MatchType::Module,
                 contextstr: context,
                 docs: String::new(),
+                visibility: Visibility::Public,
             });
         }
 
@@ -1546,6 +2200,12 @@ pub fn resolve_name(
             return out;
         }
     }
+    if namespace.contains(Namespace::StdMacro) && (!is_exact_match || searchstr.ends_with('!')) {
+        get_crate_macros(searchstr, filepath, pos, search_type, session, &mut out);
+        if is_exact_match && !out.is_empty() {
+            return out;
+        }
+    }
     if namespace.contains(Namespace::StdMacro) {
         get_std_macros(searchstr, search_type, session, &mut out);
         if is_exact_match && !out.is_empty() {
@@ -1553,7 +2213,14 @@ pub fn resolve_name(
         }
     }
 
-    for m in search_prelude_file(pathseg, search_type, namespace, session, import_info) {
+    for m in search_prelude_file(
+        pathseg,
+        filepath,
+        search_type,
+        namespace,
+        session,
+        import_info,
+    ) {
         out.push(m);
         if is_exact_match {
             return out;
@@ -1592,7 +2259,7 @@ pub fn get_super_scope(
 
         for filename in &["mod.rs", "lib.rs"] {
             let f_path = moduledir.join(&filename);
-            if f_path.exists() || session.contains_file(&f_path) {
+            if session.file_exists(&f_path) {
                 return Some(core::Scope {
                     filepath: f_path,
                     point: BytePos::ZERO,
@@ -1687,13 +2354,18 @@ fn search_impl_scope(
             is_local: header.is_local(),
             range: blob_range.shift(header.scope_start()),
         };
-        out.extend(run_matchers_on_blob(
+        let matches = run_matchers_on_blob(
             src.as_src(),
             &match_cxt,
             Namespace::Impl,
             session,
             import_info,
-        ));
+        );
+        if header.is_trait() {
+            out.extend(matches.into_iter().map(into_trait_item));
+        } else {
+            out.extend(matches);
+        }
     }
     out
 }
@@ -1706,13 +2378,31 @@ fn get_impled_items(
     import_info: &ImportInfo<'_, '_>,
 ) -> Vec<Match> {
     let mut out = get_enum_variants(search_path, search_type, context, session);
-    for header in search_for_impls(
+    let mut impled_traits = Vec::new();
+    let impls = search_for_impls(
         context.point,
         &context.matchstr,
         &context.filepath,
         context.local,
         session,
-    ) {
+    );
+    for header in impls
+        .into_iter()
+        .chain(search_for_derived_impls(context, session))
+    {
+        if header.is_derived() {
+            let trait_match = try_continue!(header.resolve_trait(session, import_info));
+            out.extend(search_for_trait_items(
+                trait_match.clone(),
+                &search_path.name,
+                search_type,
+                true,
+                false,
+                session,
+            ));
+            impled_traits.push(trait_match);
+            continue;
+        }
         out.extend(search_impl_scope(
             &search_path,
             search_type,
@@ -1721,21 +2411,19 @@ fn get_impled_items(
             import_info,
         ));
         let trait_match = try_continue!(header.resolve_trait(session, import_info));
-        for timpl_header in search_for_generic_impls(
-            trait_match.point,
-            &trait_match.matchstr,
-            &trait_match.filepath,
+        impled_traits.push(trait_match);
+    }
+    for timpl_header in
+        search_for_blanket_impls(&impled_traits, &context.filepath, context.point, session)
+    {
+        debug!("found generic impl!! {:?}", timpl_header);
+        out.extend(search_impl_scope(
+            &search_path,
+            search_type,
+            &timpl_header,
             session,
-        ) {
-            debug!("found generic impl!! {:?}", timpl_header);
-            out.extend(search_impl_scope(
-                &search_path,
-                search_type,
-                &timpl_header,
-                session,
-                import_info,
-            ));
-        }
+            import_info,
+        ));
     }
     if search_type != ExactMatch {
         return out;
@@ -1767,6 +2455,17 @@ pub fn resolve_path(
         pos,
         search_type
     );
+    if let Some(ref qself) = path.qself {
+        return resolve_qualified_path(
+            qself,
+            path,
+            filepath,
+            pos,
+            search_type,
+            session,
+            import_info,
+        );
+    }
     let len = path.len();
     if let Some(ref prefix) = path.prefix {
         match prefix {
@@ -1808,7 +2507,7 @@ pub fn resolve_path(
     }
     if len == 1 {
         let pathseg = &path.segments[0];
-        resolve_name(
+        let mut out = resolve_name(
             pathseg,
             filepath,
             pos,
@@ -1816,7 +2515,20 @@ pub fn resolve_path(
             namespace,
             session,
             import_info,
-        )
+        );
+        // `#[macro_export]`ed macros live in the crate root, wherever they are defined
+        if path.prefix == Some(PathPrefix::Crate)
+            && namespace.contains(Namespace::Macro)
+            && (search_type == StartsWith || out.is_empty())
+        {
+            let crateroot = filepath
+                .parent()
+                .and_then(|dir| find_possible_crate_root_modules(dir, session).pop())
+                .unwrap_or_else(|| filepath.to_owned());
+            let exported = search_exported_macros(&pathseg.name, &crateroot, search_type, session);
+            extend_unique(&mut out, exported);
+        }
+        out
     } else if len != 0 {
         let mut parent_path = path.clone();
         let last_seg = parent_path.segments.pop().unwrap();
@@ -1854,6 +2566,84 @@ pub fn resolve_path(
     }
 }
 
+/// resolve paths like `<T as Trait>::X` through the impl of `Trait` for `T`,
+/// along with the item declared in `Trait`
+fn resolve_qualified_path(
+    qself: &RacerPath,
+    path: &RacerPath,
+    filepath: &Path,
+    pos: BytePos,
+    search_type: SearchType,
+    session: &Session<'_>,
+    import_info: &ImportInfo<'_, '_>,
+) -> Vec<Match> {
+    let mut trait_path = path.clone();
+    trait_path.qself = None;
+    let last_seg = try_vec!(trait_path.segments.pop());
+    let trait_match = try_vec!(resolve_path(
+        &trait_path,
+        filepath,
+        pos,
+        ExactMatch,
+        Namespace::Trait,
+        session,
+        import_info,
+    )
+    .into_iter()
+    .next());
+    let self_match = resolve_path(
+        qself,
+        filepath,
+        pos,
+        ExactMatch,
+        Namespace::Type,
+        session,
+        import_info,
+    )
+    .into_iter()
+    .next();
+    let self_match = match self_match {
+        Some(ref m) if m.mtype == MatchType::Type => typeinf::get_type_of_typedef(m, session),
+        m => m,
+    };
+    let mut out = Vec::new();
+    if let Some(self_match) = self_match {
+        if let MatchType::Enum(_) | MatchType::Struct(_) | MatchType::Union(_) = self_match.mtype {
+            let impls = search_for_impls(
+                self_match.point,
+                &self_match.matchstr,
+                &self_match.filepath,
+                self_match.local,
+                session,
+            );
+            for header in impls {
+                let impled = try_continue!(header.resolve_trait(session, import_info));
+                if impled.filepath == trait_match.filepath && impled.point == trait_match.point {
+                    out.extend(search_impl_scope(
+                        &last_seg,
+                        search_type,
+                        &header,
+                        session,
+                        import_info,
+                    ));
+                }
+            }
+        }
+    }
+    // declarations and default items of the trait, e.g. for type parameters
+    let found: HashSet<_> = out.iter().map(|m| m.matchstr.clone()).collect();
+    let items = search_for_trait_items(
+        trait_match,
+        &last_seg.name,
+        search_type,
+        true,
+        true,
+        session,
+    );
+    out.extend(items.filter(|m| !found.contains(&m.matchstr)));
+    out
+}
+
 /// resolve paths like ::path::to::file
 fn resolve_global_path(
     path: &RacerPath,
@@ -1913,7 +2703,7 @@ fn resolve_following_path(
                 "searching a module '{}' for {}",
                 followed_match.matchstr, pathseg.name,
             );
-            search_next_scope(
+            let mut out = search_next_scope(
                 followed_match.point,
                 &pathseg,
                 &followed_match.filepath,
@@ -1922,7 +2712,20 @@ fn resolve_following_path(
                 namespace,
                 session,
                 import_info,
-            )
+            );
+            if followed_match.mtype == MatchType::Crate
+                && namespace.contains(Namespace::Macro)
+                && (search_type == StartsWith || out.is_empty())
+            {
+                let exported = search_exported_macros(
+                    &pathseg.name,
+                    &followed_match.filepath,
+                    search_type,
+                    session,
+                );
+                extend_unique(&mut out, exported);
+            }
+            out
         }
         MatchType::Enum(_) | MatchType::Struct(_) | MatchType::Union(_) => get_impled_items(
             following_seg,
@@ -2027,7 +2830,7 @@ pub fn resolve_method(
                             search_type,
                             session,
                         ) {
-                            out.push(m);
+                            out.push(into_trait_item(m));
                         }
                     }
 
@@ -2087,6 +2890,7 @@ pub fn do_external_search(
                 mtype: MatchType::Module,
                 contextstr: context,
                 docs: String::new(),
+                visibility: Visibility::Public,
             });
         }
     } else {
@@ -2204,12 +3008,86 @@ fn collect_inherited_traits(trait_match: Match, s: &Session<'_>) -> Vec<Match> {
     res
 }
 
+/// Max number of `Deref::Target` applications in autoderef
+const AUTODEREF_LIMIT: usize = 16;
+
 pub fn search_for_fields_and_methods(
     context: Match,
     searchstr: &str,
     search_type: SearchType,
     only_methods: bool,
     session: &Session<'_>,
+) -> Vec<Match> {
+    let mut out = search_for_fields_and_methods_no_deref(
+        context.clone(),
+        searchstr,
+        search_type,
+        only_methods,
+        session,
+    );
+    // autoderef: like rustc, repeatedly apply `Deref::Target` and collect fields and methods
+    // of each type. Items found earlier shadow the ones with the same name found later.
+    let mut names: HashSet<String> = out.iter().map(|m| m.matchstr.clone()).collect();
+    let mut visited = vec![(context.filepath.clone(), context.point)];
+    let mut current = context;
+    for _ in 0..AUTODEREF_LIMIT {
+        match current.mtype {
+            MatchType::Struct(_) | MatchType::Enum(_) | MatchType::Union(_) => {}
+            _ => break,
+        }
+        let target = match get_deref_target(&current, session) {
+            Some(ty) => ty,
+            None => break,
+        };
+        debug!("[autoderef] {} derefs to {:?}", current.matchstr, target);
+        let next = match target.dereference() {
+            Ty::Match(m) => m,
+            Ty::PathSearch(paths) => match paths.resolve_as_match(session) {
+                Some(m) => m,
+                None => break,
+            },
+            Ty::Array(_, _) | Ty::Slice(_) => {
+                let mut m = primitive::PrimKind::Slice.to_module_match().unwrap();
+                m.matchstr = "[T]".to_owned();
+                m
+            }
+            ty @ Ty::TraitObject(_) => {
+                out.extend(
+                    get_field_matches_from_ty(ty, searchstr, search_type, session)
+                        .into_iter()
+                        .filter(|m| !names.contains(&m.matchstr)),
+                );
+                break;
+            }
+            _ => break,
+        };
+        if visited.contains(&(next.filepath.clone(), next.point)) {
+            break;
+        }
+        visited.push((next.filepath.clone(), next.point));
+        let level: Vec<_> = search_for_fields_and_methods_no_deref(
+            next.clone(),
+            searchstr,
+            search_type,
+            only_methods,
+            session,
+        )
+        .into_iter()
+        .filter(|m| !names.contains(&m.matchstr))
+        .collect();
+        names.extend(level.iter().map(|m| m.matchstr.clone()));
+        out.extend(level);
+        current = next;
+    }
+    out
+}
+
+fn search_for_fields_and_methods_no_deref(
+    context: Match,
+    searchstr: &str,
+    search_type: SearchType,
+    only_methods: bool,
+    session: &Session<'_>,
 ) -> Vec<Match> {
     let m = context;
     let mut out = Vec::new();
@@ -2271,12 +3149,31 @@ pub fn search_for_fields_and_methods(
             debug!("got a trait, looking for methods {}", m.matchstr);
             out.extend(search_for_trait_methods(m, searchstr, search_type, session))
         }
-        MatchType::TypeParameter(bounds) => {
+        MatchType::TypeParameter(ref bounds) => {
             debug!("got a trait bound, looking for methods {}", m.matchstr);
             let traits = bounds.get_traits(session);
-            traits.into_iter().for_each(|m| {
-                out.extend(search_for_trait_methods(m, searchstr, search_type, session))
-            });
+            let mut found_methods = HashSet::new();
+            for tr in &traits {
+                // supertraits are searched in `search_for_trait_items`
+                for method in search_for_trait_methods(tr.clone(), searchstr, search_type, session)
+                {
+                    if found_methods.insert(method.matchstr.clone()) {
+                        out.push(method);
+                    }
+                }
+            }
+            out.extend(
+                search_for_blanket_impl_methods(
+                    &traits,
+                    &m.filepath,
+                    m.point,
+                    searchstr,
+                    search_type,
+                    session,
+                )
+                .into_iter()
+                .filter(|method| !found_methods.contains(&method.matchstr)),
+            );
         }
         _ => {
             debug!(
@@ -2288,6 +3185,46 @@ pub fn search_for_fields_and_methods(
     out
 }
 
+/// Search methods callable on `dyn Trait`: methods of the trait and its supertraits, and
+/// inherent methods in `impl dyn Trait` blocks (like `<dyn Any>::downcast_ref`)
+fn search_for_trait_object_methods(
+    traitm: Match,
+    searchstr: &str,
+    search_type: SearchType,
+    session: &Session<'_>,
+) -> Vec<Match> {
+    let src = session.load_source_file(&traitm.filepath);
+    let scope_start = scopes::scope_start(src.as_src(), traitm.point);
+    let mut out = Vec::new();
+    for header in cached_generic_impls(&traitm.filepath, session, scope_start) {
+        if !header.is_trait_object() || header.is_trait() {
+            continue;
+        }
+        if header.self_path().name() != Some(traitm.matchstr.as_str()) {
+            continue;
+        }
+        debug!("found an impl for trait object!! {:?}", header);
+        let src = session.load_source_file(header.file_path());
+        out.extend(search_scope_for_methods(
+            header.scope_start(),
+            src.as_src(),
+            searchstr,
+            header.file_path(),
+            false,
+            false,
+            search_type,
+            session,
+        ));
+    }
+    out.extend(search_for_trait_methods(
+        traitm,
+        searchstr,
+        search_type,
+        session,
+    ));
+    out
+}
+
 #[inline(always)]
 fn search_for_trait_methods<'s, 'sess: 's>(
     traitm: Match,
@@ -2326,26 +3263,13 @@ fn search_for_trait_items<'s, 'sess: 's>(
             })
         })
         .flatten()
+        .map(into_trait_item)
 }
 
-fn search_for_deref_matches(
-    target_ty: Ty,      // target = ~
-    type_match: &Match, // the type which implements Deref
-    impl_header: &ImplHeader,
-    fieldsearchstr: &str,
-    session: &Session<'_>,
-) -> Vec<Match> {
-    match target_ty {
-        Ty::PathSearch(ref paths) => {
-            let ty = match get_assoc_type_from_header(&paths.path, type_match, impl_header, session)
-            {
-                Some(t) => t,
-                None => return vec![],
-            };
-            get_field_matches_from_ty(ty, fieldsearchstr, SearchType::StartsWith, session)
-        }
-        _ => get_field_matches_from_ty(target_ty, fieldsearchstr, SearchType::StartsWith, session),
-    }
+/// Items of traits and trait impls are as visible as the trait, whatever is written
+fn into_trait_item(mut m: Match) -> Match {
+    m.visibility = Visibility::Public;
+    m
 }
 
 pub(crate) fn get_field_matches_from_ty(
@@ -2385,10 +3309,15 @@ pub(crate) fn get_field_matches_from_ty(
             m.matchstr = "[T]".to_owned();
             search_for_fields_and_methods(m, searchstr, stype, false, session)
         }
-        Ty::TraitObject(traitbounds) => traitbounds
-            .into_iter()
-            .flat_map(|ps| get_field_matches_from_ty(Ty::PathSearch(ps), searchstr, stype, session))
-            .collect(),
+        Ty::TraitObject(traitbounds) => {
+            let mut found_methods = HashSet::new();
+            traitbounds
+                .get_traits(session)
+                .into_iter()
+                .flat_map(|tr| search_for_trait_object_methods(tr, searchstr, stype, session))
+                .filter(|m| found_methods.insert(m.matchstr.clone()))
+                .collect()
+        }
         Ty::Future(_, scope) => get_future(scope, session)
             .into_iter()
             .flat_map(|f| search_for_trait_methods(f, searchstr, stype, session))
@@ -2418,30 +3347,59 @@ fn get_assoc_type_from_header(
     session: &Session<'_>,
 ) -> Option<Ty> {
     debug!(
-        "[search_for_deref_matches] target: {:?} impl: {:?}",
+        "[get_assoc_type_from_header] target: {:?} impl: {:?}",
         target_path, impl_header
     );
-    if let Some((pos, _)) = impl_header.generics().search_param_by_path(target_path) {
-        type_match
-            .resolved_generics()
-            .nth(pos)
-            .map(|x| x.to_owned())
-    } else {
-        resolve_path_with_primitive(
-            &target_path,
-            impl_header.file_path(),
-            BytePos::ZERO,
-            SearchType::ExactMatch,
-            Namespace::Type,
-            session,
-        )
-        .into_iter()
-        .next()
-        .map(Ty::Match)
+    let generics = get_impl_generics_for(impl_header, type_match);
+    if let Some((_, param)) = generics.search_param_by_path(target_path) {
+        return param.resolved().map(|x| x.to_owned());
+    }
+    let mut path = target_path.clone();
+    path.replace_by_bounds(&generics);
+    ast::find_type_match(&path, impl_header.file_path(), BytePos::ZERO, session).map(Ty::Match)
+}
+
+/// Resolves type parameters of `impl_header` by matching its self type against `type_match`,
+/// e.g. `T` = `String` for `impl<T> Deref for Rc<T>` and `Rc<String>`
+fn get_impl_generics_for(impl_header: &ImplHeader, type_match: &Match) -> GenericsArgs {
+    let mut generics = impl_header.generics().clone();
+    let self_generics = match impl_header.self_path().segments.last() {
+        Some(seg) => &seg.generics,
+        None => return generics,
+    };
+    let type_generics = match type_match.to_generics() {
+        Some(gen) => gen,
+        None => return generics,
+    };
+    for (type_param, self_ty) in type_generics.zip_type_args(self_generics) {
+        let resolved = try_continue!(type_param.resolved());
+        if let Ty::PathSearch(ref paths) = self_ty {
+            let (pos, _) = try_continue!(generics.search_param_by_path(&paths.path));
+            generics.0[pos].resolve(resolved.to_owned());
+        }
+    }
+    generics
+}
+
+/// Substitutes the impl's type parameters in an associated type
+fn resolve_assoc_type(
+    item_ty: Ty,
+    type_match: &Match,
+    impl_header: &ImplHeader,
+    session: &Session<'_>,
+) -> Option<Ty> {
+    match item_ty {
+        Ty::PathSearch(paths) => {
+            get_assoc_type_from_header(&paths.path, type_match, impl_header, session)
+        }
+        _ => Some(
+            item_ty
+                .replace_by_resolved_generics_deep(&get_impl_generics_for(impl_header, type_match)),
+        ),
     }
 }
 
-fn get_std_macros(
+pub(crate) fn get_std_macros(
     searchstr: &str,
     search_type: SearchType,
     session: &Session<'_>,
@@ -2465,7 +3423,7 @@ fn get_std_macros(
         "alloc/src/macros.rs",
     ] {
         let macro_path = std_path.join(macro_file);
-        if !macro_path.exists() {
+        if !session.file_exists(&macro_path) {
             continue;
         }
         get_std_macros_(
@@ -2479,6 +3437,313 @@ fn get_std_macros(
     }
 }
 
+/// Searches macros in textual scope at `pos`: `macro_rules!` defined earlier in the enclosing
+/// modules, and the macros of `#[macro_use] mod`s and `#[macro_use] extern crate`s declared
+/// before it.
+pub(crate) fn get_crate_macros(
+    searchstr: &str,
+    filepath: &Path,
+    pos: BytePos,
+    search_type: SearchType,
+    session: &Session<'_>,
+    out: &mut Vec<Match>,
+) {
+    let crateroot = filepath
+        .parent()
+        .and_then(|dir| find_possible_crate_root_modules(dir, session).pop())
+        .unwrap_or_else(|| filepath.to_owned());
+    let mut walk = MacroScopeWalk::new(
+        searchstr,
+        search_type,
+        MacroWalkMode::Textual(filepath, pos),
+        session,
+    );
+    // the file may not be declared as a module of the crate root
+    if !walk.walk_file(&crateroot) {
+        walk.walk_file(filepath);
+    }
+    debug!("[get_crate_macros] {:?} in scope at {:?}", walk.out, pos);
+    extend_unique(out, walk.out);
+}
+
+/// Files defining `macro_rules!` macros, by the name of the macro
+pub(crate) type MacroFiles = HashMap<String, Vec<PathBuf>>;
+
+/// Names of the `macro_rules!` macros defined anywhere in the crate of `filepath`, with the
+/// files defining them, cached per crate root in the session.
+/// Returns `None` for files of std, whose macros aren't expanded.
+pub(crate) fn crate_macro_files(filepath: &Path, session: &Session<'_>) -> Option<Rc<MacroFiles>> {
+    if let Some(ref std_path) = *RUST_SRC_PATH {
+        if filepath.starts_with(std_path) {
+            return None;
+        }
+    }
+    let mut crateroot = crate_root_of(filepath, session).unwrap_or_else(|| filepath.to_owned());
+    let mut scopes = crate_module_scopes(&crateroot, session);
+    // a file which isn't declared as a module (yet) is searched on its own
+    if !scopes.iter().any(|(file, _)| file == filepath) {
+        crateroot = filepath.to_owned();
+        scopes = crate_module_scopes(&crateroot, session);
+    }
+    if let Some(macros) = session.crate_macros.borrow().get(&crateroot) {
+        return Some(Rc::clone(macros));
+    }
+    let mut macros = MacroFiles::new();
+    let mut files: Vec<&PathBuf> = Vec::new();
+    for (file, _) in scopes.iter() {
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+    for file in files {
+        let src = session.load_source_file(file);
+        let mut search_from = 0;
+        while let Some(n) = src[search_from..].find("macro_rules!") {
+            let start = search_from + n;
+            search_from = start + "macro_rules!".len();
+            if src[..start]
+                .chars()
+                .next_back()
+                .map_or(false, util::is_ident_char)
+            {
+                continue;
+            }
+            let name: String = src[search_from..]
+                .trim_start()
+                .chars()
+                .take_while(|&c| util::is_ident_char(c))
+                .collect();
+            if !name.is_empty() {
+                macros.entry(name).or_default().push(file.to_owned());
+            }
+        }
+    }
+    debug!("[crate_macro_files] {:?}: {:?}", crateroot, macros);
+    let macros = Rc::new(macros);
+    session
+        .crate_macros
+        .borrow_mut()
+        .insert(crateroot, Rc::clone(&macros));
+    Some(macros)
+}
+
+/// Searches `#[macro_export]`ed macros, which all live at the root of the crate
+pub(crate) fn search_exported_macros(
+    searchstr: &str,
+    crateroot: &Path,
+    search_type: SearchType,
+    session: &Session<'_>,
+) -> Vec<Match> {
+    // std macros are handled by `get_std_macros`
+    if let Some(ref std_path) = *RUST_SRC_PATH {
+        if crateroot.starts_with(std_path) {
+            return Vec::new();
+        }
+    }
+    let mut walk = MacroScopeWalk::new(searchstr, search_type, MacroWalkMode::Exported, session);
+    walk.walk_file(crateroot);
+    walk.out
+}
+
+fn extend_unique(out: &mut Vec<Match>, matches: Vec<Match>) {
+    for m in matches {
+        if !out
+            .iter()
+            .any(|o| o.filepath == m.filepath && o.point == m.point)
+        {
+            out.push(m);
+        }
+    }
+}
+
+enum MacroWalkMode<'a> {
+    /// Macros visible at the position, following `#[macro_use]` and the modules enclosing it
+    Textual(&'a Path, BytePos),
+    /// `#[macro_export]`ed macros in all modules
+    Exported,
+}
+
+/// Walks the module tree of a crate in textual order, collecting macro definitions
+struct MacroScopeWalk<'a, 's> {
+    searchstr: &'a str,
+    search_type: SearchType,
+    mode: MacroWalkMode<'a>,
+    session: &'a Session<'s>,
+    visited: Vec<PathBuf>,
+    out: Vec<Match>,
+}
+
+impl<'a, 's> MacroScopeWalk<'a, 's> {
+    fn new(
+        searchstr: &'a str,
+        search_type: SearchType,
+        mode: MacroWalkMode<'a>,
+        session: &'a Session<'s>,
+    ) -> Self {
+        MacroScopeWalk {
+            searchstr: searchstr.trim_end_matches('!'),
+            search_type,
+            mode,
+            session,
+            visited: Vec::new(),
+            out: Vec::new(),
+        }
+    }
+
+    /// Returns true if the walk stopped at the target position
+    fn walk_file(&mut self, filepath: &Path) -> bool {
+        if self.visited.iter().any(|p| p == filepath) {
+            return false;
+        }
+        self.visited.push(filepath.to_owned());
+        let src = self.session.load_source_file(filepath);
+        self.walk_scope(filepath, src.as_src(), BytePos::ZERO)
+    }
+
+    fn walk_scope(&mut self, filepath: &Path, src: Src<'_>, scope_start: BytePos) -> bool {
+        let target = match self.mode {
+            MacroWalkMode::Textual(target, pos) if target == filepath => Some(pos),
+            _ => None,
+        };
+        for range in src.iter_stmts() {
+            let start = scope_start + range.start;
+            let contains_target = match target {
+                Some(pos) if pos <= start => return true,
+                Some(pos) => pos <= scope_start + range.end,
+                None => false,
+            };
+            let blob = &src[range.to_range()];
+            if blob.starts_with('#') {
+                continue;
+            }
+            let item = trim_visibility(blob);
+            let item_start = start + BytePos(blob.len() - item.len());
+            if item.starts_with("macro_rules!") {
+                if self.is_textual()
+                    || matchers::has_outer_attribute(&src, range.start, "macro_export")
+                {
+                    self.push_macro(filepath, item, item_start);
+                }
+                continue;
+            }
+            let is_macro_use = matchers::has_outer_attribute(&src, range.start, "macro_use");
+            if let Some((_, name)) = item_name(item, "mod ") {
+                if let Some(brace) = item.find('{') {
+                    if !contains_target && !self.enters_module(is_macro_use) {
+                        continue;
+                    }
+                    let body_start = range.start + BytePos(blob.len() - item.len() + brace + 1);
+                    let body = ByteRange::new(body_start, range.end - BytePos(1));
+                    if self.walk_scope(filepath, src.shift_range(body), scope_start + body_start)
+                        || contains_target
+                    {
+                        return true;
+                    }
+                } else if let Some(modfile) = self.module_file(filepath, start, name) {
+                    let leads_to_target = match self.mode {
+                        MacroWalkMode::Textual(target, _) => module_contains(&modfile, target),
+                        MacroWalkMode::Exported => false,
+                    };
+                    if leads_to_target {
+                        self.walk_file(&modfile);
+                        return true;
+                    }
+                    if self.enters_module(is_macro_use) {
+                        self.walk_file(&modfile);
+                    }
+                }
+            } else if let Some((_, name)) = item_name(item, "extern crate ") {
+                if !is_macro_use || !self.is_textual() {
+                    continue;
+                }
+                if let Some(cratefile) = get_crate_file(name, filepath, self.session) {
+                    let exported = search_exported_macros(
+                        self.searchstr,
+                        &cratefile,
+                        self.search_type,
+                        self.session,
+                    );
+                    extend_unique(&mut self.out, exported);
+                }
+            }
+            if contains_target {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_textual(&self) -> bool {
+        match self.mode {
+            MacroWalkMode::Textual(..) => true,
+            MacroWalkMode::Exported => false,
+        }
+    }
+
+    /// Macros of a module stay in textual scope after it only with `#[macro_use]`
+    fn enters_module(&self, is_macro_use: bool) -> bool {
+        is_macro_use || !self.is_textual()
+    }
+
+    fn module_file(&self, filepath: &Path, point: BytePos, name: &str) -> Option<PathBuf> {
+        resolve_module_file(name, point, filepath, self.session)
+    }
+
+    fn push_macro(&mut self, filepath: &Path, item: &str, item_start: BytePos) {
+        let (offset, name) = match item_name(item, "macro_rules!") {
+            Some((offset, name)) if symbol_matches(self.search_type, self.searchstr, name) => {
+                (offset, name)
+            }
+            _ => return,
+        };
+        let point = item_start + offset;
+        let raw_src = self.session.load_raw_file(filepath);
+        let matchstr = format!("{}!", name);
+        // a later definition shadows the earlier one
+        self.out.retain(|m| m.matchstr != matchstr);
+        self.out.push(Match {
+            matchstr,
+            filepath: filepath.to_owned(),
+            point,
+            coords: raw_src.point_to_coords(point),
+            local: false,
+            mtype: MatchType::Macro,
+            contextstr: matchers::first_line(item),
+            docs: find_doc(&raw_src, item_start),
+            visibility: Visibility::Public,
+        });
+    }
+}
+
+/// Returns the identifier following `keyword` at the start of `item`, with its offset
+fn item_name<'i>(item: &'i str, keyword: &str) -> Option<(BytePos, &'i str)> {
+    if !item.starts_with(keyword) {
+        return None;
+    }
+    let rest = &item[keyword.len()..];
+    let start = BytePos(item.len() - rest.trim_start().len());
+    let end = find_ident_end(item, start);
+    if start == end {
+        None
+    } else {
+        Some((start, &item[start.0..end.0]))
+    }
+}
+
+/// Checks if `target` is the file of the module `modfile` or one of its submodules
+fn module_contains(modfile: &Path, target: &Path) -> bool {
+    if modfile == target {
+        return true;
+    }
+    if modfile.ends_with("mod.rs") {
+        modfile
+            .parent()
+            .map_or(false, |dir| target.starts_with(dir))
+    } else {
+        target.starts_with(modfile.with_extension(""))
+    }
+}
+
 fn get_std_macros_(
     macro_path: &Path,
     searchstr: &str,
@@ -2538,6 +3803,7 @@ fn get_std_macros_(
             mtype: MatchType::Macro,
             contextstr: matchers::first_line(blob),
             docs: matchers::find_doc(&raw_src, range.start),
+            visibility: Visibility::Public,
         })
     }));
     if let Some(builtin_start) = builtin_start {
@@ -2555,6 +3821,7 @@ fn get_std_macros_(
                 mtype: MatchType::Macro,
                 contextstr: matchers::first_line(blob),
                 docs: matchers::find_doc(&raw_src, range.start),
+                visibility: Visibility::Public,
             })
         }));
     }
@@ -2580,12 +3847,7 @@ pub(crate) fn get_iter_item(selfm: &Match, session: &Session<'_>) -> Option<Ty>
     );
     item.into_iter()
         .next()
-        .and_then(|(_, item_ty)| match item_ty {
-            Ty::PathSearch(paths) => {
-                get_assoc_type_from_header(&paths.path, selfm, &iter_header, session)
-            }
-            _ => Some(item_ty),
-        })
+        .and_then(|(_, item_ty)| resolve_assoc_type(item_ty, selfm, &iter_header, session))
 }
 
 pub(crate) fn get_tuple_field_matches<'a, 'b: 'a>(
@@ -2601,6 +3863,7 @@ pub(crate) fn get_tuple_field_matches<'a, 'b: 'a>(
                 .map(|mut m| {
                     m.matchstr = field.to_owned();
                     m.mtype = MatchType::StructField;
+                    m.visibility = Visibility::Public;
                     m
                 })
         } else {
@@ -2628,6 +3891,33 @@ pub(crate) fn get_index_output(selfm: &Match, session: &Session<'_>) -> Option<T
     get_associated_type_match(&index_header, "Output", selfm, session)
 }
 
+/// Get the `Deref::Target` of `selfm`, if it implements `Deref`
+pub(crate) fn get_deref_target(selfm: &Match, session: &Session<'_>) -> Option<Ty> {
+    // the impl is cached per type, as autoderef asks for it at every level
+    let key = (selfm.filepath.clone(), selfm.point);
+    let cached = session.deref_impls.borrow().get(&key).cloned();
+    let deref_header = match cached {
+        Some(header) => header,
+        None => {
+            let header = search_trait_impls(
+                selfm.point,
+                &selfm.matchstr,
+                &["Deref"],
+                true,
+                &selfm.filepath,
+                selfm.local,
+                session,
+            )
+            .into_iter()
+            .next()
+            .map(Rc::new);
+            session.deref_impls.borrow_mut().insert(key, header.clone());
+            header
+        }
+    }?;
+    get_associated_type_match(&deref_header, "Target", selfm, session)
+}
+
 pub(crate) fn get_associated_type_match(
     impl_header: &ImplHeader,
     type_name: &str,
@@ -2643,12 +3933,7 @@ pub(crate) fn get_associated_type_match(
     output
         .into_iter()
         .next()
-        .and_then(|(_, item_ty)| match item_ty {
-            Ty::PathSearch(paths) => {
-                get_assoc_type_from_header(&paths.path, context, impl_header, session)
-            }
-            _ => Some(item_ty),
-        })
+        .and_then(|(_, item_ty)| resolve_assoc_type(item_ty, context, impl_header, session))
 }
 
 pub(crate) fn get_struct_fields(
@@ -2747,3 +4032,32 @@ pub(crate) fn resolve_binary_expr_type(
         Some(Ty::Match(base_type.clone()))
     }
 }
+
+/// Resolves the type of a unary `-` or `!` expression
+/// # Arguments
+/// * base_type: the type of the operand
+/// * op: the operator
+pub(crate) fn resolve_unary_expr_type(
+    base_type: &Match,
+    op: UnOp,
+    session: &Session<'_>,
+) -> Option<Ty> {
+    let trait_name = typeinf::get_unary_operator_trait(op)?;
+    let matching_impl = search_trait_impls(
+        base_type.point,
+        &base_type.matchstr,
+        &[trait_name],
+        true,
+        &base_type.filepath,
+        base_type.local,
+        session,
+    )
+    .into_iter()
+    .next();
+    matching_impl
+        .and_then(|trait_impl| {
+            get_associated_type_match(&trait_impl, "Output", &base_type, session)
+        })
+        // default to base type (e.g. for primitives) if an impl can't be found
+        .or_else(|| Some(Ty::Match(base_type.clone())))
+}
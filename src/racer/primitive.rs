@@ -1,4 +1,4 @@
-use crate::core::{BytePos, Match, MatchType, Namespace, SearchType, Session};
+use crate::core::{BytePos, Match, MatchType, Namespace, SearchType, Session, Visibility};
 use crate::matchers::ImportInfo;
 use crate::nameres::{self, RUST_SRC_PATH};
 use rustc_ast::ast::{IntTy, LitIntType, UintTy};
@@ -81,7 +81,7 @@ impl PrimKind {
     }
     fn impl_files(self) -> Option<&'static [&'static str]> {
         match self {
-            PrimKind::Bool => None,
+            PrimKind::Bool => Some(&["core/src/bool.rs"]),
             PrimKind::Never => None,
             PrimKind::Char => Some(&["core/src/char/methods.rs"]),
             PrimKind::Unit => None,
@@ -161,6 +161,7 @@ impl PrimKind {
             mtype: MatchType::Builtin(self),
             contextstr: String::new(),
             docs: String::new(),
+            visibility: Visibility::Public,
         })
     }
     pub fn to_doc_match(self, session: &Session<'_>) -> Option<Match> {
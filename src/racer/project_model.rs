@@ -16,4 +16,13 @@ pub trait ProjectModelProvider {
         search_fn: Box<dyn Fn(&str) -> bool>,
     ) -> Vec<(String, PathBuf)>;
     fn resolve_dependency(&self, manifest: &Path, dep_name: &str) -> Option<PathBuf>;
+    /// Returns the features enabled for the package of `manifest`, if known.
+    /// Items gated on features are only filtered when this returns `Some`.
+    fn enabled_features(&self, _manifest: &Path) -> Option<Vec<String>> {
+        None
+    }
+    /// Returns the `OUT_DIR` the build script of the package of `manifest` wrote to
+    fn out_dir(&self, _manifest: &Path) -> Option<PathBuf> {
+        None
+    }
 }
@@ -1,11 +1,10 @@
 use crate::ast_types::Path as RacerPath;
 #[cfg(test)]
 use crate::core::{self, Coordinate};
-use crate::core::{BytePos, ByteRange, CompletionType, Namespace, RangedRawSrc, Src};
+use crate::core::{BytePos, ByteRange, CompletionType, Namespace, Src};
 
 use crate::util::{self, char_at};
 use std::iter::Iterator;
-use std::path::{Path, PathBuf};
 use std::str::from_utf8;
 
 fn find_close<'a, A>(iter: A, open: u8, close: u8, level_end: u32) -> Option<BytePos>
@@ -158,38 +157,6 @@ fn get_local_module_path_(msrc: Src<'_>, point: BytePos, out: &mut Vec<String>)
     }
 }
 
-pub fn get_module_file_from_path(
-    msrc: Src<'_>,
-    point: BytePos,
-    parentdir: &Path,
-    raw_src: RangedRawSrc,
-) -> Option<PathBuf> {
-    let mut iter = msrc.iter_stmts();
-    while let Some(range) = iter.next() {
-        let blob = &raw_src[range.to_range()];
-        let start = range.start;
-        if blob.starts_with("#[path ") {
-            if let Some(ByteRange {
-                start: _,
-                end: modend,
-            }) = iter.next()
-            {
-                if start < point && modend > point {
-                    let pathstart = blob.find('"')? + 1;
-                    let pathend = blob[pathstart..].find('"').unwrap();
-                    let path = &blob[pathstart..pathstart + pathend];
-                    debug!("found a path attribute, path = |{}|", path);
-                    let filepath = parentdir.join(path);
-                    if filepath.exists() {
-                        return Some(filepath);
-                    }
-                }
-            }
-        }
-    }
-    None
-}
-
 // TODO(kngwyu): this functions shouldn't be generic
 pub fn find_impl_start(msrc: Src<'_>, point: BytePos, scopestart: BytePos) -> Option<BytePos> {
     let len = point - scopestart;
@@ -355,7 +322,7 @@ mod test_get_start_of_pattern {
 }
 
 pub fn expand_search_expr(msrc: &str, point: BytePos) -> ByteRange {
-    let start = get_start_of_search_expr(msrc, point);
+    let start = get_start_of_qualified_path(msrc, get_start_of_search_expr(msrc, point));
     ByteRange::new(start, util::find_ident_end(msrc, point))
 }
 
@@ -435,6 +402,50 @@ mod test_expand_seacrh_expr {
     fn handles_double_dot() {
         assert_eq!((2, 5), expand_search_expr_("..foo", 4))
     }
+
+    #[test]
+    fn handles_qualified_path() {
+        assert_eq!(
+            (8, 27),
+            expand_search_expr_("let a = <T as Trait>::CONST + 1", 25)
+        )
+    }
+
+    #[test]
+    fn ignores_turbofish() {
+        assert_eq!((16, 21), expand_search_expr_("let a = Vec::<T>::new", 19))
+    }
+}
+
+#[cfg(test)]
+mod test_qualified_path {
+    use super::expr_to_path;
+    fn path_names(s: &str) -> Vec<String> {
+        expr_to_path(s)
+            .0
+            .segments
+            .into_iter()
+            .map(|seg| seg.name)
+            .collect()
+    }
+    #[test]
+    fn converts_qualified_self() {
+        assert_eq!(path_names("<T>::new"), vec!["T", "new"]);
+    }
+
+    #[test]
+    fn converts_qualified_trait() {
+        assert_eq!(
+            path_names("<Vec<T> as IntoIterator<T>>::Item"),
+            vec!["IntoIterator", "Item"]
+        );
+    }
+
+    #[test]
+    fn keeps_self_type_of_qualified_trait() {
+        let qself = expr_to_path("<Vec<T> as IntoIterator<T>>::Item").0.qself;
+        assert_eq!(qself.map(|p| p.to_string()), Some("Vec".to_owned()));
+    }
 }
 
 fn fill_gaps(buffer: &str, result: &mut String, start: usize, prev: usize) {
@@ -681,7 +692,97 @@ pub(crate) fn get_current_stmt<'c>(src: Src<'c>, pos: BytePos) -> (BytePos, Stri
     )
 }
 
+/// Extends the start of a search expression over the qualified self type,
+/// e.g. `<T as Trait>` in `<T as Trait>::CONST`
+pub(crate) fn get_start_of_qualified_path(src: &str, start: BytePos) -> BytePos {
+    if !src[start.0..].starts_with("::") || !src[..start.0].ends_with('>') {
+        return start;
+    }
+    let mut level = 0;
+    for (i, b) in src.as_bytes()[..start.0].iter().enumerate().rev() {
+        match *b {
+            b'>' => level += 1,
+            b'<' => {
+                level -= 1;
+                if level > 0 {
+                    continue;
+                }
+                // `Vec::<T>::new` isn't a qualified path
+                return match src[..i].trim_end().chars().last() {
+                    Some(c) if util::is_search_expr_char(c) => start,
+                    _ => BytePos(i),
+                };
+            }
+            b';' | b'{' | b'}' => break,
+            _ => {}
+        }
+    }
+    start
+}
+
+/// Converts a qualified path to a plain one,
+/// `<T>::X` to `T::X` and `<T as Trait>::X` to `Trait::X` qualified by `T`
+fn qualified_expr_to_path(expr: &str) -> Option<(RacerPath, Namespace)> {
+    let mut level = 0;
+    let mut as_pos = None;
+    let mut end = None;
+    for (i, b) in expr.as_bytes().iter().enumerate() {
+        match *b {
+            b'<' => level += 1,
+            b'>' => {
+                level -= 1;
+                if level == 0 {
+                    end = Some(i);
+                    break;
+                }
+            }
+            b' ' if level == 1 && as_pos.is_none() && expr[i..].starts_with(" as ") => {
+                as_pos = Some(i)
+            }
+            _ => {}
+        }
+    }
+    let end = end?;
+    let rest = expr[end + 1..].trim_start();
+    if !rest.starts_with("::") {
+        return None;
+    }
+    // generic arguments aren't necessary to resolve associated items
+    let strip_generics = |qualified: &str| {
+        let mut path = String::new();
+        let mut level = 0;
+        for c in qualified.chars() {
+            match c {
+                '<' => level += 1,
+                '>' => level -= 1,
+                _ if level == 0 && !c.is_whitespace() => path.push(c),
+                _ => {}
+            }
+        }
+        path
+    };
+    match as_pos {
+        Some(pos) => {
+            let mut path = strip_generics(&expr[pos + 4..end]);
+            path.push_str(rest);
+            let (mut path, namespace) = expr_to_path(&path);
+            path.qself = Some(Box::new(expr_to_path(&strip_generics(&expr[1..pos])).0));
+            Some((path, namespace))
+        }
+        None => {
+            let mut path = strip_generics(&expr[1..end]);
+            path.push_str(rest);
+            Some(expr_to_path(&path))
+        }
+    }
+}
+
 pub(crate) fn expr_to_path(expr: &str) -> (RacerPath, Namespace) {
+    if expr.starts_with('<') {
+        if let Some(res) = qualified_expr_to_path(expr) {
+            return res;
+        }
+    }
     let is_global = expr.starts_with("::");
     let v: Vec<_> = (if is_global { &expr[2..] } else { expr })
         .split("::")
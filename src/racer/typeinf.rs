@@ -1,7 +1,8 @@
 //! Type inference
 //! THIS MODULE IS ENTIRELY TOO UGLY SO REALLY NEADS REFACTORING(kngwyu)
 use crate::ast;
-use crate::ast_types::{Pat, Ty};
+use crate::ast_types::{GenericsArgs, Pat, Path as RacerPath, TraitBounds, Ty};
+use crate::attr_macros::{self, AttrRewrite};
 use crate::core;
 use crate::core::{
     BytePos, ByteRange, Match, MatchType, Namespace, Scope, SearchType, Session, SessionExt, Src,
@@ -11,7 +12,7 @@ use crate::nameres;
 use crate::primitive::PrimKind;
 use crate::scopes;
 use crate::util::{self, txt_matches};
-use rustc_ast::ast::BinOpKind;
+use rustc_ast::ast::{BinOpKind, BindingMode, Mutability, UnOp};
 use std::path::Path;
 
 // Removes the body of the statement (anything in the braces {...}), leaving just
@@ -40,6 +41,107 @@ pub(crate) fn get_operator_trait(op: BinOpKind) -> &'static str {
     }
 }
 
+/// Get the trait name implementing which overrides the unary operator `op`
+/// For `*`, it is `None` since dereference is resolved via `get_type_of_deref`
+pub(crate) fn get_unary_operator_trait(op: UnOp) -> Option<&'static str> {
+    match op {
+        UnOp::Neg => Some("Neg"),
+        UnOp::Not => Some("Not"),
+        UnOp::Deref => None,
+    }
+}
+
+/// Get the type of `*expr` from the type of `expr`
+pub(crate) fn get_type_of_deref(ty: Ty, session: &Session<'_>) -> Option<Ty> {
+    match ty {
+        Ty::RefPtr(ty, _) | Ty::Ptr(ty, _) => Some(*ty),
+        Ty::Match(m) => nameres::get_deref_target(&m, session),
+        Ty::PathSearch(paths) => {
+            let m = paths.resolve_as_match(session)?;
+            nameres::get_deref_target(&m, session)
+        }
+        _ => None,
+    }
+}
+
+/// Result types of std macros, written as Rust types
+/// `!` means the macro diverges
+const STD_MACRO_TYPES: &[(&str, &str)] = &[
+    ("vec", "std::vec::Vec"),
+    ("format", "std::string::String"),
+    ("format_args", "std::fmt::Arguments"),
+    ("matches", "bool"),
+    ("cfg", "bool"),
+    ("concat", "&'static str"),
+    ("stringify", "&'static str"),
+    ("include_str", "&'static str"),
+    ("env", "&'static str"),
+    ("file", "&'static str"),
+    ("module_path", "&'static str"),
+    ("option_env", "std::option::Option<&'static str>"),
+    ("include_bytes", "&'static [u8]"),
+    ("line", "u32"),
+    ("column", "u32"),
+    ("assert", "()"),
+    ("assert_eq", "()"),
+    ("assert_ne", "()"),
+    ("debug_assert", "()"),
+    ("debug_assert_eq", "()"),
+    ("debug_assert_ne", "()"),
+    ("print", "()"),
+    ("println", "()"),
+    ("eprint", "()"),
+    ("eprintln", "()"),
+    ("panic", "!"),
+    ("todo", "!"),
+    ("unimplemented", "!"),
+    ("unreachable", "!"),
+];
+
+/// Get the result type of calling the macro `name`
+/// Types registered via `Session::add_macro_type` are searched first, then
+/// `STD_MACRO_TYPES` unless `name` resolves to a macro outside of std.
+pub(crate) fn get_type_of_macro_call(
+    name: &str,
+    scope: &Scope,
+    session: &Session<'_>,
+) -> Option<Ty> {
+    let type_str = match session.macro_type(name) {
+        Some(s) => s,
+        None => {
+            let (_, type_str) = STD_MACRO_TYPES.iter().find(|(n, _)| *n == name)?;
+            let is_std = |path: &Path| {
+                nameres::RUST_SRC_PATH
+                    .as_ref()
+                    .map_or(false, |std_path| path.starts_with(std_path))
+            };
+            let shadowed = nameres::resolve_path_with_primitive(
+                &RacerPath::from_vec(false, vec![&format!("{}!", name)]),
+                &scope.filepath,
+                scope.point,
+                SearchType::ExactMatch,
+                Namespace::Global,
+                session,
+            )
+            .into_iter()
+            .any(|m| matches!(m.mtype, MatchType::Macro) && !is_std(&m.filepath));
+            if shadowed {
+                return None;
+            }
+            type_str.to_string()
+        }
+    };
+    debug!("[get_type_of_macro_call] {}! -> {}", name, type_str);
+    if type_str.trim() == "!" {
+        return Some(Ty::Never);
+    }
+    let ty = ast::parse_type(format!("type __RacerMacroTy = {};", type_str), scope).type_?;
+    match ty {
+        Ty::PathSearch(paths) => paths.resolve_as_match(session).map(Ty::Match),
+        _ => Some(ty),
+    }
+}
+
 // TODO(kngwyu): use libsyntax parser
 pub fn first_param_is_self(blob: &str) -> bool {
     // Restricted visibility introduces the possibility of `pub(in ...)` at the start
@@ -167,6 +269,7 @@ pub fn get_type_of_self(
                 mtype: core::MatchType::Trait,
                 contextstr: matchers::first_line(&msrc[start.0..]),
                 docs: String::new(),
+                visibility: util::visibility_of(&msrc[start.0..]),
             }))
         })
     }
@@ -217,33 +320,113 @@ pub(crate) fn resolve_lvalue_ty<'a>(
     pos: BytePos,
     session: &Session<'_>,
 ) -> Option<Ty> {
+    resolve_lvalue_ty_with_mode(l_value, r_value, None, query, fpath, pos, session)
+}
+
+/// Peels references off the type matched by a non-reference pattern, updating the
+/// default binding mode as rustc's match ergonomics do.
+/// `mode` is `None` for 'move', `Some(mutability)` for 'ref' and 'ref mut'.
+pub(crate) fn peel_refs_for_pattern(
+    mut ty: Ty,
+    mut mode: Option<Mutability>,
+) -> (Ty, Option<Mutability>) {
+    while let Ty::RefPtr(inner, mutbl) = ty {
+        mode = match mode {
+            Some(Mutability::Not) => Some(Mutability::Not),
+            _ => Some(mutbl),
+        };
+        ty = *inner;
+    }
+    (ty, mode)
+}
+
+/// The type of a binding `bm name` given the type it matches and the default binding mode
+pub(crate) fn type_of_binding(ty: Ty, bm: BindingMode, mode: Option<Mutability>) -> Ty {
+    match (bm, mode) {
+        (BindingMode::ByRef(mutbl), _) | (BindingMode::ByValue(_), Some(mutbl)) => {
+            Ty::RefPtr(Box::new(ty), mutbl)
+        }
+        (BindingMode::ByValue(_), None) => ty,
+    }
+}
+
+fn resolve_lvalue_ty_with_mode(
+    l_value: Pat,
+    r_value: Option<Ty>,
+    mode: Option<Mutability>,
+    query: &str,
+    fpath: &Path,
+    pos: BytePos,
+    session: &Session<'_>,
+) -> Option<Ty> {
+    let (r_value, mode) = match l_value {
+        Pat::Tuple(_) | Pat::TupleStruct(..) | Pat::Struct(..) | Pat::Slice(_) => match r_value {
+            Some(ty) => {
+                let (ty, mode) = peel_refs_for_pattern(ty, mode);
+                (Some(ty), mode)
+            }
+            None => (None, mode),
+        },
+        _ => (r_value, mode),
+    };
+    let resolve = |pat: Pat, ty: Option<Ty>, mode: Option<Mutability>| {
+        resolve_lvalue_ty_with_mode(pat, ty, mode, query, fpath, pos, session)
+    };
     match l_value {
-        Pat::Ident(_bi, name) => {
-            if name != query {
-                return None;
+        Pat::Ident(bi, name, sub) => {
+            if name == query {
+                return r_value.map(|ty| type_of_binding(ty, bi, mode));
             }
-            r_value
+            resolve(*sub?, r_value, mode)
         }
+        Pat::Box(pat) => {
+            let boxed = match r_value?.dereference() {
+                Ty::Match(ref m) if m.matchstr == "Box" => m.resolved_generics().next().cloned(),
+                Ty::PathSearch(ref paths) if paths.path.name() == Some("Box") => {
+                    paths.path.generic_types().next().cloned()
+                }
+                _ => None,
+            };
+            resolve(*pat, boxed, mode)
+        }
+        Pat::Slice(pats) => {
+            let elem = match r_value? {
+                Ty::Array(elem, _) | Ty::Slice(elem) => *elem,
+                _ => return None,
+            };
+            for p in pats {
+                // rest @ ..
+                if let Pat::Ident(bi, ref name, Some(ref sub)) = p {
+                    if let Pat::Rest = **sub {
+                        if name != query {
+                            continue;
+                        }
+                        return Some(type_of_binding(Ty::Slice(Box::new(elem)), bi, mode));
+                    }
+                }
+                let ret = try_continue!(resolve(p, Some(elem.clone()), mode));
+                return Some(ret);
+            }
+            None
+        }
+        Pat::Or(pats) => pats
+            .into_iter()
+            .filter_map(|p| resolve(p, r_value.clone(), mode))
+            .next(),
         Pat::Tuple(pats) => {
             if let Ty::Tuple(ty) = r_value? {
                 for (p, t) in pats.into_iter().zip(ty) {
-                    let ret = try_continue!(resolve_lvalue_ty(p, t, query, fpath, pos, session,));
+                    let ret = try_continue!(resolve(p, t, mode));
                     return Some(ret);
                 }
             }
             None
         }
-        Pat::Ref(pat, _) => {
-            if let Some(ty) = r_value {
-                if let Ty::RefPtr(ty, _) = ty {
-                    resolve_lvalue_ty(*pat, Some(*ty), query, fpath, pos, session)
-                } else {
-                    resolve_lvalue_ty(*pat, Some(ty), query, fpath, pos, session)
-                }
-            } else {
-                resolve_lvalue_ty(*pat, None, query, fpath, pos, session)
-            }
-        }
+        // `&pat` resets the default binding mode to 'move'
+        Pat::Ref(pat, _) => match r_value {
+            Some(Ty::RefPtr(ty, _)) => resolve(*pat, Some(*ty), None),
+            ty => resolve(*pat, ty, None),
+        },
         Pat::TupleStruct(path, pats) => {
             let ma = ast::find_type_match(&path, fpath, pos, session)?;
             match &ma.mtype {
@@ -251,14 +434,13 @@ pub(crate) fn resolve_lvalue_ty<'a>(
                     for (pat, (_, _, t)) in
                         pats.into_iter().zip(get_tuplestruct_fields(&ma, session))
                     {
-                        let ret =
-                            try_continue!(resolve_lvalue_ty(pat, t, query, fpath, pos, session));
+                        let ret = try_continue!(resolve(pat, t, mode));
                         return Some(ret);
                     }
                     None
                 }
                 MatchType::EnumVariant(enum_) => {
-                    let generics = if let Some(Ty::Match(match_)) = r_value.map(Ty::dereference) {
+                    let generics = if let Some(Ty::Match(match_)) = r_value {
                         match_.into_generics()
                     } else {
                         enum_.to_owned().and_then(|ma| ma.into_generics())
@@ -273,8 +455,7 @@ pub(crate) fn resolve_lvalue_ty<'a>(
                         if let Some(ref gen) = generics {
                             t = t.map(|ty| ty.replace_by_resolved_generics(&gen));
                         }
-                        let ret =
-                            try_continue!(resolve_lvalue_ty(pat, t, query, fpath, pos, session));
+                        let ret = try_continue!(resolve(pat, t, mode));
                         return Some(ret);
                     }
                     None
@@ -317,7 +498,11 @@ fn get_type_of_for_arg(m: &Match, session: &Session<'_>) -> Option<Ty> {
             Ty::PathSearch(paths) => {
                 nameres::get_iter_item(&paths.resolve_as_match(session)?, session)
             }
-            Ty::RefPtr(ty, _) => get_item(*ty, session),
+            // `for x in &collection` iterates over references to the items
+            Ty::RefPtr(ty, mutbl) => {
+                get_item(*ty, session).map(|item| Ty::RefPtr(Box::new(item), mutbl))
+            }
+            Ty::Array(elem, _) | Ty::Slice(elem) => Some(*elem),
             _ => None,
         }
     }
@@ -334,9 +519,19 @@ fn get_type_of_for_arg(m: &Match, session: &Session<'_>) -> Option<Ty> {
 fn get_type_of_if_let(m: &Match, session: &Session<'_>, start: BytePos) -> Option<Ty> {
     // HACK: use outer scope when getting r-value's type
     let scope = Scope::new(m.filepath.clone(), start);
+    // parse `let PAT = EXPR else { .. };` as `if let PAT = EXPR {}`
+    let let_else = if m.contextstr.starts_with("let ") {
+        matchers::find_let_else(&m.contextstr)
+    } else {
+        None
+    };
+    let src = match let_else {
+        Some(else_start) => format!("if {} {{}}", m.contextstr[..else_start].trim_end()),
+        None => m.contextstr.clone(),
+    };
     let ast::IfLetVisitor {
         let_pat, rh_expr, ..
-    } = ast::parse_if_let(m.contextstr.clone(), scope, session);
+    } = ast::parse_if_let(src, scope, session);
     debug!(
         "[get_type_of_if_let] match: {:?}\n  let: {:?}\n  rh: {:?},",
         m, let_pat, rh_expr,
@@ -518,6 +713,21 @@ pub fn get_return_type_of_function(
     fnmatch: &Match,
     contextm: &Match,
     session: &Session<'_>,
+) -> Option<Ty> {
+    get_return_type_of_function_call(fnmatch, contextm, &[], &Vec::new, session)
+}
+
+/// Get the return type of calling `fnmatch`, resolving the function's own type parameters
+/// from the turbofish(e.g. `parse::<u32>()`) and the types of arguments.
+/// `args` is only called when the function has type parameters, and must give the receiver
+/// first when `fnmatch` is called as a method.
+/// For closures passed as arguments, `args` gives the type of the closure body.
+pub(crate) fn get_return_type_of_function_call(
+    fnmatch: &Match,
+    contextm: &Match,
+    turbofish: &[Ty],
+    args: &dyn Fn() -> Vec<Option<Ty>>,
+    session: &Session<'_>,
 ) -> Option<Ty> {
     let src = session.load_source_file(&fnmatch.filepath);
     let point = scopes::expect_stmt_start(src.as_src(), fnmatch.point);
@@ -527,6 +737,16 @@ pub fn get_return_type_of_function(
     let mut scope = Scope::from_match(fnmatch);
     // TODO(kngwyu): if point <= 5 scope is incorrect
     scope.point = point.checked_sub("impl b{".len()).unwrap_or(BytePos::ZERO);
+    let fn_generics = if declares_type_params(&src[point.0..point.0 + block_start]) {
+        let (inputs, mut generics) =
+            ast::parse_fn_args_and_generics(decl.clone(), scope.clone(), scope.point.0 as i32);
+        if generics.args().next().is_some() {
+            infer_fn_generics(&mut generics, &inputs, turbofish, &args(), session);
+        }
+        generics
+    } else {
+        GenericsArgs::default()
+    };
     let (ty, is_async) = ast::parse_fn_output(decl, scope);
     let resolve_ty = |ty| {
         if let Some(Ty::PathSearch(ref paths)) = ty {
@@ -535,6 +755,11 @@ pub fn get_return_type_of_function(
                 if "Self" == path_seg.name {
                     return get_type_of_self_arg(fnmatch, src.as_src(), session);
                 }
+                if let Some((_, param)) = fn_generics.search_param_by_path(path) {
+                    if let Some(resolved) = param.resolved() {
+                        return Some(resolved.to_owned());
+                    }
+                }
                 if path.segments.len() == 1 && path_seg.generics.is_empty() {
                     for type_param in fnmatch.generics() {
                         if type_param.name() == &path_seg.name {
@@ -544,17 +769,177 @@ pub fn get_return_type_of_function(
                 }
             }
         }
-        ty
-    };
-    resolve_ty(ty).map(|ty| {
-        if is_async {
-            Ty::Future(Box::new(ty), Scope::from_match(fnmatch))
+        if fn_generics.args().any(|param| param.resolved().is_some()) {
+            ty.map(|ty| ty.replace_by_resolved_generics_deep(&fn_generics))
         } else {
             ty
         }
+    };
+    let rewrite = if is_async {
+        attr_macros::rewrite_of_fn(src.as_src(), point, fnmatch)
+    } else {
+        None
+    };
+    resolve_ty(ty).map(|ty| match rewrite {
+        _ if !is_async => ty,
+        Some(AttrRewrite::BlockOn) => ty,
+        Some(AttrRewrite::BoxedFuture) => {
+            let future = Ty::Future(Box::new(ty), Scope::from_match(fnmatch));
+            pin_box(future.clone(), fnmatch, session).unwrap_or(future)
+        }
+        None => Ty::Future(Box::new(ty), Scope::from_match(fnmatch)),
     })
 }
 
+/// Makes `Pin<Box<ty>>`
+fn pin_box(ty: Ty, fnmatch: &Match, session: &Session<'_>) -> Option<Ty> {
+    let find_std_type = |segments| {
+        let path = RacerPath::from_vec(false, segments);
+        ast::find_type_match(&path, &fnmatch.filepath, fnmatch.point, session)
+    };
+    let mut boxed = find_std_type(vec!["std", "boxed", "Box"])?;
+    let mut pin = find_std_type(vec!["std", "pin", "Pin"])?;
+    boxed.resolve_generics(&[ty]);
+    pin.resolve_generics(&[Ty::Match(boxed)]);
+    Some(Ty::Match(pin))
+}
+
+/// Checks if the function declaration `decl` has generic parameters, e.g. `fn f<T>(t: T)`
+fn declares_type_params(decl: &str) -> bool {
+    let name_start = match util::txt_matches_with_pos(SearchType::ExactMatch, "fn", decl) {
+        Some(pos) => pos + "fn".len(),
+        None => return false,
+    };
+    decl[name_start..]
+        .trim_start()
+        .trim_start_matches(util::is_ident_char)
+        .trim_start()
+        .starts_with('<')
+}
+
+/// Resolves the function's type parameters by turbofish and argument types
+fn infer_fn_generics(
+    generics: &mut GenericsArgs,
+    inputs: &[(Pat, Option<Ty>, ByteRange)],
+    turbofish: &[Ty],
+    args: &[Option<Ty>],
+    session: &Session<'_>,
+) {
+    if !turbofish.is_empty() {
+        generics.apply_types(turbofish);
+    }
+    for ((_, param_ty, _), arg_ty) in inputs.iter().zip(args) {
+        if let (Some(param_ty), Some(arg_ty)) = (param_ty, arg_ty) {
+            unify_type_params(param_ty, arg_ty.to_owned(), generics, session);
+        }
+    }
+}
+
+/// Matches the declared type of a parameter against the actual type, and resolves the
+/// type parameters which appear in the declared type.
+fn unify_type_params(param: &Ty, arg: Ty, generics: &mut GenericsArgs, session: &Session<'_>) {
+    debug!("[unify_type_params] param: {:?}, arg: {:?}", param, arg);
+    match param {
+        Ty::RefPtr(inner, _) | Ty::Ptr(inner, _) | Ty::Slice(inner) | Ty::Array(inner, _) => {
+            let arg = match arg {
+                Ty::RefPtr(a, _) | Ty::Ptr(a, _) | Ty::Slice(a) | Ty::Array(a, _) => *a,
+                a => a,
+            };
+            unify_type_params(inner, arg, generics, session);
+        }
+        Ty::Tuple(params) => {
+            if let Ty::Tuple(args) = arg {
+                for (p, a) in params.iter().zip(args) {
+                    if let (Some(p), Some(a)) = (p, a) {
+                        unify_type_params(p, a, generics, session);
+                    }
+                }
+            }
+        }
+        // `impl Fn() -> T`
+        Ty::TraitObject(bounds) => {
+            if let Some(output) = closure_output(bounds) {
+                unify_type_params(&output, arg, generics, session);
+            }
+        }
+        Ty::Match(m) => {
+            if let MatchType::TypeParameter(_) = m.mtype {
+                resolve_type_param(&m.matchstr, arg, generics, session);
+            }
+        }
+        Ty::PathSearch(paths) => {
+            if paths.path.is_single() && paths.path.segments[0].generics.is_empty() {
+                if generics.search_param_by_path(&paths.path).is_some() {
+                    resolve_type_param(&paths.path.segments[0].name, arg, generics, session);
+                    return;
+                }
+            }
+            let param_generics = match paths.path.segments.last() {
+                Some(seg) if !seg.generics.is_empty() => &seg.generics,
+                _ => return,
+            };
+            let arg_match = match arg.dereference() {
+                Ty::Match(m) => m,
+                Ty::PathSearch(ps) => match ps.resolve_as_match(session) {
+                    Some(m) => m,
+                    None => return,
+                },
+                _ => return,
+            };
+            if Some(arg_match.matchstr.as_str()) != paths.path.name() {
+                return;
+            }
+            let arg_generics = match arg_match.to_generics() {
+                Some(gen) => gen,
+                None => return,
+            };
+            for (a, p) in arg_generics.zip_type_args(param_generics) {
+                if let Some(resolved) = a.resolved() {
+                    unify_type_params(p, resolved.to_owned(), generics, session);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn resolve_type_param(name: &str, arg: Ty, generics: &mut GenericsArgs, session: &Session<'_>) {
+    let (pos, param) = match generics.search_param_by_name(name) {
+        Some(found) => found,
+        None => return,
+    };
+    if param.resolved().is_some() {
+        return;
+    }
+    // for `F: FnOnce(T) -> U`, the argument is a closure and `arg` is the type of its body
+    if let Some(output) = closure_output(&param.bounds) {
+        if let Some(arg) = get_closure_output_of_arg(arg, session) {
+            unify_type_params(&output, arg, generics, session);
+        }
+        return;
+    }
+    if let Some(param) = generics.0.get_mut(pos) {
+        param.resolve(arg);
+    }
+}
+
+fn closure_output(bounds: &TraitBounds) -> Option<Ty> {
+    bounds
+        .get_closure()?
+        .path
+        .segments
+        .iter()
+        .find_map(|seg| seg.output.clone())
+}
+
+// functions can be passed instead of closures
+fn get_closure_output_of_arg(arg: Ty, session: &Session<'_>) -> Option<Ty> {
+    match arg {
+        Ty::Match(ref m) if m.mtype.is_function() => get_return_type_of_function(m, m, session),
+        _ => Some(arg),
+    }
+}
+
 pub(crate) fn get_type_of_indexed_value(body: Ty, session: &Session<'_>) -> Option<Ty> {
     match body.dereference() {
         Ty::Match(m) => nameres::get_index_output(&m, session),
@@ -566,8 +951,34 @@ pub(crate) fn get_type_of_indexed_value(body: Ty, session: &Session<'_>) -> Opti
     }
 }
 
+/// Get the type of `fut.await`, looking through `Pin` and `Box` for boxed futures
+pub(crate) fn get_future_output(fut: Ty, session: &Session<'_>) -> Option<Ty> {
+    match fut.dereference() {
+        Ty::Future(output, _) => Some(*output),
+        Ty::Match(m) if m.matchstr == "Pin" || m.matchstr == "Box" => {
+            let inner = m.resolved_generics().next()?.to_owned();
+            get_future_output(inner, session)
+        }
+        Ty::PathSearch(p) => p
+            .resolve_as_match(session)
+            .and_then(|m| get_future_output(Ty::Match(m), session)),
+        _ => None,
+    }
+}
+
 pub(crate) fn get_type_of_typedef(m: &Match, session: &Session<'_>) -> Option<Match> {
-    debug!("get_type_of_typedef match is {:?}", m);
+    get_type_of_typedef_with_generics(m, &[], session)
+}
+
+/// Resolves a type alias, substituting its type parameters by `types`
+/// (e.g. `Guard` by `MutexGuard<T>` for `type LockResult<Guard> = Result<Guard, ..>`),
+/// and applies the generics of the aliased path to the result
+pub(crate) fn get_type_of_typedef_with_generics(
+    m: &Match,
+    types: &[Ty],
+    session: &Session<'_>,
+) -> Option<Match> {
+    debug!("get_type_of_typedef match is {:?}, types: {:?}", m, types);
     let msrc = session.load_source_file(&m.filepath);
     let blobstart = m.point - BytePos(5); // 5 == "type ".len()
     let blob = msrc.get_src_from_start(blobstart);
@@ -576,7 +987,13 @@ pub(crate) fn get_type_of_typedef(m: &Match, session: &Session<'_>) -> Option<Ma
         let blob = msrc[range.to_range()].to_owned();
         debug!("get_type_of_typedef blob string {}", blob);
         let scope = Scope::new(m.filepath.clone(), range.start);
-        ast::parse_type(blob, &scope).type_
+        let ast::TypeVisitor {
+            type_,
+            mut generics,
+            ..
+        } = ast::parse_type(blob, &scope);
+        generics.apply_types(types);
+        type_.map(|ty| ty.replace_by_resolved_generics_deep(&generics))
     })?;
     match type_.dereference() {
         Ty::Match(m) => Some(m),
@@ -612,6 +1029,14 @@ pub(crate) fn get_type_of_typedef(m: &Match, session: &Session<'_>) -> Option<Ma
             .into_iter()
             .filter(|m_| Some(m_.matchstr.as_ref()) == paths.path.name() && m_.point != m.point)
             .next()
+            .and_then(|mut m_| {
+                let types: Vec<_> = paths.path.generic_types().cloned().collect();
+                if m_.mtype == core::MatchType::Type {
+                    return get_type_of_typedef_with_generics(&m_, &types, session);
+                }
+                m_.resolve_generics(&types);
+                Some(m_)
+            })
         }
         _ => None,
     }
@@ -628,3 +1053,76 @@ fn get_type_of_static(m: Match) -> Option<Ty> {
     let res = ast::parse_static(contextstr, scope);
     res.ty
 }
+
+#[cfg(test)]
+mod test_binding_mode {
+    use super::{peel_refs_for_pattern, type_of_binding};
+    use crate::ast_types::Ty;
+    use rustc_ast::ast::{BindingMode, Mutability};
+
+    fn unit() -> Ty {
+        Ty::Tuple(Vec::new())
+    }
+    fn ref_of(ty: Ty, mutbl: Mutability) -> Ty {
+        Ty::RefPtr(Box::new(ty), mutbl)
+    }
+
+    #[test]
+    fn peels_refs_and_sets_default_mode() {
+        assert_eq!(peel_refs_for_pattern(unit(), None), (unit(), None));
+        assert_eq!(
+            peel_refs_for_pattern(ref_of(unit(), Mutability::Not), None),
+            (unit(), Some(Mutability::Not))
+        );
+        assert_eq!(
+            peel_refs_for_pattern(ref_of(unit(), Mutability::Mut), None),
+            (unit(), Some(Mutability::Mut))
+        );
+    }
+
+    #[test]
+    fn shared_ref_wins_over_mut_ref() {
+        let ty = ref_of(ref_of(unit(), Mutability::Mut), Mutability::Not);
+        assert_eq!(
+            peel_refs_for_pattern(ty, None),
+            (unit(), Some(Mutability::Not))
+        );
+        let ty = ref_of(unit(), Mutability::Mut);
+        assert_eq!(
+            peel_refs_for_pattern(ty, Some(Mutability::Not)),
+            (unit(), Some(Mutability::Not))
+        );
+    }
+
+    #[test]
+    fn binding_by_value_follows_default_mode() {
+        let by_value = BindingMode::ByValue(Mutability::Not);
+        assert_eq!(type_of_binding(unit(), by_value, None), unit());
+        assert_eq!(
+            type_of_binding(unit(), by_value, Some(Mutability::Not)),
+            ref_of(unit(), Mutability::Not)
+        );
+        assert_eq!(
+            type_of_binding(unit(), by_value, Some(Mutability::Mut)),
+            ref_of(unit(), Mutability::Mut)
+        );
+    }
+
+    #[test]
+    fn ref_bindings_borrow() {
+        let by_ref = BindingMode::ByRef(Mutability::Not);
+        let by_ref_mut = BindingMode::ByRef(Mutability::Mut);
+        assert_eq!(
+            type_of_binding(unit(), by_ref, None),
+            ref_of(unit(), Mutability::Not)
+        );
+        assert_eq!(
+            type_of_binding(unit(), by_ref_mut, None),
+            ref_of(unit(), Mutability::Mut)
+        );
+        assert_eq!(
+            type_of_binding(unit(), by_ref_mut, Some(Mutability::Mut)),
+            ref_of(unit(), Mutability::Mut)
+        );
+    }
+}
@@ -7,7 +7,9 @@ use std::{
 };
 
 use crate::core::SearchType::{self, ExactMatch, StartsWith};
-use crate::core::{BytePos, ByteRange, Location, LocationExt, RawSource, Session, SessionExt};
+use crate::core::{
+    BytePos, ByteRange, Location, LocationExt, RawSource, Session, SessionExt, Visibility,
+};
 
 #[cfg(unix)]
 pub const PATH_SEP: char = ':';
@@ -475,6 +477,9 @@ fn check_rust_sysroot() -> Option<path::PathBuf> {
 /// If that isn't available, checks `/usr/local/src/rust/src` and
 /// `/usr/src/rust/src` as default values.
 ///
+/// These paths belong to the installed toolchain, not to a project, so they
+/// are checked on disk rather than through a [`FileLoader`].
+///
 /// If the Rust standard library source code cannot be found, returns
 /// `Err(racer::RustSrcPathError::Missing)`.
 ///
@@ -501,6 +506,8 @@ fn check_rust_sysroot() -> Option<path::PathBuf> {
 ///     }
 /// }
 /// ```
+///
+/// [`FileLoader`]: trait.FileLoader.html
 pub fn get_rust_src_path() -> Result<path::PathBuf, RustSrcPathError> {
     use std::env;
 
@@ -791,6 +798,280 @@ fn test_trim_visibility() {
     assert_eq!(trim_visibility("pub (in super)  const fn"), "const fn");
 }
 
+/// Parses the visibility of the item or the field declared by `decl`,
+/// skipping its attributes
+pub(crate) fn visibility_of(decl: &str) -> Visibility {
+    let decl = &decl[skip_attributes(decl).0..];
+    let vis = match strip_visibility(decl) {
+        Some(end) => decl[..end.0].trim_end(),
+        // nothing follows, like `pub (u8, u8)` in a tuple struct's fields
+        None if decl.starts_with("pub") && !decl[3..].starts_with(is_ident_char) => decl,
+        None => return Visibility::Private,
+    };
+    if vis == "crate" {
+        return Visibility::Crate;
+    }
+    let restriction = vis["pub".len()..].trim_start();
+    if !restriction.starts_with('(') {
+        return Visibility::Public;
+    }
+    let restriction = restriction[1..].trim_end_matches(')').trim();
+    match restriction {
+        "crate" => Visibility::Crate,
+        "self" => Visibility::Private,
+        "super" => Visibility::Super,
+        _ if restriction.starts_with("in") && restriction[2..].starts_with(char::is_whitespace) => {
+            Visibility::InPath(restriction[2..].trim().to_owned())
+        }
+        // a parenthesized type after `pub`, like `struct S(pub (u8, u8))`
+        _ => Visibility::Public,
+    }
+}
+
+#[test]
+fn test_visibility_of() {
+    assert_eq!(visibility_of("fn f()"), Visibility::Private);
+    assert_eq!(visibility_of("#[inline] pub fn f()"), Visibility::Public);
+    assert_eq!(visibility_of("pub(crate) struct S"), Visibility::Crate);
+    assert_eq!(visibility_of("crate fn f()"), Visibility::Crate);
+    assert_eq!(visibility_of("pub(self) a: u8"), Visibility::Private);
+    assert_eq!(visibility_of("pub (super) a: u8"), Visibility::Super);
+    assert_eq!(
+        visibility_of("pub(in crate::a) fn f()"),
+        Visibility::InPath("crate::a".to_owned())
+    );
+    assert_eq!(visibility_of("pub (u8, u8)"), Visibility::Public);
+    assert_eq!(visibility_of("publish()"), Visibility::Private);
+}
+
+/// Returns the length of the outer attribute `#[..]` at the start of `s`, if any
+pub(crate) fn attribute_len(s: &str) -> Option<usize> {
+    if !s.starts_with("#[") {
+        return None;
+    }
+    find_closing_bracket(s, 1).map(|close| close + 1)
+}
+
+/// Returns the length of the inner attribute `#![..]` at the start of `s`, if any
+pub(crate) fn inner_attribute_len(s: &str) -> Option<usize> {
+    if !s.starts_with("#![") {
+        return None;
+    }
+    find_closing_bracket(s, 2).map(|close| close + 1)
+}
+
+/// Finds the `]` closing the `[` at `open`, skipping string and char literals
+fn find_closing_bracket(s: &str, open: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut level = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' => level += 1,
+            b']' => {
+                level -= 1;
+                if level == 0 {
+                    return Some(i);
+                }
+            }
+            b'"' => i = string_end(bytes, i)?,
+            b'\'' => i = char_literal_end(bytes, i).unwrap_or(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the position of the `"` closing the string literal opened at `open`
+fn string_end(bytes: &[u8], open: usize) -> Option<usize> {
+    // raw strings, like `r#"..."#`
+    let hashes = bytes[..open]
+        .iter()
+        .rev()
+        .take_while(|&&b| b == b'#')
+        .count();
+    let is_raw = open > hashes && bytes[open - hashes - 1] == b'r';
+    let mut i = open + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if !is_raw => i += 1,
+            b'"' if !is_raw => return Some(i),
+            b'"' if bytes[i + 1..].iter().take_while(|&&b| b == b'#').count() >= hashes => {
+                return Some(i + hashes);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the position of the `'` closing the char literal opened at `open`,
+/// or `None` for lifetimes
+fn char_literal_end(bytes: &[u8], open: usize) -> Option<usize> {
+    if bytes.get(open + 1) == Some(&b'\\') {
+        return bytes
+            .get(open + 3..)?
+            .iter()
+            .position(|&b| b == b'\'')
+            .map(|len| open + 3 + len);
+    }
+    let c_len = std::str::from_utf8(&bytes[open + 1..])
+        .ok()
+        .and_then(|rest| rest.chars().next())
+        .map_or(1, char::len_utf8);
+    Some(open + 1 + c_len).filter(|&close| bytes.get(close) == Some(&b'\''))
+}
+
+/// Skips outer attributes at the start of `s`, like `#[inline] #[tracing::instrument(skip(self))]`
+pub(crate) fn skip_attributes(s: &str) -> BytePos {
+    let mut pos = 0;
+    loop {
+        let rest = &s[pos..];
+        let trimmed = rest.trim_start();
+        match attribute_len(trimmed) {
+            Some(len) => pos += rest.len() - trimmed.len() + len,
+            None if pos == 0 => return BytePos::ZERO,
+            None => return BytePos(pos + rest.len() - trimmed.len()),
+        }
+    }
+}
+
+/// Returns the contents of inner attributes at the start of `src`, like `no_std` for
+/// `#![no_std]`. `src` must not contain comments.
+pub(crate) fn inner_attributes(src: &str) -> Vec<&str> {
+    inner_attribute_ranges(src)
+        .into_iter()
+        .map(|range| src[range.to_range()].trim())
+        .collect()
+}
+
+/// Like `inner_attributes`, but returns the ranges of the contents in `src`
+pub(crate) fn inner_attribute_ranges(src: &str) -> Vec<ByteRange> {
+    let mut out = Vec::new();
+    let mut pos = src.len() - src.trim_start().len();
+    while let Some(len) = inner_attribute_len(&src[pos..]) {
+        out.push(ByteRange::new(pos + 3, pos + len - 1));
+        pos += len;
+        pos += src[pos..].len() - src[pos..].trim_start().len();
+    }
+    out
+}
+
+/// Returns the contents of outer attributes written before `point`, nearest first.
+/// e.g. `["tokio::main", "inline(always)"]` for `#[inline(always)] #[tokio::main] fn`
+pub(crate) fn outer_attributes(src: &str, point: BytePos) -> Vec<&str> {
+    outer_attribute_ranges(src, point)
+        .into_iter()
+        .map(|range| src[range.to_range()].trim())
+        .collect()
+}
+
+/// Like `outer_attributes`, but returns the ranges of the contents in `src`
+pub(crate) fn outer_attribute_ranges(src: &str, point: BytePos) -> Vec<ByteRange> {
+    let mut out = Vec::new();
+    let mut before = src[..point.0].trim_end();
+    while before.ends_with(']') {
+        let open = match find_opening_bracket(before) {
+            Some(open) => open,
+            None => break,
+        };
+        // stop at `#![..]`, which belongs to the enclosing item
+        if open == 0 || before.as_bytes()[open - 1] != b'#' {
+            break;
+        }
+        out.push(ByteRange::new(open + 1, before.len() - 1));
+        before = before[..open - 1].trim_end();
+    }
+    out
+}
+
+/// Finds the `[` opening the `]` at the end of `s`, skipping string and char literals
+fn find_opening_bracket(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut level = 0;
+    let mut i = bytes.len();
+    while i > 0 {
+        i -= 1;
+        match bytes[i] {
+            b']' => level += 1,
+            b'[' => {
+                level -= 1;
+                if level == 0 {
+                    return Some(i);
+                }
+            }
+            b'"' => i = string_start(bytes, i)?,
+            // `'x'` or `'\x'`
+            b'\'' if i >= 2 && bytes[i - 2] == b'\'' => i -= 2,
+            b'\'' if i >= 3 && bytes[i - 3] == b'\'' && bytes[i - 2] == b'\\' => i -= 3,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the position of the `"` opening the string literal closed at `close`
+fn string_start(bytes: &[u8], close: usize) -> Option<usize> {
+    (0..close).rev().find(|&i| {
+        let backslashes = bytes[..i].iter().rev().take_while(|&&b| b == b'\\').count();
+        bytes[i] == b'"' && backslashes % 2 == 0
+    })
+}
+
+/// Returns the path of an attribute, e.g. `tokio::main` for `tokio::main(flavor = "..")`
+pub(crate) fn attribute_path(attr: &str) -> &str {
+    let end = attr
+        .find(|c: char| !(is_ident_char(c) || c == ':'))
+        .unwrap_or_else(|| attr.len());
+    &attr[..end]
+}
+
+#[test]
+fn test_skip_attributes() {
+    assert_eq!(skip_attributes("fn foo()"), BytePos(0));
+    assert_eq!(
+        skip_attributes("#[doc = include_str!(\"a\")]\n #[a(b[0])] pub fn"),
+        BytePos(39)
+    );
+}
+
+#[test]
+fn test_attributes_with_literals() {
+    let src = "#[doc = \"]\"]\n#[a(b = ']', c = r#\"[\"#)]\nfn foo() {}";
+    assert_eq!(attribute_len(src), Some(12));
+    assert_eq!(skip_attributes(src), BytePos(src.find("fn").unwrap()));
+    let point = BytePos(src.find("fn").unwrap());
+    assert_eq!(
+        outer_attributes(src, point),
+        vec!["a(b = ']', c = r#\"[\"#)", "doc = \"]\""]
+    );
+    assert_eq!(attribute_len("#[a('static)] fn"), Some(13));
+    assert_eq!(attribute_len("#[a('\\'', ']')] fn"), Some(15));
+}
+
+#[test]
+fn test_inner_attributes() {
+    let src = "\n#![no_std]\n#![cfg_attr(feature = \"]\", no_implicit_prelude)]\n#[inline]\nfn";
+    assert_eq!(
+        inner_attributes(src),
+        vec!["no_std", "cfg_attr(feature = \"]\", no_implicit_prelude)"]
+    );
+}
+
+#[test]
+fn test_outer_attributes() {
+    let src = "#![no_std]\n#[inline(always)]\n#[tracing::instrument(skip(self))]\nfn foo() {}";
+    let point = BytePos(src.find("fn").unwrap());
+    let attrs = outer_attributes(src, point);
+    assert_eq!(
+        attrs,
+        vec!["tracing::instrument(skip(self))", "inline(always)"]
+    );
+    assert_eq!(attribute_path(attrs[0]), "tracing::instrument");
+}
+
 /// Checks if the completion point is in a function declaration by looking
 /// to see if the second-to-last word is `fn`.
 pub fn in_fn_name(line_before_point: &str) -> bool {
@@ -1,3 +1,11 @@
 pub fn test() {
     println!("Hello from test function");
 }
+
+/// Defines a unit struct
+#[macro_export]
+macro_rules! fixture_struct {
+    ($name:ident) => {
+        pub struct $name;
+    };
+}
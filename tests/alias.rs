@@ -201,3 +201,46 @@ fn completes_for_alias_with_type_param() {
     ";
     assert_eq!(get_only_completion(src, None).matchstr, "method");
 }
+
+#[test]
+fn completes_fields_through_generic_type_alias() {
+    let src = r#"
+        struct Entry { entry_field: u32 }
+        struct Wrapper<K, V> { key: K, value: V }
+        type Map<V> = Wrapper<String, V>;
+        fn main() {
+            let map: Map<Entry> = unimplemented!();
+            map.value.entry_f~
+        }
+    "#;
+    assert_eq!(get_only_completion(src, None).matchstr, "entry_field");
+}
+
+#[test]
+fn completes_fields_through_nested_type_alias() {
+    let src = r#"
+        struct Entry { entry_field: u32 }
+        struct Wrapper<T> { inner: T }
+        type Wrapped<T> = Wrapper<T>;
+        type EntryWrapper = Wrapped<Entry>;
+        fn main() {
+            let w: EntryWrapper = unimplemented!();
+            w.inner.entry_f~
+        }
+    "#;
+    assert_eq!(get_only_completion(src, None).matchstr, "entry_field");
+}
+
+#[test]
+fn completes_methods_of_lock_result_guard() {
+    let src = r#"
+        use std::sync::Mutex;
+        struct Entry { entry_field: u32 }
+        fn main() {
+            let mutex = Mutex::new(Entry { entry_field: 0 });
+            let guard = mutex.lock().unwrap();
+            guard.entry_f~
+        }
+    "#;
+    assert_eq!(get_only_completion(src, None).matchstr, "entry_field");
+}
@@ -54,3 +54,161 @@ fn completion_in_async_block() {
     }"#;
     assert_eq!(get_definition(src, None).matchstr, "println!");
 }
+
+#[test]
+fn completes_output_of_async_block() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    async fn waiting_for() {
+        let fut = async { Foo { foo_field: 0 } };
+        fut.await.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_poll_on_async_block() {
+    let src = r#"
+    async fn waiting_for() {
+        let fut = async { 0u32 };
+        fut.po~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "poll");
+}
+
+#[test]
+fn completes_after_await_and_try() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    async fn get_foo() -> Result<Foo, ()> {
+        Ok(Foo { foo_field: 0 })
+    }
+    async fn waiting_for() -> Result<(), ()> {
+        let foo = get_foo().await?;
+        foo.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_output_of_impl_future() {
+    let src = r#"
+    use std::future::Future;
+    struct Foo { foo_field: u32 }
+    fn make_foo() -> impl Future<Output = Foo> {
+        async { Foo { foo_field: 0 } }
+    }
+    async fn waiting_for() {
+        make_foo().await.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_output_of_pinned_boxed_future() {
+    let src = r#"
+    use std::future::Future;
+    use std::pin::Pin;
+    struct Foo { foo_field: u32 }
+    struct Maker;
+    impl Maker {
+        fn make_foo(&self) -> Pin<Box<dyn Future<Output = Foo>>> {
+            Box::pin(async { Foo { foo_field: 0 } })
+        }
+    }
+    async fn waiting_for(maker: Maker) {
+        let foo = maker.make_foo().await;
+        foo.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_methods_of_async_trait_future() {
+    let src = r#"
+    use async_trait::async_trait;
+    struct Foo { foo_field: u32 }
+    #[async_trait]
+    trait Getter {
+        async fn get_foo(&self) -> Foo;
+    }
+    struct G;
+    #[async_trait]
+    impl Getter for G {
+        async fn get_foo(&self) -> Foo {
+            Foo { foo_field: 0 }
+        }
+    }
+    async fn waiting_for(g: G) {
+        let fut = g.get_foo();
+        fut.as_mu~
+    }
+"#;
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "as_mut"), "got: {:?}", got);
+}
+
+#[test]
+fn completes_output_of_async_trait_method() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    #[async_trait::async_trait]
+    trait Getter {
+        async fn get_foo(&self) -> Foo;
+    }
+    struct G;
+    #[async_trait::async_trait]
+    impl Getter for G {
+        async fn get_foo(&self) -> Foo {
+            Foo { foo_field: 0 }
+        }
+    }
+    async fn waiting_for(g: G) {
+        g.get_foo().await.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_return_value_of_runtime_main() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    #[tokio::main(flavor = "current_thread")]
+    async fn run() -> Foo {
+        Foo { foo_field: 0 }
+    }
+    fn main() {
+        run().foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn plain_test_attribute_keeps_async_fn_future() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    #[test]
+    async fn run() -> Foo {
+        Foo { foo_field: 0 }
+    }
+    async fn waiting() {
+        run().await.foo_f~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
@@ -0,0 +1,186 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_methods_through_nested_derefs() {
+    let src = r"
+    use std::ops::Deref;
+    struct Inner;
+    impl Inner {
+        fn inner_method(&self) {}
+    }
+    struct Middle(Inner);
+    impl Deref for Middle {
+        type Target = Inner;
+        fn deref(&self) -> &Inner {
+            &self.0
+        }
+    }
+    struct Outer(Middle);
+    impl Deref for Outer {
+        type Target = Middle;
+        fn deref(&self) -> &Middle {
+            &self.0
+        }
+    }
+    fn main() {
+        let o = Outer(Middle(Inner));
+        o.inner_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "inner_method");
+}
+
+#[test]
+fn outer_methods_shadow_deref_target_methods() {
+    let src = r"
+    use std::ops::Deref;
+    struct Inner;
+    impl Inner {
+        fn same_name(&self) -> u16 { 0 }
+    }
+    struct Outer(Inner);
+    impl Outer {
+        fn same_name(&self) -> u8 { 0 }
+    }
+    impl Deref for Outer {
+        type Target = Inner;
+        fn deref(&self) -> &Inner {
+            &self.0
+        }
+    }
+    fn main() {
+        let o = Outer(Inner);
+        o.same_n~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert!(got.contextstr.contains("u8"), "{:?}", got);
+}
+
+#[test]
+fn completes_generic_deref_target() {
+    let src = r"
+    use std::ops::Deref;
+    struct Foo {
+        foo_field: u32,
+    }
+    struct MyBox<T>(T);
+    impl<T> Deref for MyBox<T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            &self.0
+        }
+    }
+    fn main() {
+        let b: MyBox<Foo> = MyBox(Foo { foo_field: 0 });
+        b.foo_fi~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_field_through_box() {
+    let src = r"
+    struct Foo {
+        foo_field: u32,
+    }
+    fn main() {
+        let b: Box<Foo> = Box::new(Foo { foo_field: 0 });
+        b.foo_fi~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_str_methods_through_rc_string() {
+    let src = r"
+    use std::rc::Rc;
+    fn main() {
+        let s: Rc<String> = Rc::new(String::new());
+        s.to_lowerc~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "to_lowercase");
+}
+
+#[test]
+fn completes_slice_methods_through_vec() {
+    let src = r"
+    fn main() {
+        let v: Vec<u32> = Vec::new();
+        v.windo~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "windows");
+}
+
+#[test]
+fn completes_field_through_mutex_guard() {
+    let src = r"
+    use std::sync::{Mutex, MutexGuard};
+    struct Foo {
+        foo_field: u32,
+    }
+    fn main() {
+        let m = Mutex::new(Foo { foo_field: 0 });
+        let guard: MutexGuard<Foo> = m.lock().unwrap();
+        guard.foo_fi~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_str_methods_through_cow() {
+    let src = r#"
+    use std::borrow::Cow;
+    fn main() {
+        let c: Cow<str> = Cow::Borrowed("cow");
+        c.to_lowerc~
+    }
+"#;
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "to_lowercase");
+}
+
+#[test]
+fn completes_field_through_ref() {
+    let src = r"
+    use std::cell::{Ref, RefCell};
+    struct Foo {
+        foo_field: u32,
+    }
+    fn main() {
+        let cell = RefCell::new(Foo { foo_field: 0 });
+        let r: Ref<Foo> = cell.borrow();
+        r.foo_fi~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
+
+#[test]
+fn completes_field_through_ref_mut() {
+    let src = r"
+    use std::cell::{RefCell, RefMut};
+    struct Foo {
+        foo_field: u32,
+    }
+    fn main() {
+        let cell = RefCell::new(Foo { foo_field: 0 });
+        let r: RefMut<Foo> = cell.borrow_mut();
+        r.foo_fi~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_field");
+}
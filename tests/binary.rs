@@ -133,3 +133,17 @@ fn completes_if_operator_trait_is_not_explicit() {
     let got = get_one_completion(src, None);
     assert_eq!(got.matchstr, "bar");
 }
+
+#[test]
+fn completes_binary_operators_for_reference_operands() {
+    let src = r"
+    fn main() {
+        let n = 2u32;
+        let r = &n;
+        let m = r * 3;
+        m.pow~
+    }
+";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|ma| ma.matchstr == "pow"), "{:?}", got);
+}
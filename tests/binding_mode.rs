@@ -0,0 +1,192 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_binding_of_option_ref_in_match() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        match &opt {
+            Some(x) => x.foo_m~
+            None => {}
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_binding_of_option_ref_in_if_let() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        let r = &opt;
+        if let Some(x) = r {
+            x.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_binding_of_mut_ref_in_while_let() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let mut opt = Some(Foo);
+        while let Some(x) = &mut opt {
+            x.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_tuple_binding_of_ref_in_for_loop() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let v = vec![(Foo, 0)];
+        for (x, _) in &v {
+            x.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_binding_after_ref_pattern_resets_mode() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let pair = &(Foo, Foo);
+        let &(ref a, _) = pair;
+        a.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+// Dereferencing only gives `Foo` back if the binding is a reference, as `Foo` isn't `Deref`
+#[test]
+fn binds_reference_in_match_on_ref() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        match &opt {
+            Some(x) => {
+                let y = *x;
+                y.foo_m~
+            }
+            None => {}
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn binds_reference_in_for_loop_over_ref() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let v = vec![Foo];
+        for x in &v {
+            let y = *x;
+            y.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn binds_mut_references_in_let_of_mut_ref() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let mut pair = (Foo, 0);
+        let (a, _b) = &mut pair;
+        let y = *a;
+        y.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn binds_value_in_match_by_value() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        match opt {
+            Some(x) => {
+                x.foo_m~
+            }
+            None => {}
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        match opt {
+            Some(x) => {
+                let y = *x;
+                y.foo_m~
+            }
+            None => {}
+        }
+    }
+";
+    let got = get_all_completions(src, None);
+    assert!(got.is_empty(), "{:?}", got);
+}
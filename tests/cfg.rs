@@ -0,0 +1,149 @@
+use racer::{complete_from_file, find_definition, CfgFilter};
+use racer_testutils::*;
+
+#[test]
+fn finds_definition_of_enabled_platform_variant() {
+    let src = "
+    #[cfg(all(unix, windows))]
+    fn platform() -> u8 { 0 }
+    #[cfg(any(unix, windows))]
+    fn platform() -> u16 { 0 }
+    fn main() {
+        platf~orm();
+    }
+    ";
+    let got = get_definition(src, None);
+    assert_eq!(got.contextstr, "fn platform() -> u16");
+}
+
+#[test]
+fn hides_items_disabled_by_cfg() {
+    let src = "
+    #[cfg(not(any(unix, windows)))]
+    fn cfg_other() {}
+    #[cfg(any(unix, windows))]
+    fn cfg_desktop() {}
+    fn main() {
+        cfg_~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "cfg_desktop");
+}
+
+#[test]
+fn keeps_items_with_undecidable_cfg() {
+    let src = "
+    #[cfg(test)]
+    fn only_in_test() {}
+    fn main() {
+        only_in~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "only_in_test");
+}
+
+#[test]
+fn hides_methods_disabled_by_cfg() {
+    let src = "
+    struct Foo;
+    impl Foo {
+        #[cfg(all(unix, windows))]
+        fn cfg_never(&self) {}
+        fn cfg_always(&self) {}
+    }
+    fn main() {
+        Foo.cfg_~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "cfg_always");
+}
+
+#[test]
+fn dims_items_disabled_by_cfg() {
+    let src = "
+    #[cfg(all(unix, windows))]
+    fn cfg_never() {}
+    fn cfg_always() {}
+    fn main() {
+        cfg_~
+    }
+    ";
+    let dir = TmpDir::new();
+    let (completion_point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("src.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_ref()));
+    session.set_cfg_filter(CfgFilter::Dim);
+    let got: Vec<_> = complete_from_file(&path, completion_point, &session).collect();
+    assert_eq!(got.len(), 2, "{:?}", got);
+    for m in got {
+        let disabled = session.is_disabled_by_cfg(&m);
+        assert_eq!(disabled, m.matchstr == "cfg_never", "{:?}", m);
+    }
+}
+
+#[test]
+fn forgets_disabled_items_of_previous_query() {
+    let src = "
+    #[cfg(windows)]
+    fn cfg_windows() {}
+    fn main() {
+        cfg_win~
+    }
+    ";
+    let dir = TmpDir::new();
+    let (completion_point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("src.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_ref()));
+    session.set_cfg_filter(CfgFilter::Dim);
+    let is_disabled = |target: &str| {
+        session.set_cfg_target(target);
+        let got: Vec<_> = complete_from_file(&path, completion_point, &session).collect();
+        assert_eq!(got.len(), 1, "{:?}", got);
+        session.is_disabled_by_cfg(&got[0])
+    };
+    assert!(is_disabled("x86_64-unknown-linux-gnu"));
+    assert!(!is_disabled("x86_64-pc-windows-msvc"));
+}
+
+#[test]
+fn evaluates_cfg_for_specified_target() {
+    let src = "
+    #[cfg(unix)]
+    fn on_unix() -> u8 { 0 }
+    #[cfg(windows)]
+    fn on_unix() -> u16 { 0 }
+    fn main() {
+        on_un~ix();
+    }
+    ";
+    let dir = TmpDir::new();
+    let (point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("src.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_ref()));
+    session.set_cfg_target("x86_64-pc-windows-msvc");
+    let got = find_definition(&path, point, &session).unwrap();
+    assert_eq!(got.contextstr, "fn on_unix() -> u16");
+}
+
+#[test]
+fn hides_items_of_disabled_features() {
+    let src = "
+    #[cfg(feature = \"nope\")]
+    fn feature_gated() {}
+    fn feature_free() {}
+    fn main() {
+        feature_~
+    }
+    ";
+    with_test_project(|dir| {
+        let srcdir = dir.nested_dir("src");
+        let got = get_only_completion(src, Some(srcdir));
+        assert_eq!(got.matchstr, "feature_free");
+    })
+}
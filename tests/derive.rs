@@ -0,0 +1,136 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_method_of_derived_trait() {
+    let src = "
+    #[derive(Clone, Debug)]
+    struct Foo { foo_field: u32 }
+    fn main() {
+        let x = Foo { foo_field: 0 };
+        x.clo~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "clone"), "got: {:?}", got);
+}
+
+#[test]
+fn completes_assoc_fn_of_derived_trait() {
+    let src = "
+    #[derive(Default)]
+    struct Foo { foo_field: u32 }
+    fn main() {
+        Foo::defa~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "default");
+}
+
+#[test]
+fn completes_methods_of_derives_in_several_attributes() {
+    let src = "
+    #[derive(Debug)]
+    #[cfg_attr(test, derive(PartialEq))]
+    enum Kind { A, B }
+    fn main() {
+        let k = Kind::A;
+        k.e~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "eq"), "got: {:?}", got);
+}
+
+#[test]
+fn finds_definition_of_derived_method() {
+    let src = "
+    #[derive(Clone)]
+    struct Foo;
+    fn main() {
+        let x = Foo;
+        x.clo~ne();
+    }
+    ";
+    let got = get_definition(src, None);
+    assert_eq!(got.matchstr, "clone");
+    assert!(got.filepath.ends_with("clone.rs"), "got: {:?}", got);
+}
+
+// a serde-like facade, which re-exports the derive macro under the name of its trait
+const SERDE_FACADE: &str = "
+    mod ser {
+        pub trait Serialize {
+            fn serialize<S>(&self, serializer: S) {}
+        }
+    }
+    pub use self::ser::Serialize;
+    pub use serde_derive::Serialize;
+    ";
+
+#[test]
+fn completes_method_of_derive_reexported_with_its_trait() {
+    let src = "
+    use crate::serde::Serialize;
+    #[derive(Serialize)]
+    struct Foo;
+    fn main() {
+        let f = Foo;
+        f.seria~
+    }
+    ";
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "pub mod serde;\nmod user;\n");
+    let _serde = dir.write_file("serde.rs", SERDE_FACADE);
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "serialize");
+}
+
+#[test]
+fn completes_method_of_derive_by_path() {
+    let src = "
+    use crate::serde;
+    #[derive(Debug, serde::Serialize)]
+    enum Kind { A, B }
+    fn main() {
+        let k = Kind::A;
+        k.seria~
+    }
+    ";
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "pub mod serde;\nmod user;\n");
+    let _serde = dir.write_file("serde.rs", SERDE_FACADE);
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "serialize");
+}
+
+#[test]
+fn completes_method_of_derived_trait_in_no_std_crate() {
+    let src = "
+    #![no_std]
+    #[derive(Clone)]
+    struct Foo { foo_field: u32 }
+    fn main() {
+        let x = Foo { foo_field: 0 };
+        x.clo~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "clone"), "got: {:?}", got);
+}
+
+#[test]
+fn skips_derives_of_disabled_cfg_attr() {
+    let src = "
+    #[cfg_attr(all(unix, windows), derive(PartialEq))]
+    enum Kind { A, B }
+    fn main() {
+        let k = Kind::A;
+        k.e~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().all(|m| m.matchstr != "eq"), "got: {:?}", got);
+}
@@ -0,0 +1,138 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_first_element_of_slice_pattern() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let arr = [Foo, Foo, Foo];
+        let [first, ..] = arr;
+        first.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_last_element_of_slice_pattern() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let arr = [Foo, Foo, Foo];
+        let [.., last] = arr;
+        last.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_rest_binding_of_slice_pattern() {
+    let src = r"
+    struct Foo;
+    fn main() {
+        let arr = [Foo, Foo, Foo];
+        let [_, rest @ ..] = arr;
+        rest.split_first_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "split_first_mut");
+}
+
+#[test]
+fn completes_binding_in_ref_pattern() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    struct Bar;
+    fn main() {
+        let &(a, _) = &(Foo, Bar);
+        a.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_binding_in_or_pattern() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    enum E {
+        A(Foo),
+        B(Foo),
+    }
+    fn main() {
+        let e = E::A(Foo);
+        match e {
+            E::A(x) | E::B(x) => x.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_at_binding() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let opt = Some(Foo);
+        match opt {
+            Some(inner @ Foo) => inner.foo_m~
+            None => {}
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_binding_in_box_pattern() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let b: Box<Foo> = Box::new(Foo);
+        match b {
+            box f => f.foo_m~
+        }
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn mismatched_tuple_pattern_does_not_panic() {
+    let src = r"
+    struct Foo;
+    fn main() {
+        let (a, b) = Foo;
+        a.~
+    }
+";
+    let got = get_all_completions(src, None);
+    assert!(got.is_empty(), "{:?}", got);
+}
@@ -0,0 +1,75 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_field_of_struct_with_const_param() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    struct Buf<const SIZE: usize, T> { data: [u8; SIZE], inner: T }
+    fn main() {
+        let buf: Buf<32, Foo> = unimplemented!();
+        buf.inner.foo_f~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
+
+#[test]
+fn completes_method_of_impl_with_const_param() {
+    let src = "
+    struct Buf<const SIZE: usize> { data: [u8; SIZE] }
+    impl<const N: usize> Buf<N> {
+        fn capacity(&self) -> usize { N }
+    }
+    fn main() {
+        let buf: Buf<32> = unimplemented!();
+        buf.capa~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "capacity");
+}
+
+#[test]
+fn completes_field_of_struct_with_lifetime_and_const_params() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    struct Wrapper<'a, const N: usize, T> { inner: &'a T, len: [u8; N] }
+    fn func(w: Wrapper<'_, 4, Foo>) {
+        w.inner.foo_f~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
+
+#[test]
+fn completes_const_param_name() {
+    let src = "
+    fn func<const LENGTH: usize>() -> usize {
+        LENG~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "LENGTH");
+}
+
+#[test]
+fn completes_generic_associated_type_of_lending_iterator() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    trait LendingIterator {
+        type Item<'a> where Self: 'a;
+        fn next(&mut self) -> Option<Self::Item<'_>>;
+    }
+    struct Lender { foo: Foo }
+    impl LendingIterator for Lender {
+        type Item<'a> where Self: 'a = &'a Foo;
+        fn next(&mut self) -> Option<Self::Item<'_>> { Some(&self.foo) }
+    }
+    fn main() {
+        let mut l = Lender { foo: Foo { foo_field: 0 } };
+        if let Some(x) = l.next() {
+            x.foo_f~
+        }
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
@@ -41,3 +41,62 @@ fn enum_variant_with_tuple() {
     let got = get_only_completion(src, None);
     assert_eq!(got.matchstr, "capacity");
 }
+
+#[test]
+fn let_else_binding() {
+    let src = "
+    fn main() {
+        let s = Some(String::new());
+        let Some(s) = s else { return; };
+        s.capa~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "capacity");
+}
+
+#[test]
+fn let_else_binding_with_tuple() {
+    let src = "
+    fn main() {
+        let s = Some((String::new(), 0u32));
+        let Some((s, _)) = s else {
+            return;
+        };
+        s.capa~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "capacity");
+}
+
+#[test]
+fn let_with_if_else_is_not_let_else() {
+    let src = "
+    fn main() {
+        let s = if true { String::new() } else { String::new() };
+        s.capa~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "capacity");
+}
+
+#[test]
+fn let_else_binding_keeps_statement_as_context() {
+    let src = "
+    fn main() {
+        let s = Some(String::new());
+        let Some(s) = s else { return; };
+        s~.capacity();
+    }
+";
+    let got = get_definition(src, None);
+    assert_eq!(got.matchstr, "s");
+    assert!(
+        got.contextstr
+            .starts_with("let Some(s) = s else { return; }"),
+        "{:?}",
+        got.contextstr
+    );
+}
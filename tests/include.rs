@@ -0,0 +1,77 @@
+use racer_testutils::*;
+use std::fs;
+
+#[test]
+fn completes_items_of_included_file() {
+    let src = r#"
+    include!("generated.rs");
+    fn main() {
+        Genera~
+    }
+    "#;
+    let dir = TmpDir::new();
+    let generated = dir.write_file("generated.rs", "pub struct Generated;\n");
+    let got = get_only_completion(src, Some(dir));
+    assert_eq!(got.matchstr, "Generated");
+    assert_eq!(got.filepath, generated.path());
+}
+
+#[test]
+fn completes_items_of_file_included_in_module() {
+    let src = r#"
+    mod bindings {
+        include!(concat!("gen", "/bindings.rs"));
+    }
+    fn main() {
+        bindings::Bind~
+    }
+    "#;
+    let dir = TmpDir::new();
+    let gen = dir.nested_dir("gen");
+    let _bindings = gen.write_file(
+        "bindings.rs",
+        "pub fn bindgen_fn() {}\npub struct Binding;\n",
+    );
+    let got = get_only_completion(src, Some(dir));
+    assert_eq!(got.matchstr, "Binding");
+}
+
+#[test]
+fn stops_at_mutually_included_files() {
+    let src = r#"
+    include!("a.rs");
+    fn main() {
+        Incl~
+    }
+    "#;
+    let dir = TmpDir::new();
+    let _a = dir.write_file("a.rs", "include!(\"b.rs\");\npub struct InclA;\n");
+    let _b = dir.write_file("b.rs", "include!(\"a.rs\");\npub struct InclB;\n");
+    let mut got: Vec<_> = get_all_completions(src, Some(dir))
+        .into_iter()
+        .map(|m| m.matchstr)
+        .collect();
+    got.sort();
+    assert_eq!(got, vec!["InclA", "InclB"]);
+}
+
+#[test]
+fn finds_definition_in_out_dir() {
+    let src = r#"
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+    fn main() {
+        let x = Out~DirStruct;
+    }
+    "#;
+    with_test_project(|dir| {
+        let out = dir
+            .path()
+            .join("target/debug/build/test_project-0123456789abcdef/out");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(out.join("bindings.rs"), "pub struct OutDirStruct;\n").unwrap();
+        let srcdir = dir.nested_dir("src");
+        let got = get_definition(src, Some(srcdir));
+        assert_eq!(got.matchstr, "OutDirStruct");
+        assert_eq!(got.filepath, out.join("bindings.rs"));
+    })
+}
@@ -0,0 +1,451 @@
+use racer::complete_from_file;
+use racer_testutils::*;
+
+#[test]
+fn completes_methods_for_format_macro() {
+    let src = r#"
+    fn main() {
+        let s = format!("{}", 1);
+        s.push_st~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "push_str");
+}
+
+#[test]
+fn completes_methods_for_include_str_macro() {
+    let src = r#"
+    fn main() {
+        let s = include_str!("foo.txt");
+        s.to_lowerc~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "to_lowercase");
+}
+
+#[test]
+fn completes_methods_for_env_macro() {
+    let src = r#"
+    fn main() {
+        env!("HOME").to_lowerc~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "to_lowercase");
+}
+
+#[test]
+fn completes_methods_for_line_macro() {
+    let src = r#"
+    fn main() {
+        line!().pow~
+    }
+    "#;
+
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|ma| ma.matchstr == "pow"));
+}
+
+#[test]
+fn completes_methods_for_vec_macro() {
+    let src = r#"
+    fn main() {
+        let v = vec![1, 2, 3];
+        v.pus~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "push");
+}
+
+#[test]
+fn completes_string_methods_for_format_macro() {
+    let src = r#"
+    fn main() {
+        format!("{}", 1).into_byt~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "into_bytes");
+}
+
+#[test]
+fn completes_vec_methods_for_vec_macro_of_strings() {
+    let src = r#"
+    fn main() {
+        let v = vec![String::new(), String::new()];
+        v.capaci~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "capacity");
+}
+
+#[test]
+fn completes_methods_for_matches_macro() {
+    let src = r#"
+    fn main() {
+        let b = matches!(Some(1), Some(_));
+        b.then_so~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "then_some");
+}
+
+#[test]
+fn local_macro_shadows_std_macro_type() {
+    let src = r#"
+    macro_rules! vec {
+        () => { 0 };
+    }
+    fn main() {
+        let v = vec![];
+        v.pus~
+    }
+    "#;
+
+    let got = get_all_completions(src, None);
+    assert!(got.is_empty(), "{:?}", got);
+}
+
+#[test]
+fn diverging_macro_has_no_completions() {
+    let src = r#"
+    fn main() {
+        let n = todo!();
+        n.~
+    }
+    "#;
+
+    let got = get_all_completions(src, None);
+    assert!(got.is_empty(), "{:?}", got);
+}
+
+#[test]
+fn completes_user_registered_macro_type() {
+    let src = r#"
+    struct Value;
+    impl Value {
+        fn as_object(&self) {}
+    }
+    macro_rules! json {
+        ($($t:tt)*) => { Value };
+    }
+    fn main() {
+        let v = json!({});
+        v.as_obj~
+    }
+    "#;
+
+    let dir = TmpDir::new();
+    let (completion_point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("src.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_ref()));
+    session.add_macro_type("json!", "Value");
+    let got: Vec<_> = complete_from_file(&path, completion_point, &session).collect();
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "as_object");
+}
+
+#[test]
+fn registered_macro_type_overrides_std_table() {
+    let src = r#"
+    fn main() {
+        let v = format!("{}", 1);
+        v.dedup_by_k~
+    }
+    "#;
+
+    let dir = TmpDir::new();
+    let (completion_point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("src.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_ref()));
+    session.add_macro_type("format", "std::vec::Vec<u8>");
+    let got: Vec<_> = complete_from_file(&path, completion_point, &session).collect();
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "dedup_by_key");
+}
+
+#[test]
+fn completes_struct_generated_by_local_macro() {
+    let src = r#"
+    macro_rules! newtype {
+        ($name:ident, $inner:ty) => {
+            pub struct $name(pub $inner);
+        };
+    }
+    newtype!(Meters, f64);
+    fn main() {
+        let m: Mete~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "Meters");
+}
+
+#[test]
+fn completes_item_named_only_in_macro_body() {
+    let src = r#"
+    macro_rules! make_config {
+        () => {
+            struct GeneratedConfig {
+                verbose: bool,
+            }
+        };
+    }
+    make_config!();
+    fn main() {
+        Generat~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "GeneratedConfig");
+}
+
+#[test]
+fn finds_definition_of_generated_item_at_invocation() {
+    let src = r#"
+    macro_rules! newtype {
+        ($name:ident, $inner:ty) => {
+            pub struct $name(pub $inner);
+        };
+    }
+    newtype!(Meters, f64);
+    fn main() {
+        let m: Meter~s = Meters(1.0);
+    }
+    "#;
+
+    let got = get_definition(src, None);
+    assert_eq!(got.matchstr, "Meters");
+    assert_eq!(got.point.0, src.find("newtype!(Meters").unwrap());
+}
+
+#[test]
+fn completes_items_of_nested_local_macros() {
+    let src = r#"
+    macro_rules! unit_struct {
+        ($name:ident) => {
+            struct $name;
+        };
+    }
+    macro_rules! unit_structs {
+        ($($name:ident),*) => {
+            $(unit_struct!($name);)*
+        };
+    }
+    unit_structs!(Alpha, Beta);
+    fn main() {
+        Bet~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "Beta");
+}
+
+#[test]
+fn stops_expanding_recursive_macro() {
+    let src = r#"
+    macro_rules! forever {
+        () => {
+            forever!();
+        };
+    }
+    forever!();
+    struct RealStruct;
+    fn main() {
+        RealStr~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "RealStruct");
+}
+
+#[test]
+fn stops_expanding_self_doubling_macro() {
+    let src = r#"
+    macro_rules! m {
+        () => {
+            m!();
+            m!();
+        };
+    }
+    m!();
+    struct RealStruct;
+    fn main() {
+        RealStr~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "RealStruct");
+}
+
+#[test]
+fn replacing_file_evicts_its_expansions() {
+    let old_src = "
+    macro_rules! make { () => { struct OldName; } }
+    make!();
+    fn main() {
+        OldNa~
+    }
+    ";
+    let dir = TmpDir::new();
+    let path = dir.write_file("src.rs", "");
+    let cache = racer::FileCache::default();
+    let complete = |src: &str| {
+        let (point, clean_src) = get_pos_and_source(src);
+        let session = racer::Session::new(&cache, Some(path.as_ref()));
+        session.cache_file_contents(path.path(), clean_src);
+        complete_from_file(&path, point, &session)
+            .map(|m| m.matchstr)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(complete(old_src), vec!["OldName"]);
+    // the invocation is unchanged, but expands to something else now
+    assert_eq!(complete(&old_src.replace("Old", "New")), vec!["NewName"]);
+}
+
+#[test]
+fn editing_macro_in_other_file_updates_expansion() {
+    let src = "
+    make!();
+    fn main() {
+        OldNa~
+    }
+    ";
+    let (point, clean_src) = get_pos_and_source(src);
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "#[macro_use]\nmod macros;\nmod user;\n");
+    let macros = dir.write_file("macros.rs", "");
+    let user = dir.write_file("user.rs", &clean_src);
+    let cache = racer::FileCache::default();
+    let complete = |name: &str| {
+        let session = racer::Session::new(&cache, Some(user.as_ref()));
+        let def = format!("macro_rules! make {{ () => {{ struct {}; }} }}\n", name);
+        session.cache_file_contents(macros.path(), def);
+        session.cache_file_contents(user.path(), clean_src.replace("OldNa", &name[..5]));
+        complete_from_file(&user, point, &session)
+            .map(|m| m.matchstr)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(complete("OldName"), vec!["OldName"]);
+    assert_eq!(complete("NewName"), vec!["NewName"]);
+}
+
+#[test]
+fn completes_macro_from_macro_use_module() {
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "#[macro_use]\nmod macros;\nmod user;\n");
+    let _macros = dir.write_file("macros.rs", "macro_rules! my_macro { () => {} }\n");
+    let src = "
+    fn main() {
+        my_mac~
+    }
+    ";
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert_eq!(got.len(), 1, "got: {:?}", got);
+    assert_eq!(got[0].matchstr, "my_macro!");
+}
+
+#[test]
+fn doesnt_complete_macro_declared_after_module() {
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "mod user;\n#[macro_use]\nmod macros;\n");
+    let _macros = dir.write_file("macros.rs", "macro_rules! my_macro { () => {} }\n");
+    let src = "
+    fn main() {
+        my_mac~
+    }
+    ";
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert!(got.is_empty(), "got: {:?}", got);
+}
+
+#[test]
+fn finds_definition_of_exported_macro_imported_by_use() {
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "mod macros;\nmod user;\n");
+    let macros = dir.write_file(
+        "macros.rs",
+        "#[macro_export]\nmacro_rules! exported_macro { () => {} }\n",
+    );
+    let src = "
+    use crate::exported_macro;
+    fn main() {
+        exported_ma~cro!();
+    }
+    ";
+    let got = find_definition_with_name(src, Some(dir), "user.rs").unwrap();
+    assert_eq!(got.matchstr, "exported_macro!");
+    assert_eq!(got.filepath, macros.path());
+}
+
+#[test]
+fn completes_exported_macro_by_crate_path() {
+    let dir = TmpDir::new();
+    let _lib = dir.write_file("lib.rs", "mod macros;\nmod user;\n");
+    let _macros = dir.write_file(
+        "macros.rs",
+        "#[macro_export]\nmacro_rules! exported_macro { () => {} }\n",
+    );
+    let src = "
+    fn main() {
+        crate::exported_ma~
+    }
+    ";
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert!(
+        got.iter().any(|m| m.matchstr == "exported_macro!"),
+        "got: {:?}",
+        got
+    );
+}
+
+#[test]
+fn completes_macro_from_macro_use_extern_crate() {
+    let src = "
+    #[macro_use]
+    extern crate fixtures;
+    fn main() {
+        fixture_str~
+    }
+    ";
+    with_test_project(|dir| {
+        let srcdir = dir.nested_dir("src");
+        let got = get_only_completion(src, Some(srcdir));
+        assert_eq!(got.matchstr, "fixture_struct!");
+        assert_eq!(got.docs, "Defines a unit struct");
+    })
+}
+
+#[test]
+fn finds_definition_of_macro_by_crate_path() {
+    let src = "
+    fn main() {
+        fixtures::fixture_stru~ct!(Foo);
+    }
+    ";
+    with_test_project(|dir| {
+        let srcdir = dir.nested_dir("src");
+        let got = get_definition(src, Some(srcdir));
+        assert_eq!(got.matchstr, "fixture_struct!");
+        assert!(got.filepath.ends_with("foo.rs"), "got: {:?}", got);
+    })
+}
@@ -0,0 +1,98 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_method_return_type_resolved_by_arg() {
+    let src = r"
+    struct Wrapper<T> {
+        inner: T,
+    }
+    struct Bar;
+    impl Bar {
+        fn bar_method(&self) {}
+    }
+    struct Foo;
+    impl Foo {
+        fn wrap<T>(&self, t: T) -> Wrapper<T> {
+            Wrapper { inner: t }
+        }
+    }
+    fn main() {
+        let foo = Foo;
+        foo.wrap(Bar).inner.bar_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "bar_method");
+}
+
+#[test]
+fn completes_function_return_type_resolved_by_turbofish() {
+    let src = r"
+    struct Bar;
+    impl Bar {
+        fn bar_method(&self) {}
+    }
+    fn make<T: Default>() -> T {
+        T::default()
+    }
+    fn main() {
+        make::<Bar>().bar_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "bar_method");
+}
+
+#[test]
+fn completes_method_return_type_resolved_by_ref_arg() {
+    let src = r"
+    struct Bar;
+    impl Bar {
+        fn bar_method(&self) {}
+    }
+    fn identity<T>(t: &T) -> &T {
+        t
+    }
+    fn main() {
+        let bar = Bar;
+        identity(&bar).bar_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "bar_method");
+}
+
+#[test]
+fn completes_option_map_with_closure() {
+    let src = r"
+    struct Bar;
+    impl Bar {
+        fn bar_method(&self) {}
+    }
+    fn main() {
+        let opt: Option<u32> = None;
+        opt.map(|_| Bar).unwrap().bar_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "bar_method");
+}
+
+#[test]
+fn completes_option_map_with_function() {
+    let src = r"
+    struct Bar;
+    impl Bar {
+        fn bar_method(&self) {}
+    }
+    fn make_bar(_: u32) -> Bar {
+        Bar
+    }
+    fn main() {
+        let opt: Option<u32> = None;
+        opt.map(make_bar).unwrap().bar_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "bar_method");
+}
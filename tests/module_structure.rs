@@ -1,3 +1,4 @@
+use racer::{complete_from_file, Match};
 use racer_testutils::*;
 
 #[test]
@@ -55,3 +56,87 @@ fn finds_new_type_of_submodule() {
         assert!(got.filepath.ends_with("src/submod2018/foo2018.rs"));
     })
 }
+
+/// Completes at `~` in `lib.rs`, where `lib.rs` and `files` only exist in the cache
+fn get_completions_in_cache(src: &str, files: &[(&str, &str)]) -> Vec<Match> {
+    let dir = TmpDir::new();
+    let (point, clean_src) = get_pos_and_source(src);
+    let path = dir.path().join("lib.rs");
+    let cache = racer::FileCache::default();
+    let session = racer::Session::new(&cache, Some(path.as_path()));
+    session.cache_file_contents(path.clone(), clean_src);
+    for (name, contents) in files {
+        session.cache_file_contents(dir.path().join(name), *contents);
+    }
+    complete_from_file(&path, point, &session).collect()
+}
+
+#[test]
+fn completes_nested_module_only_in_cache() {
+    let src = "
+    mod foo;
+    fn main() {
+        foo::bar::bar_f~
+    }
+    ";
+    let got = get_completions_in_cache(
+        src,
+        &[
+            ("foo.rs", "pub mod bar;"),
+            ("foo/bar.rs", "pub fn bar_fn() {}"),
+            ("bar.rs", "pub fn bar_fake() {}"),
+        ],
+    );
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "bar_fn");
+}
+
+#[test]
+fn completes_module_declared_in_inline_module() {
+    let src = "
+    mod outer {
+        pub mod inner;
+    }
+    fn main() {
+        outer::inner::inner_f~
+    }
+    ";
+    let got = get_completions_in_cache(src, &[("outer/inner.rs", "pub fn inner_fn() {}")]);
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "inner_fn");
+}
+
+#[test]
+fn completes_path_attribute_relative_to_inline_module() {
+    let src = r#"
+    mod outer {
+        #[path = "renamed.rs"]
+        pub mod inner;
+    }
+    fn main() {
+        outer::inner::renamed_f~
+    }
+    "#;
+    let got = get_completions_in_cache(src, &[("outer/renamed.rs", "pub fn renamed_fn() {}")]);
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "renamed_fn");
+}
+
+#[test]
+fn completes_path_attribute_in_inline_module_of_non_mod_rs_file() {
+    let src = "
+    mod foo;
+    fn main() {
+        foo::a::b::x_f~
+    }
+    ";
+    let foo = r#"
+    pub mod a {
+        #[path = "x.rs"]
+        pub mod b;
+    }
+    "#;
+    let got = get_completions_in_cache(src, &[("foo.rs", foo), ("foo/a/x.rs", "pub fn x_fn() {}")]);
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "x_fn");
+}
@@ -0,0 +1,132 @@
+use racer::{complete_from_file, Edition, FileCache, Match, ProjectModelProvider, Session};
+use racer_testutils::*;
+use std::path::{Path, PathBuf};
+
+/// A project model which only knows the edition of the crate
+struct EditionModel(Edition);
+
+impl ProjectModelProvider for EditionModel {
+    fn edition(&self, _manifest: &Path) -> Option<Edition> {
+        Some(self.0)
+    }
+    fn discover_project_manifest(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.with_file_name("Cargo.toml"))
+    }
+    fn search_dependencies(
+        &self,
+        _manifest: &Path,
+        _search_fn: Box<dyn Fn(&str) -> bool>,
+    ) -> Vec<(String, PathBuf)> {
+        Vec::new()
+    }
+    fn resolve_dependency(&self, _manifest: &Path, _dep_name: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+fn get_all_completions_in_edition(src: &str, edition: Edition) -> Vec<Match> {
+    let dir = TmpDir::new();
+    let (completion_point, clean_src) = get_pos_and_source(src);
+    let path = dir.write_file("lib.rs", &clean_src);
+    let cache = FileCache::default();
+    let session = Session::with_project_model(&cache, Box::new(EditionModel(edition)));
+    complete_from_file(&path, completion_point, &session).collect()
+}
+
+#[test]
+fn completes_std_prelude_items() {
+    let src = "
+    fn main() {
+        let v: Ve~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "Vec"));
+}
+
+#[test]
+fn no_std_crate_uses_core_prelude() {
+    let src = "
+    #![no_std]
+    fn main() {
+        let v: Ve~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().all(|m| m.matchstr != "Vec"));
+}
+
+#[test]
+fn completes_core_prelude_items_in_no_std_crate() {
+    let src = "
+    #![no_std]
+    fn main() {
+        let o: Opti~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "Option"));
+}
+
+#[test]
+fn no_implicit_prelude_disables_prelude() {
+    let src = "
+    #![no_implicit_prelude]
+    fn main() {
+        let o: Opti~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().all(|m| m.matchstr != "Option"));
+}
+
+#[test]
+fn completes_alloc_items_in_no_std_crate() {
+    let src = "
+    #![no_std]
+    extern crate alloc;
+    fn main() {
+        let v: alloc::vec::Ve~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "Vec"));
+}
+
+#[test]
+fn completes_2021_prelude_items_in_2021_edition() {
+    let src = "
+    fn main() {
+        let t: TryFr~
+    }
+    ";
+    let got = get_all_completions_in_edition(src, Edition::Ed2021);
+    assert!(got.iter().any(|m| m.matchstr == "TryFrom"), "{:?}", got);
+    let got = get_all_completions_in_edition(src, Edition::Ed2018);
+    assert!(got.iter().all(|m| m.matchstr != "TryFrom"), "{:?}", got);
+}
+
+#[test]
+fn cfg_attr_no_std_uses_core_prelude() {
+    let src = "
+    //! A crate which can be built without std
+    #![cfg_attr(not(feature = \"std\"), no_std)]
+    fn main() {
+        let v: Ve~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().all(|m| m.matchstr != "Vec"));
+}
+
+#[test]
+fn disabled_cfg_attr_no_std_keeps_std_prelude() {
+    let src = "
+    #![cfg_attr(all(unix, windows), no_std)]
+    fn main() {
+        let v: Ve~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "Vec"));
+}
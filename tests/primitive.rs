@@ -76,6 +76,19 @@ fn completes_methods_for_char() {
     assert_eq!(got.matchstr, "is_uppercase");
 }
 
+#[test]
+fn completes_methods_for_bool() {
+    let src = r#"
+    fn main() {
+        let b = true;
+        b.then_so~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "then_some");
+}
+
 #[test]
 fn completes_slice_methods_for_array() {
     let src = r#"
@@ -116,3 +129,106 @@ fn completes_slice_methods_for_vec() {
     let got = get_only_completion(src, None);
     assert_eq!(got.matchstr, "split_first_mut");
 }
+
+#[test]
+fn completes_methods_for_cast_expr() {
+    let src = r#"
+    fn main() {
+        let a = 5u32;
+        (a as f64).sqr~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "sqrt");
+}
+
+#[test]
+fn completes_slice_methods_for_repeat_expr() {
+    let src = r#"
+    fn main() {
+        [0u8; 32].split_mu~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "split_mut");
+}
+
+#[test]
+fn completes_methods_for_range() {
+    let src = r#"
+    fn main() {
+        let n = 10usize;
+        (0..n).contai~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "contains");
+}
+
+#[test]
+fn completes_methods_for_range_inclusive() {
+    let src = r#"
+    fn main() {
+        (0..=5).into_inn~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "into_inner");
+}
+
+#[test]
+fn completes_element_type_of_range_from_let() {
+    let src = r#"
+    fn main() {
+        let r = 'a'..'z';
+        r.start.is_upperc~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "is_uppercase");
+}
+
+#[test]
+fn completes_element_type_of_range_from_non_literal_bound() {
+    let src = r#"
+    fn main() {
+        let n = 10usize;
+        let r = 0..n;
+        r.start.is_power_of_t~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "is_power_of_two");
+}
+
+#[test]
+fn completes_iterator_methods_for_mapped_range() {
+    let src = r#"
+    fn main() {
+        let n = 10usize;
+        (0..n).map(|i| i * 2).coll~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "collect");
+}
+
+#[test]
+fn completes_iterator_methods_for_reversed_range() {
+    let src = r#"
+    fn main() {
+        let n = 10usize;
+        (0..n).rev().step_b~
+    }
+    "#;
+
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "step_by");
+}
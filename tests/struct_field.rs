@@ -145,3 +145,40 @@ fn struct_field_scalar_primitive_types() {
         assert_eq!(completion.contextstr, expected);
     }
 }
+
+#[test]
+fn completes_field_of_nested_tuple_index() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    fn main() {
+        let t: (u8, (u8, Foo)) = unimplemented!();
+        t.1.1.foo_f~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
+
+#[test]
+fn completes_field_of_tuple_field_in_generic_struct() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    struct Pair<T> { pair: (u8, T) }
+    fn func(p: Pair<Foo>) {
+        p.pair.1.foo_f~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
+
+#[test]
+fn completes_field_of_nested_generic_struct() {
+    let src = "
+    struct Foo { foo_field: u32 }
+    struct Inner<U> { value: U }
+    struct Wrapper<T> { inner: Inner<T> }
+    fn func(w: Wrapper<Foo>) {
+        w.inner.value.foo_f~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
@@ -3880,3 +3880,22 @@ fn completes_crate_local_enum_variant() {
     ";
     assert_eq!(get_only_completion(src, None).matchstr, "Variant");
 }
+
+#[test]
+fn completes_method_annotated_with_attribute_macro() {
+    let src = r#"
+    struct Foo { foo_field: u32 }
+    struct S;
+    impl S {
+        #[doc = include_str!("get.md")]
+        #[tracing::instrument(skip(self), fields(kind = "get"))]
+        pub fn get(&self) -> Foo {
+            Foo { foo_field: 0 }
+        }
+    }
+    fn main() {
+        S.get().foo_f~
+    }
+    "#;
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_field");
+}
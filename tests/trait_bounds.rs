@@ -377,3 +377,406 @@ fn print_trait_object() {
     assert_eq!(got.matchstr, "0");
     assert_eq!(got.contextstr, "Box<dyn Object>");
 }
+
+#[test]
+fn completes_assoc_const_for_type_parameter() {
+    let src = "
+        fn main() {
+            trait Bounded {
+                const MAX_VALUE: Self;
+            }
+            fn func<T: Bounded>() {
+                T::MAX_V~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "MAX_VALUE");
+}
+
+#[test]
+fn completes_assoc_fn_for_type_parameter_by_where_clause() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn create() -> Self;
+            }
+            fn func<T>()
+            where
+                T: Trait,
+            {
+                T::cre~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "create");
+}
+
+#[test]
+fn completes_external_assoc_fn_for_type_parameter() {
+    let src = "
+        fn func<T: Default>() {
+            T::defa~
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "default");
+}
+
+#[test]
+fn completes_qualified_path_with_trait() {
+    let src = "
+        fn main() {
+            trait Trait {
+                const CONST_VALUE: usize;
+            }
+            fn func<T: Trait>() {
+                let _ = <T as Trait>::CONST_~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "CONST_VALUE");
+}
+
+#[test]
+fn completes_qualified_path_without_trait() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn create() -> Self;
+            }
+            fn func<T: Trait>() {
+                let _ = <T>::cre~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "create");
+}
+
+#[test]
+fn finds_definition_of_qualified_path() {
+    let src = "
+        trait Trait {
+            const CONST_VALUE: usize;
+        }
+        fn func<T: Trait>() {
+            let _ = <T as Trait>::CONST_V~ALUE;
+        }
+        ";
+    let got = get_definition(src, None);
+    assert_eq!(got.matchstr, "CONST_VALUE");
+}
+
+#[test]
+fn finds_definition_of_qualified_path_in_impl_of_self_type() {
+    let src = "
+        trait Trait {
+            const CONST_VALUE: usize;
+        }
+        struct Foo;
+        impl Trait for Foo {
+            const CONST_VALUE: usize = 1;
+        }
+        fn main() {
+            let _ = <Foo as Trait>::CONST_V~ALUE;
+        }
+        ";
+    let got = get_definition(src, None);
+    assert_eq!(got.matchstr, "CONST_VALUE");
+    assert_eq!(got.point.0, src.find("CONST_VALUE: usize = 1").unwrap());
+}
+
+#[test]
+fn completes_qualified_path_with_trait_of_other_type() {
+    let src = "
+        trait Trait {
+            type Output;
+        }
+        trait Other {
+            type Outcome;
+        }
+        struct Foo;
+        impl Trait for Foo {
+            type Output = u8;
+        }
+        impl Other for Foo {
+            type Outcome = u8;
+        }
+        fn main() {
+            let _: <Foo as Trait>::Out~
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "Output");
+}
+
+#[test]
+fn completes_methods_for_fnarg_by_impl_where_clause() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn method(&self);
+            }
+            struct Wrapper<T>(T);
+            impl<T> Wrapper<T>
+            where
+                T: Trait + Send,
+            {
+                fn func(&self, arg: T) {
+                    arg.meth~
+                }
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "method");
+}
+
+#[test]
+fn completes_methods_for_fnarg_by_trait_where_clause() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn method(&self);
+            }
+            trait Container<T>
+            where
+                T: Trait,
+            {
+                fn func(&self, arg: T) {
+                    arg.meth~
+                }
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "method");
+}
+
+#[test]
+fn completes_inherited_methods_by_where_self_clause() {
+    let src = "
+        fn main() {
+            trait Inherited {
+                fn inherited(&self);
+            }
+            trait Trait
+            where
+                Self: Inherited,
+            {
+                fn method(&self);
+            }
+            fn func<T: Trait>(arg: &T) {
+                arg.inheri~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "inherited");
+}
+
+#[test]
+fn completes_methods_for_assoc_type_by_where_clause() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn method(&self);
+            }
+            trait Container {
+                type Item;
+            }
+            fn func<C>(item: C::Item)
+            where
+                C: Container,
+                C::Item: Trait,
+            {
+                item.meth~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "method");
+}
+
+#[test]
+fn completes_assoc_type_constrained_by_equality() {
+    let src = "
+        fn main() {
+            struct Foo;
+            impl Foo {
+                fn foo_method(&self) {}
+            }
+            fn func<I>(item: I::Item)
+            where
+                I: Iterator<Item = Foo> + Send,
+            {
+                item.foo_m~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "foo_method");
+}
+
+#[test]
+fn completes_supertrait_methods_for_external_bound() {
+    let src = "
+        fn func<T: Ord>(arg: T) {
+            arg.partial_c~
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "partial_cmp");
+}
+
+#[test]
+fn completes_blanket_impl_methods_for_type_parameter() {
+    let src = "
+        fn main() {
+            trait Trait {
+                fn method(&self);
+            }
+            trait Ext {
+                fn ext_method(&self) {}
+            }
+            impl<T: Trait> Ext for T {}
+            fn func<T: Trait>(arg: T) {
+                arg.ext_m~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "ext_method");
+}
+
+#[test]
+fn completes_blanket_impl_methods_for_supertrait_bound() {
+    let src = "
+        fn main() {
+            trait Base {}
+            trait Trait: Base {}
+            trait Describe {
+                fn describe(&self);
+            }
+            impl<T: Base> Describe for T {
+                fn describe(&self) {}
+            }
+            fn func<T: Trait>(arg: T) {
+                arg.descr~
+            }
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "describe");
+}
+
+#[test]
+fn completes_blanket_impl_methods_for_struct() {
+    let src = "
+        fn main() {
+            trait Trait {}
+            trait Ext {
+                fn ext_method(&self) {}
+            }
+            impl<T: Trait> Ext for T {}
+            struct Foo;
+            impl Trait for Foo {}
+            let foo = Foo;
+            foo.ext_m~
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "ext_method");
+}
+
+#[test]
+fn completes_into_iter_for_iterator_bound() {
+    let src = "
+        fn func<I: Iterator>(iter: I) {
+            iter.into_it~
+        }
+        ";
+    assert_eq!(get_only_completion(src, None).matchstr, "into_iter");
+}
+
+#[test]
+fn completes_blanket_impl_methods_in_crate_root() {
+    let src = "
+        use crate::traits::Base;
+        use crate::Ext;
+        fn func<T: Base>(arg: T) {
+            arg.ext_m~
+        }
+        ";
+    let dir = TmpDir::new();
+    let _lib = dir.write_file(
+        "lib.rs",
+        "
+        mod traits;
+        mod user;
+        pub trait Ext {
+            fn ext_method(&self) {}
+        }
+        impl<T: traits::Base> Ext for T {}
+        ",
+    );
+    let _traits = dir.write_file("traits.rs", "pub trait Base {}");
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "ext_method");
+}
+
+#[test]
+fn completes_blanket_impl_methods_in_other_module() {
+    let src = "
+        use crate::ext::blanket::Ext;
+        use crate::traits::Base;
+        fn func<T: Base>(arg: T) {
+            arg.ext_m~
+        }
+        ";
+    let dir = TmpDir::new();
+    let _lib = dir.write_file(
+        "lib.rs",
+        "
+        mod traits;
+        mod ext;
+        mod user;
+        ",
+    );
+    let _traits = dir.write_file("traits.rs", "pub trait Base {}");
+    let _ext = dir.write_file(
+        "ext.rs",
+        "
+        pub mod blanket {
+            use crate::traits::Base;
+            pub trait Ext {
+                fn ext_method(&self) {}
+            }
+            impl<T: Base> Ext for T {}
+        }
+        ",
+    );
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "ext_method");
+}
+
+#[test]
+fn doesnt_complete_blanket_impl_methods_of_trait_not_in_scope() {
+    let src = "
+        use crate::traits::Base;
+        fn func<T: Base>(arg: T) {
+            arg.ext_m~
+        }
+        ";
+    let dir = TmpDir::new();
+    let _lib = dir.write_file(
+        "lib.rs",
+        "
+        mod traits;
+        mod ext;
+        mod user;
+        ",
+    );
+    let _traits = dir.write_file("traits.rs", "pub trait Base {}");
+    let _ext = dir.write_file(
+        "ext.rs",
+        "
+        pub trait Ext {
+            fn ext_method(&self) {}
+        }
+        impl<T: crate::traits::Base> Ext for T {}
+        ",
+    );
+    let got = get_all_completions_with_name(src, Some(dir), "user.rs");
+    assert!(got.is_empty(), "{:?}", got);
+}
@@ -0,0 +1,95 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_methods_of_ref_trait_object() {
+    let src = "
+    trait Shape {
+        fn area(&self) -> f64;
+    }
+    fn func(shape: &dyn Shape) {
+        shape.ar~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "area");
+}
+
+#[test]
+fn completes_supertrait_methods_of_boxed_trait_object() {
+    let src = "
+    trait Base {
+        fn base_method(&self);
+    }
+    trait Shape: Base {
+        fn area(&self) -> f64;
+    }
+    fn func(shape: Box<dyn Shape>) {
+        shape.base_m~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "base_method");
+}
+
+#[test]
+fn completes_inherent_methods_of_trait_object() {
+    let src = "
+    trait Shape {
+        fn area(&self) -> f64;
+    }
+    impl dyn Shape {
+        fn describe(&self) {}
+    }
+    fn func(shape: &dyn Shape) {
+        shape.descr~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "describe");
+}
+
+#[test]
+fn doesnt_complete_inherent_methods_of_trait_object_for_bounds() {
+    let src = "
+    trait Shape {
+        fn area(&self) -> f64;
+    }
+    impl dyn Shape {
+        fn describe(&self) {}
+    }
+    fn func<T: Shape>(shape: T) {
+        shape.descr~
+    }
+    ";
+    assert!(get_all_completions(src, None).is_empty());
+}
+
+#[test]
+fn completes_downcast_ref_of_dyn_any() {
+    let src = "
+    use std::any::Any;
+    fn func(value: &dyn Any) {
+        value.downcast_r~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "downcast_ref");
+}
+
+#[test]
+fn completes_methods_of_boxed_dyn_error() {
+    let src = "
+    use std::error::Error;
+    fn func(err: Box<dyn Error + Send + Sync>) {
+        err.sour~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "source");
+}
+
+#[test]
+fn completes_supertrait_methods_of_arc_dyn_fn() {
+    let src = "
+    use std::sync::Arc;
+    fn func(callback: Arc<dyn Fn(u32) -> u32 + Send>) {
+        callback.call_o~
+    }
+    ";
+    assert_eq!(get_only_completion(src, None).matchstr, "call_once");
+}
@@ -0,0 +1,103 @@
+use racer_testutils::*;
+
+#[test]
+fn completes_deref_of_user_defined_smart_pointer() {
+    let src = r"
+    use std::ops::Deref;
+    struct Inner;
+    impl Inner {
+        fn inner_method(&self) {}
+    }
+    struct Wrapper(Inner);
+    impl Deref for Wrapper {
+        type Target = Inner;
+        fn deref(&self) -> &Inner {
+            &self.0
+        }
+    }
+    fn main() {
+        let w = Wrapper(Inner);
+        let i = *w;
+        i.inner_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "inner_method");
+}
+
+#[test]
+fn completes_deref_of_reference() {
+    let src = r"
+    struct Foo;
+    impl Foo {
+        fn foo_method(&self) {}
+    }
+    fn main() {
+        let f = &Foo;
+        let g = *f;
+        g.foo_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "foo_method");
+}
+
+#[test]
+fn completes_neg_output() {
+    let src = r"
+    use std::ops::Neg;
+    struct Point;
+    struct Negated;
+    impl Negated {
+        fn negated_method(&self) {}
+    }
+    impl Neg for Point {
+        type Output = Negated;
+        fn neg(self) -> Negated {
+            Negated
+        }
+    }
+    fn main() {
+        let n = -Point;
+        n.negated_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "negated_method");
+}
+
+#[test]
+fn completes_not_output() {
+    let src = r"
+    use std::ops::Not;
+    struct Flags;
+    struct Inverted;
+    impl Inverted {
+        fn inverted_method(&self) {}
+    }
+    impl Not for Flags {
+        type Output = Inverted;
+        fn not(self) -> Inverted {
+            Inverted
+        }
+    }
+    fn main() {
+        let n = !Flags;
+        n.inverted_m~
+    }
+";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "inverted_method");
+}
+
+#[test]
+fn completes_methods_for_neg_of_primitive() {
+    let src = r"
+    fn main() {
+        let a = -5i32;
+        a.pow~
+    }
+";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|ma| ma.matchstr == "pow"));
+}
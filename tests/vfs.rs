@@ -0,0 +1,142 @@
+use racer::{complete_from_file, find_definition, BytePos, FileCache, FileLoader, Match, Session};
+use racer_testutils::*;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file tree which only exists in memory
+struct VirtualTree {
+    files: HashMap<PathBuf, String>,
+}
+
+impl VirtualTree {
+    fn new(files: &[(&str, &str)]) -> Self {
+        let files = files
+            .iter()
+            .map(|(path, contents)| (PathBuf::from(path), contents.to_string()))
+            .collect();
+        VirtualTree { files }
+    }
+}
+
+impl FileLoader for VirtualTree {
+    fn load_file(&self, path: &Path) -> io::Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not in the virtual tree"))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut entries = Vec::new();
+        for file in self.files.keys() {
+            if let Some(first) = file
+                .strip_prefix(path)
+                .ok()
+                .and_then(|rest| rest.components().next())
+            {
+                let entry = path.join(first);
+                if !entries.contains(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+const LIB: &str = "/virtual/project/src/lib.rs";
+
+/// Runs `f` with a session over the virtual tree made of `files` and `src` at `LIB`
+fn with_virtual_tree<F, T>(src: &str, files: &[(&str, &str)], f: F) -> T
+where
+    F: FnOnce(&Session<'_>, BytePos) -> T,
+{
+    let (point, clean_src) = get_pos_and_source(src);
+    let mut files = files.to_vec();
+    files.push((LIB, clean_src.as_str()));
+    let cache = FileCache::new(VirtualTree::new(&files));
+    let session = Session::new(&cache, Some(Path::new(LIB)));
+    f(&session, point)
+}
+
+#[test]
+fn completes_in_module_files_of_virtual_tree() {
+    let src = "
+    mod foo;
+    fn main() {
+        foo::bar::bar_f~
+    }
+    ";
+    let got: Vec<Match> = with_virtual_tree(
+        src,
+        &[
+            ("/virtual/project/src/foo/mod.rs", "pub mod bar;"),
+            ("/virtual/project/src/foo/bar.rs", "pub fn bar_fn() {}"),
+        ],
+        |session, point| complete_from_file(LIB, point, session).collect(),
+    );
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "bar_fn");
+    assert_eq!(
+        got[0].filepath,
+        Path::new("/virtual/project/src/foo/bar.rs")
+    );
+}
+
+#[test]
+fn finds_module_of_virtual_tree() {
+    let src = "
+    mod foo;
+    use fo~o::Foo;
+    ";
+    let got = with_virtual_tree(
+        src,
+        &[("/virtual/project/src/foo.rs", "pub struct Foo;")],
+        |session, point| find_definition(LIB, point, session),
+    )
+    .unwrap();
+    assert_eq!(got.matchstr, "foo");
+    assert_eq!(got.filepath, Path::new("/virtual/project/src/foo.rs"));
+}
+
+#[test]
+fn discovers_manifest_of_virtual_tree() {
+    let src = "
+    mod foo;
+    fn main() {
+        foo::foo_f~
+    }
+    ";
+    let manifest = "/virtual/project/Cargo.toml";
+    let (got, discovered) = with_virtual_tree(
+        src,
+        &[
+            (
+                manifest,
+                "[package]\nname = \"project\"\nversion = \"0.1.0\"\n",
+            ),
+            ("/virtual/project/src/foo.rs", "pub fn foo_fn() {}"),
+        ],
+        |session, point| {
+            let got: Vec<Match> = complete_from_file(LIB, point, session).collect();
+            let discovered = session
+                .project_model
+                .discover_project_manifest(Path::new(LIB));
+            (got, discovered)
+        },
+    );
+    assert_eq!(discovered, Some(PathBuf::from(manifest)));
+    assert_eq!(got.len(), 1, "{:?}", got);
+    assert_eq!(got[0].matchstr, "foo_fn");
+}
@@ -0,0 +1,160 @@
+use racer::{MatchType, Visibility};
+use racer_testutils::*;
+
+fn completion_names(src: &str) -> Vec<String> {
+    let mut names: Vec<_> = get_all_completions(src, None)
+        .into_iter()
+        .map(|m| m.matchstr)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn hides_private_items_of_other_module() {
+    let src = "
+    mod other {
+        fn vis_private() {}
+        pub fn vis_public() {}
+        pub(crate) fn vis_crate() {}
+    }
+    fn main() {
+        other::vis_~
+    }
+    ";
+    assert_eq!(completion_names(src), vec!["vis_crate", "vis_public"]);
+}
+
+#[test]
+fn completes_private_items_of_ancestor_module() {
+    let src = "
+    mod outer {
+        fn vis_private() {}
+        mod inner {
+            fn f() {
+                super::vis_~
+            }
+        }
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "vis_private");
+    assert_eq!(got.visibility, Visibility::Private);
+}
+
+#[test]
+fn completes_super_restricted_items_in_parent_module() {
+    let src = "
+    mod a {
+        pub mod b {
+            pub(super) fn vis_super() {}
+            fn vis_private() {}
+        }
+        fn f() {
+            b::vis_~
+        }
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "vis_super");
+    assert_eq!(got.visibility, Visibility::Super);
+}
+
+#[test]
+fn hides_path_restricted_items_outside_of_path() {
+    let src = "
+    mod a {
+        pub mod b {
+            pub(in crate::a) fn vis_in_a() {}
+        }
+        fn f() {
+            b::vis_~
+        }
+    }
+    ";
+    assert_eq!(completion_names(src), vec!["vis_in_a"]);
+
+    let src = "
+    mod a {
+        pub mod b {
+            pub(in crate::a) fn vis_in_a() {}
+        }
+    }
+    fn main() {
+        a::b::vis_~
+    }
+    ";
+    assert!(completion_names(src).is_empty());
+}
+
+#[test]
+fn hides_private_fields_of_struct_in_other_module() {
+    let src = "
+    mod m {
+        pub struct S {
+            pub f_public: u8,
+            f_private: u8,
+            pub(crate) f_crate: u8,
+        }
+    }
+    fn main(s: m::S) {
+        s.f_~
+    }
+    ";
+    assert_eq!(completion_names(src), vec!["f_crate", "f_public"]);
+
+    let src = "
+    mod m {
+        pub struct S {
+            pub f_public: u8,
+            f_private: u8,
+        }
+    }
+    fn main() {
+        let s = m::S { f_~ };
+    }
+    ";
+    assert_eq!(completion_names(src), vec!["f_public"]);
+}
+
+#[test]
+fn completes_methods_of_trait_impl_in_other_module() {
+    let src = "
+    mod m {
+        pub struct S;
+        pub trait Tr {
+            fn tr_method(&self);
+        }
+        impl Tr for S {
+            fn tr_method(&self) {}
+        }
+        impl S {
+            fn tr_private(&self) {}
+        }
+    }
+    use m::Tr;
+    fn main(s: m::S) {
+        s.tr_~
+    }
+    ";
+    let got = get_only_completion(src, None);
+    assert_eq!(got.matchstr, "tr_method");
+    assert_eq!(got.visibility, Visibility::Public);
+}
+
+#[test]
+fn hides_private_fields_of_other_crate() {
+    let src = "
+    fn main() {
+        let v: Vec<u8> = Vec::new();
+        v.le~
+    }
+    ";
+    let got = get_all_completions(src, None);
+    assert!(got.iter().any(|m| m.matchstr == "len"), "{:?}", got);
+    assert!(
+        got.iter().all(|m| m.mtype != MatchType::StructField),
+        "{:?}",
+        got
+    );
+}